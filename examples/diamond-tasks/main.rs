@@ -2,9 +2,7 @@ extern crate raptors;
 extern crate uuid;
 
 use opentelemetry::global;
-use tokio::io::Result;
 use tracing::info;
-use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt};
 //
 // use tracing::instrument;
@@ -13,8 +11,6 @@ use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt};
 //
 // use tracing::{info, span, Level};
 
-use std::{thread, time};
-
 use raptors::prelude::*;
 
 #[tokio::main]
@@ -38,11 +34,12 @@ async fn main() {
     };
 
     info!("================ Running raptors::diamond-tasks example ================");
-    let mut system = build_mock_system!("Raptors");
-    // system.init();
-
-    let msg = build_loadfree_msg!("spawn", "mock", 3);
-    system.issue_order(RaptorMessage::LoadfreeMSG(msg)).await;
+    let mut system = build_mock_system!("Raptors", 3).unwrap();
+    info!(
+        "actor system {} with {} actors",
+        system.name(),
+        system.ranks()
+    );
 
     // // deliver msg to first idle
     system.issue_order(build_msg!("add-op")).await;
@@ -258,8 +255,7 @@ async fn main() {
     // // TODO we need msg builder
     // let cmd = build_msg!("destroy-all");
     // syst.on_receive(cmd);
-    thread::sleep(time::Duration::from_millis((5000) as u64));
-    // let halt_all = build_msg!("halt-all");
-    // system.issue_order(halt_all).await;
-    ()
+    let summary = system.shutdown().await.unwrap();
+    info!("================ Finished raptors::diamond-tasks example ================");
+    info!("{:?}", summary);
 }
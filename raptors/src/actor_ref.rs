@@ -0,0 +1,151 @@
+// LICENSE PLACEHOLDER
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+use tracing::info;
+
+use crate::cost_model::OpCodeLike;
+use crate::errors::RaptorsError;
+use crate::messages::{PayloadMessage, RaptorMessage, SystemCommand};
+use crate::tensor_types::TensorLike;
+
+/// Definition: ActorRef addresses one named actor of a system, workloads
+/// told to it go straight to its mailbox whether it is busy or not.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut system = build_mock_system!("Raptors").unwrap();
+///     system.spawn_named("mock", "resident").await.unwrap();
+///     let actor = system.lookup("resident").unwrap();
+///     assert_eq!(actor.name(), "resident");
+///     let out = actor
+///         .ask_unary(MockOpCode::ExpOp, MockTensor::new(MockOpCode::ExpOp))
+///         .await;
+///     assert!(out.is_ok());
+///     system.shutdown().await.unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ActorRef<U, O>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    id: usize,
+    name: String,
+    system_cmd_sendbox: mpsc::Sender<RaptorMessage<U, O>>,
+    ask_timeout: Option<Duration>,
+}
+
+impl<U, O> ActorRef<U, O>
+where
+    U: 'static + TensorLike + Clone + Send + Sync + Debug,
+    O: 'static + OpCodeLike + Debug + Send + Sync,
+{
+    pub(crate) fn new(
+        id: usize,
+        name: &str,
+        system_cmd_sendbox: mpsc::Sender<RaptorMessage<U, O>>,
+        ask_timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            system_cmd_sendbox,
+            ask_timeout,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// enqueue the workload to this actor without waiting for its result
+    pub async fn tell(&self, msg: PayloadMessage<U, O>) {
+        send_order(
+            &self.system_cmd_sendbox,
+            RaptorMessage::PinnedMSG(self.id, msg),
+        )
+        .await;
+    }
+
+    /// receive the msgs published to the topic until the actor is halted
+    pub async fn subscribe(&self, topic: &str) {
+        let cmd = SystemCommand::Subscribe(topic.to_string(), self.id);
+        send_order(
+            &self.system_cmd_sendbox,
+            RaptorMessage::LoadfreeMSG(cmd.into()),
+        )
+        .await;
+    }
+
+    pub async fn unsubscribe(&self, topic: &str) {
+        let cmd = SystemCommand::Unsubscribe(topic.to_string(), self.id);
+        send_order(
+            &self.system_cmd_sendbox,
+            RaptorMessage::LoadfreeMSG(cmd.into()),
+        )
+        .await;
+    }
+
+    /// compute the op on the tensor with this actor and wait for the result
+    pub async fn ask_unary(&self, op: O, inp: U) -> Result<U, RaptorsError> {
+        let (respond_to, reply) = oneshot::channel();
+        let msg = PayloadMessage::UnaryComputeFunctorMsg {
+            op,
+            inp: Arc::new(RwLock::new(inp)),
+            respond_to,
+        };
+        self.tell(msg).await;
+        wait_reply(reply, self.ask_timeout).await
+    }
+
+    /// compute the op on the two tensors with this actor and wait for the result
+    pub async fn ask_binary(&self, op: O, lhs: U, rhs: U) -> Result<U, RaptorsError> {
+        let (respond_to, reply) = oneshot::channel();
+        let msg = PayloadMessage::ComputeFunctorMsg {
+            op,
+            lhs: Arc::new(RwLock::new(lhs)),
+            rhs: Arc::new(RwLock::new(rhs)),
+            respond_to,
+        };
+        self.tell(msg).await;
+        wait_reply(reply, self.ask_timeout).await
+    }
+}
+
+pub(crate) async fn send_order<U, O>(
+    sendbox: &mpsc::Sender<RaptorMessage<U, O>>,
+    msg: RaptorMessage<U, O>,
+) where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    if sendbox.send(msg).await.is_err() {
+        info!("::actor-system-handler::system stopped, msg dropped");
+    }
+}
+
+// a workload dropped by the system or its actor drops the sender too
+pub(crate) async fn wait_reply<U>(
+    reply: oneshot::Receiver<Result<U, RaptorsError>>,
+    timeout: Option<Duration>,
+) -> Result<U, RaptorsError> {
+    let dropped = |_| RaptorsError::system("workload dropped before responding");
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, reply)
+            .await
+            .map_err(|_| RaptorsError::system(format!("no response within {:?}", timeout)))?
+            .map_err(dropped)?,
+        None => reply.await.map_err(dropped)?,
+    }
+}
@@ -0,0 +1,189 @@
+// LICENSE PLACEHOLDER
+use std::collections::{BTreeMap, HashMap};
+
+use crate::errors::RaptorsError;
+use crate::mailbox::{Address, Envelope, MailSender, Prioritized};
+
+/// Definition: ActorRegistry keeps the mailbox of every live actor, keyed by
+/// the actor address; the id of an actor resolves to its current address.
+///
+/// Halted actors are removed without touching the ids of the others, and a
+/// restarted actor takes over the id of its predecessor with a new address.
+///
+/// ```
+/// use raptors::mailbox;
+/// use raptors::prelude::*;
+///
+/// let mut actors = ActorRegistry::<RaptorMessage<MockTensor, MockOpCode>>::new();
+/// let (sender, _receiver) = mailbox::channel(4, OverflowPolicy::Block);
+/// let address = Address::new(uuid::Uuid::new_v4());
+/// actors.register(3, address.clone(), sender);
+/// assert_eq!(actors.resolve(&address), Some(3));
+/// assert_eq!(actors.address_of(3), Some(&address));
+/// assert!(actors.deregister(3).is_some());
+/// assert!(actors.is_empty());
+/// ```
+#[derive(Debug)]
+pub struct ActorRegistry<M> {
+    ids: HashMap<Address, usize>,
+    mails: BTreeMap<usize, (Address, MailSender<M>)>,
+}
+
+impl<M> Default for ActorRegistry<M> {
+    fn default() -> Self {
+        Self {
+            ids: HashMap::new(),
+            mails: BTreeMap::new(),
+        }
+    }
+}
+
+impl<M> ActorRegistry<M> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// register the mailbox of the actor, replacing the former actor of the id
+    pub fn register(&mut self, id: usize, address: Address, mail: MailSender<M>) {
+        if let Some((former, _)) = self.mails.insert(id, (address.clone(), mail)) {
+            self.ids.remove(&former);
+        }
+        self.ids.insert(address, id);
+    }
+
+    /// forget the actor, dropping its mailbox lets the actor exit once it has
+    /// handled the msgs queued already
+    pub fn deregister(&mut self, id: usize) -> Option<Address> {
+        let (address, _) = self.mails.remove(&id)?;
+        self.ids.remove(&address);
+        Some(address)
+    }
+
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.mails.clear();
+    }
+
+    pub fn resolve(&self, address: &Address) -> Option<usize> {
+        self.ids.get(address).copied()
+    }
+
+    pub fn address_of(&self, id: usize) -> Option<&Address> {
+        self.mails.get(&id).map(|(address, _)| address)
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.mails.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.mails.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mails.is_empty()
+    }
+
+    /// ids of the registered actors whose mailbox is still open
+    pub fn live_ids(&self) -> Vec<usize> {
+        self.mails
+            .iter()
+            .filter(|(_, (_, mail))| !mail.is_closed())
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    pub fn mails(&self) -> impl Iterator<Item = &MailSender<M>> {
+        self.mails.values().map(|(_, mail)| mail)
+    }
+}
+
+impl<M: Prioritized> ActorRegistry<M> {
+    /// deliver the msg of the envelope to the mailbox of its receiver, returns
+    /// the msg the mailbox displaced to make room if any
+    pub async fn send(&self, envelope: Envelope<M>) -> Result<Option<M>, RaptorsError> {
+        self.deliver(envelope).await.map_err(|(e, _)| e)
+    }
+
+    /// as `send`, a msg that is not delivered is handed back with the error
+    pub async fn deliver(&self, envelope: Envelope<M>) -> Result<Option<M>, (RaptorsError, M)> {
+        let id = match self.resolve(&envelope.receiver) {
+            Some(id) => id,
+            None => {
+                let e = RaptorsError::mailbox(format!(
+                    "no actor at address {}",
+                    envelope.receiver.into_aid()
+                ));
+                return Err((e, envelope.msg));
+            }
+        };
+        match self.mails.get(&id) {
+            Some((_, mail)) => mail.deliver(envelope.msg).await,
+            None => Err((RaptorsError::actor(id, "mailbox is gone"), envelope.msg)),
+        }
+    }
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailbox::{self, OverflowPolicy};
+    use crate::prelude::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn actor_registry_routes_by_address_test() {
+        let mut actors = ActorRegistry::<LoadfreeMessage<MockTensor>>::new();
+        let mut receivers = vec![];
+        let mut addresses = vec![];
+        for id in 0..3 {
+            let (sender, receiver) = mailbox::channel(4, OverflowPolicy::Block);
+            let address = Address::new(Uuid::new_v4());
+            actors.register(id, address.clone(), sender);
+            receivers.push(receiver);
+            addresses.push(address);
+        }
+        // halting #1 leaves #2 where it is
+        assert_eq!(actors.deregister(1), Some(addresses[1].clone()));
+        assert_eq!(actors.resolve(&addresses[2]), Some(2));
+        assert_eq!(actors.live_ids(), vec![0, 2]);
+
+        let system = Address::new(Uuid::new_v4());
+        let envelope = Envelope::new(
+            system.clone(),
+            addresses[2].clone(),
+            build_loadfree_msg!("add-op"),
+        );
+        assert_eq!(actors.send(envelope).await, Ok(None));
+        assert_eq!(
+            receivers[2].recv().await,
+            Some(build_loadfree_msg!("add-op"))
+        );
+
+        let envelope = Envelope::new(
+            system.clone(),
+            addresses[1].clone(),
+            build_loadfree_msg!("add-op"),
+        );
+        assert!(actors.send(envelope).await.is_err());
+        assert_eq!(receivers[1].recv().await, None);
+        // the msg that is not delivered is handed back
+        let envelope = Envelope::new(system, addresses[1].clone(), build_loadfree_msg!("exp-op"));
+        let refused = actors.deliver(envelope).await.err().map(|(_, msg)| msg);
+        assert_eq!(refused, Some(build_loadfree_msg!("exp-op")));
+    }
+
+    #[test]
+    fn actor_registry_replaces_restarted_actors_test() {
+        let mut actors = ActorRegistry::<LoadfreeMessage<MockTensor>>::new();
+        let (sender, _receiver) = mailbox::channel(4, OverflowPolicy::Block);
+        let former = Address::new(Uuid::new_v4());
+        actors.register(0, former.clone(), sender.clone());
+        let restarted = Address::new(Uuid::new_v4());
+        actors.register(0, restarted.clone(), sender);
+        assert_eq!(actors.resolve(&former), None);
+        assert_eq!(actors.resolve(&restarted), Some(0));
+        assert_eq!(actors.len(), 1);
+    }
+}
@@ -1,21 +1,94 @@
 // LICENSE PLACEHOLDER
-use std::sync::{Arc, RwLock};
-use tracing::{debug, info};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::Instant;
+use tracing::info;
 // use tracing::instrument;
 // use tracing::{span, Level};
-use tokio::sync::mpsc::error::TryRecvError;
 use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
 use std::cmp::Ordering;
 use std::fmt::Debug;
 
+use crate::blocking_pool::BlockingPool;
 use crate::build_loadfree_msg;
+use crate::cost_learner::CostLearner;
 use crate::cost_model::OpCodeLike;
+use crate::errors::RaptorsError;
 use crate::executor_types::ExecutorLike;
-use crate::messages::{ActorCommand, LoadfreeMessage, PayloadMessage, RaptorMessage};
+use crate::mailbox::MailReceiver;
+use crate::messages::{ActorCommand, LoadfreeMessage, PayloadMessage, RaptorMessage, ReadySignal};
 use crate::tensor_types::TensorLike;
 
+pub type InflightSlot<U, O> = Arc<Mutex<Option<RaptorMessage<U, O>>>>;
+
+// the slot stays usable after a panic while it was locked, the parked msg is
+// exactly what the supervisor wants to recover
+pub(crate) fn lock_slot<U, O>(
+    slot: &InflightSlot<U, O>,
+) -> MutexGuard<'_, Option<RaptorMessage<U, O>>>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    slot.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// wait for the inputs of a NonRet*ComputeFunctorMsg, each checker is re-armed
+// with the signal it resolved to so the msg stays replayable
+async fn resolve_ready<U, O>(id: usize, msg: &mut RaptorMessage<U, O>) -> ReadySignal
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    match msg {
+        RaptorMessage::PayloadMSG(PayloadMessage::NonRetUnaryComputeFunctorMsg {
+            inp_ready_checker,
+            respond_id,
+            ..
+        }) => {
+            info!("::actors#{}::inp-ready-checker checking", id);
+            let inp_ready = rearm_ready(id, inp_ready_checker, *respond_id).await;
+            info!("::actors#{}::inp-ready-checker ready", id);
+            inp_ready
+        }
+        RaptorMessage::PayloadMSG(PayloadMessage::NonRetBinaryComputeFunctorMsg {
+            lhs_ready_checker,
+            rhs_ready_checker,
+            respond_id,
+            ..
+        }) => {
+            info!("::actors#{}::lhs-ready-checker checking", id);
+            let lhs_ready = rearm_ready(id, lhs_ready_checker, *respond_id).await;
+            info!("::actors#{}::lhs-ready-checker ready", id);
+
+            info!("::actors#{}::rhs-ready-checker checking", id);
+            let rhs_ready = rearm_ready(id, rhs_ready_checker, *respond_id).await;
+            info!("::actors#{}::rhs-ready-checker ready", id);
+            lhs_ready.and(rhs_ready)
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn rearm_ready(
+    id: usize,
+    checker: &mut oneshot::Receiver<ReadySignal>,
+    respond_id: usize,
+) -> ReadySignal {
+    // an input whose producer is gone without a signal can never be ready
+    let signal = (&mut *checker).await.unwrap_or_else(|_| {
+        Err(RaptorsError::actor(
+            id,
+            format!("input of var #{} dropped before being ready", respond_id),
+        ))
+    });
+    let (rearmed_tx, rearmed_rx) = oneshot::channel();
+    let _ = rearmed_tx.send(signal.clone());
+    *checker = rearmed_rx;
+    signal
+}
+
 // T: executor type
 // U: Tensor type
 // O: OpCode type
@@ -27,34 +100,66 @@ where
     O: OpCodeLike + Debug,
 {
     id: usize,
+    // bumped by every restart under the same id, it tags the acks
+    generation: usize,
     uuid: Uuid,
-    receiver: mpsc::Receiver<RaptorMessage<U, O>>,
+    receiver: MailReceiver<RaptorMessage<U, O>>,
     respond_to: mpsc::Sender<RaptorMessage<U, O>>,
-    executor: T,
+    // lent to the blocking pool while computing, lost if the computation panics
+    kernel: Option<Kernel<T, O>>,
+    pool: BlockingPool,
+    inflight: InflightSlot<U, O>,
 }
 
 impl<T, U, O> Actor<T, U, O>
 where
-    T: ExecutorLike<TensorType = U, OpCodeType = O>,
-    U: TensorLike + Clone + Debug,
-    O: OpCodeLike + Debug,
+    T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send,
+    U: 'static + TensorLike + Clone + Debug + Send + Sync,
+    O: 'static + OpCodeLike + Debug + Send,
 {
     pub fn new(
         id: usize,
-        receiver: mpsc::Receiver<RaptorMessage<U, O>>,
+        receiver: MailReceiver<RaptorMessage<U, O>>,
         respond_to: mpsc::Sender<RaptorMessage<U, O>>,
         // WIP executor's typeid
         typeid: usize,
+    ) -> Self {
+        Self::new_with_executor(id, receiver, respond_to, T::new_with_typeid(typeid))
+    }
+
+    pub fn new_with_executor(
+        id: usize,
+        receiver: MailReceiver<RaptorMessage<U, O>>,
+        respond_to: mpsc::Sender<RaptorMessage<U, O>>,
+        mut executor: T,
     ) -> Self {
         let new_uuid = Uuid::new_v4();
-        let mut exec = T::new_with_typeid(typeid);
-        exec.init();
+        executor.init();
         Actor {
-            id: id,
-            receiver: receiver,
+            id,
+            generation: 0,
+            receiver,
             uuid: new_uuid,
-            respond_to: respond_to,
-            executor: exec,
+            respond_to,
+            kernel: Some(Kernel {
+                id,
+                executor,
+                learner: None,
+            }),
+            pool: BlockingPool::new(1),
+            inflight: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    // actors of a system share its pool, a lone actor has a slot of its own
+    pub fn set_blocking_pool(&mut self, pool: BlockingPool) {
+        self.pool = pool;
+    }
+
+    /// time each executor call and report it to the learner
+    pub fn set_cost_learner(&mut self, learner: CostLearner<O>) {
+        if let Some(kernel) = self.kernel.as_mut() {
+            kernel.learner = Some(learner);
         }
     }
 
@@ -62,168 +167,254 @@ where
         self.id
     }
 
+    // the supervisor restarts an actor under the id of the one it replaces
+    pub fn set_generation(&mut self, generation: usize) {
+        self.generation = generation;
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
     pub fn uuid(&self) -> Uuid {
         self.uuid
     }
 
-    async fn fetch_and_handle(&mut self, msg: RaptorMessage<U, O>) -> Result<(), String> {
-        match msg {
-            RaptorMessage::LoadfreeMSG(_msg) => self.fetch_and_handle_message(_msg).await,
-            RaptorMessage::PayloadMSG(_msg) => self.fetch_and_handle_payload(_msg).await,
-        }
+    /// number of msgs waiting in the mailbox
+    pub fn mailbox_depth(&self) -> usize {
+        self.receiver.depth()
     }
 
-    async fn fetch_and_handle_payload(&mut self, msg: PayloadMessage<U, O>) -> Result<(), String> {
-        match msg {
-            PayloadMessage::NonRetBinaryComputeFunctorMsg {
-                op,
-                lhs,
-                rhs,
-                out,
-                lhs_ready_checker,
-                rhs_ready_checker,
-                respond_to,
-                respond_id,
-            } => {
-                info!("::actors#{}::lhs-ready-checker checking", self.id);
-                lhs_ready_checker.await;
-                info!("::actors#{}::lhs-ready-checker ready", self.id);
-
-                info!("::actors#{}::rhs-ready-checker checking", self.id);
-                rhs_ready_checker.await;
-                info!("::actors#{}::rhs-ready-checker ready", self.id);
-
-                info!("::actor#{}::enter-computation", self.id);
-                let if_compute_successed = self.on_binary_compute_v2(op, lhs, rhs, out);
-                // WIP let outs = self.on_unary_compute(op, inp).expect("compute failed");
-                info!("::actor#{}::exit-computation", self.id);
-                // respond_to.into_iter().map(|x| x.send(0u8) );
-                respond_to
-                    .into_iter()
-                    .map(|x| {
-                        info!(
-                            "::actors#{}::out-ready-checker set-ready to var #{}",
-                            self.id, respond_id
-                        );
-                        x.send(0u8);
-                        ()
-                    })
-                    .collect::<()>();
-                Ok(())
-            }
-            // WIP add non-ret unary msg, just return a u8 signal that receives
-            // TODO maybe send a () is better
-            PayloadMessage::NonRetUnaryComputeFunctorMsg {
-                op,
-                inp,
-                out,
-                inp_ready_checker,
-                respond_to,
-                respond_id,
-            } => {
-                // TODO need unary branch
-                info!("::actors#{}::inp-ready-checker checking", self.id);
-                inp_ready_checker.await;
-                info!("::actors#{}::inp-ready-checker ready", self.id);
-                info!("::actor#{}::enter-computation", self.id);
-                let if_compute_successed = self.on_unary_compute_v2(op, inp, out);
-                // WIP let outs = self.on_unary_compute(op, inp).expect("compute failed");
-                info!("::actor#{}::exit-computation", self.id);
-                // respond_to.into_iter().map(|x| x.send(0u8) );
-                respond_to
-                    .into_iter()
-                    .map(|x| {
-                        info!(
-                            "::actors#{}::out-ready-checker set-ready to var #{}",
-                            self.id, respond_id
-                        );
-                        x.send(0u8);
-                        ()
-                    })
-                    .collect::<()>();
-                Ok(())
-            }
-            // TODO need MSG to handle unary operations
-            PayloadMessage::UnaryComputeFunctorMsg {
-                op,
-                inp,
-                respond_to,
-            } => {
-                // TODO need unary branch
-                info!("::actor#{}::enter-computation", self.id);
-                let outs = self.on_unary_compute(op, inp).expect("compute failed");
-                info!("::actor#{}::exit-computation", self.id);
-                respond_to.send(outs);
-                Ok(())
-            }
-            PayloadMessage::ComputeFunctorMsg {
-                op,
-                lhs,
-                rhs,
-                respond_to,
-            } => {
-                // TODO need unary branch
-                info!("::actor#{}::enter-computation", self.id);
-                let outs = self
-                    .on_binary_compute(op, lhs, rhs)
-                    .expect("compute failed");
-                info!("::actor#{}::exit-computation", self.id);
-                respond_to.send(outs);
-                Ok(())
-            }
+    // the shared slot that holds the workload under processing, the supervisor
+    // takes it back from there to requeue it if this actor panics
+    pub fn inflight(&self) -> InflightSlot<U, O> {
+        self.inflight.clone()
+    }
+
+    async fn fetch_and_handle(&mut self, msg: RaptorMessage<U, O>) -> Result<(), RaptorsError> {
+        let mut msg = match msg {
+            RaptorMessage::LoadfreeMSG(
+                _msg @ (LoadfreeMessage::SystemMsg(_)
+                | LoadfreeMessage::ActorMsg(_)
+                | LoadfreeMessage::DataMsg(..)),
+            ) => return self.fetch_and_handle_message(_msg).await,
+            workload => workload,
+        };
+        let ready = resolve_ready(self.id, &mut msg).await;
+        // park the workload during the computation, readiness checkers are
+        // re-armed already, hence the parked msg can be dispatched once more
+        *lock_slot(&self.inflight) = Some(msg);
+        let status = match ready {
+            Ok(()) => self.compute_parked().await,
+            // a failed input poisons the output as well, skip the computation
+            // and forward the very first failure to the dependents
+            Err(e) => Err(e),
+        };
+        self.respond_parked(status)
+    }
+
+    // the executor computes on a blocking thread, the actor task only waits
+    async fn compute_parked(&mut self) -> Result<Option<U>, RaptorsError> {
+        let mut kernel = self
+            .kernel
+            .take()
+            .ok_or_else(|| RaptorsError::actor(self.id, "executor is lost"))?;
+        let slot = self.inflight.clone();
+        let (kernel, status) = self
+            .pool
+            .run(move || {
+                let status = kernel.compute_parked(&slot);
+                (kernel, status)
+            })
+            .await?;
+        self.kernel = Some(kernel);
+        status
+    }
+
+    fn respond_parked(
+        &mut self,
+        status: Result<Option<U>, RaptorsError>,
+    ) -> Result<(), RaptorsError> {
+        let parked = lock_slot(&self.inflight).take();
+        match parked {
+            Some(RaptorMessage::PayloadMSG(_msg)) => match _msg {
+                // failures go back to the submitter, the actor keeps serving
+                // the submitter may have given up on the result
+                PayloadMessage::ComputeFunctorMsg { respond_to, .. }
+                | PayloadMessage::UnaryComputeFunctorMsg { respond_to, .. } => {
+                    let _ = respond_to.send(status.and_then(|outs| {
+                        outs.ok_or_else(|| RaptorsError::actor(self.id, "compute returns nothing"))
+                    }));
+                    Ok(())
+                }
+                PayloadMessage::NonRetUnaryComputeFunctorMsg {
+                    respond_to,
+                    respond_id,
+                    ..
+                }
+                | PayloadMessage::NonRetBinaryComputeFunctorMsg {
+                    respond_to,
+                    respond_id,
+                    ..
+                } => {
+                    let status = status.map(|_| ());
+                    self.set_ready(respond_to, respond_id, &status);
+                    status
+                }
+            },
+            _ => status.map(|_| ()),
         }
     }
 
-    async fn fetch_and_handle_message(&mut self, msg: LoadfreeMessage<U>) -> Result<(), String> {
+    async fn fetch_and_handle_message(
+        &mut self,
+        msg: LoadfreeMessage<U>,
+    ) -> Result<(), RaptorsError> {
         match msg {
-            LoadfreeMessage::MockTensorMsg(_wkl) => {
-                // info!("::actor#{}::COMPUTE {:?}", self.id, _wkl);
-                self.on_simulate(_wkl)
-            }
+            LoadfreeMessage::MockTensorMsg(_) => Err(RaptorsError::actor(
+                self.id,
+                "workloads are parked and computed, not handled as msgs",
+            )),
             LoadfreeMessage::ActorMsg(_amsg) => {
                 info!("::actor#{}::HANDLE ActorMSG - {:#?}", self.id, _amsg);
                 Ok(())
             }
-            _ => panic!("Unknown actormessage not implemented"),
+            LoadfreeMessage::DataMsg(topic, data) => {
+                info!("::actor#{}::HANDLE DataMSG on {}", self.id, topic);
+                self.kernel
+                    .as_mut()
+                    .ok_or_else(|| RaptorsError::actor(self.id, "executor is lost"))?
+                    .executor
+                    .on_published(&topic, data)
+            }
+            LoadfreeMessage::SystemMsg(_smsg) => Err(RaptorsError::actor(
+                self.id,
+                format!("system msg {:?} is not handled by actors", _smsg),
+            )),
         }
     }
 
+    // the actor is registered as available by the system when spawned, afterwards
+    // every workload it handles is acknowledged by exactly one available msg, this
+    // lets the system count the in-flight workloads precisely
     #[tracing::instrument(name = "actor::run", skip(self))]
     pub async fn run(&mut self) -> u32 {
         loop {
-            match self.receiver.try_recv() {
-                Ok(_msg) => {
+            match self.receiver.recv().await {
+                Some(_msg) => {
                     info!("::actor#{}::receive msg from system", self.id);
-                    let status = self.fetch_and_handle(_msg).await;
-                }
-                Err(TryRecvError::Empty) => {
-                    let msg = build_loadfree_msg!("available", self.id);
-                    // TODO update build_msg with generalmessage
-                    self.respond_to.try_send(RaptorMessage::LoadfreeMSG(msg));
-                    info!("::actor#{}::tell supervisor i am available", self.id);
-                    match self.receiver.recv().await {
-                        Some(_msg) => {
-                            info!("::actor#{}::receive msg from system", self.id);
-                            let status = self.fetch_and_handle(_msg).await;
-                        }
-                        None => {
-                            info!("::actor#{}::DROPPED BY SUPERVISOR -> HALTING", self.id);
+                    let is_workload = _msg.is_workload();
+                    if let Err(e) = self.fetch_and_handle(_msg).await {
+                        info!("::actor#{}::fail to handle msg - {}", self.id, e);
+                    }
+                    if is_workload {
+                        let msg = build_loadfree_msg!("available", self.id, self.generation);
+                        // TODO update build_msg with generalmessage
+                        if self
+                            .respond_to
+                            .send(RaptorMessage::LoadfreeMSG(msg))
+                            .await
+                            .is_err()
+                        {
+                            info!("::actor#{}::SUPERVISOR GONE -> HALTING", self.id);
                             break 1;
                         }
+                        info!("::actor#{}::tell supervisor i am available", self.id);
                     }
                 }
-                Err(TryRecvError::Disconnected) => {
+                None => {
                     info!("::actor#{}::DROPPED BY SUPERVISOR -> HALTING", self.id);
                     break 1;
                 }
-                _ => (),
             }
         }
     }
 
+    fn set_ready(
+        &self,
+        respond_to: Vec<oneshot::Sender<ReadySignal>>,
+        respond_id: usize,
+        status: &ReadySignal,
+    ) {
+        for x in respond_to {
+            info!(
+                "::actors#{}::out-ready-checker set-ready to var #{} - {:?}",
+                self.id, respond_id, status
+            );
+            // dependents may be dropped already, nobody is waiting then
+            let _ = x.send(status.clone());
+        }
+    }
+}
+
+// the executor of an actor, moved to a blocking thread for each computation
+#[derive(Debug)]
+struct Kernel<T, O>
+where
+    O: OpCodeLike,
+{
+    id: usize,
+    executor: T,
+    learner: Option<CostLearner<O>>,
+}
+
+impl<T, U, O> Kernel<T, O>
+where
+    T: ExecutorLike<TensorType = U, OpCodeType = O>,
+    U: TensorLike + Clone + Debug,
+    O: OpCodeLike + Debug,
+{
+    fn compute_parked(&mut self, slot: &InflightSlot<U, O>) -> Result<Option<U>, RaptorsError> {
+        let parked = lock_slot(slot);
+        info!("::actor#{}::enter-computation", self.id);
+        let started = Instant::now();
+        let status = match parked.as_ref() {
+            Some(RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(_wkl))) => {
+                // info!("::actor#{}::COMPUTE {:?}", self.id, _wkl);
+                self.on_simulate(_wkl.clone()).map(|_| None)
+            }
+            Some(RaptorMessage::PayloadMSG(_msg)) => match _msg {
+                PayloadMessage::ComputeFunctorMsg { op, lhs, rhs, .. } => self
+                    .on_binary_compute(op.clone(), lhs.clone(), rhs.clone())
+                    .map(Some),
+                PayloadMessage::UnaryComputeFunctorMsg { op, inp, .. } => {
+                    self.on_unary_compute(op.clone(), inp.clone()).map(Some)
+                }
+                PayloadMessage::NonRetUnaryComputeFunctorMsg { op, inp, out, .. } => self
+                    .on_unary_compute_v2(op.clone(), inp.clone(), out.clone())
+                    .map(|_| None),
+                PayloadMessage::NonRetBinaryComputeFunctorMsg {
+                    op, lhs, rhs, out, ..
+                } => self
+                    .on_binary_compute_v2(op.clone(), lhs.clone(), rhs.clone(), out.clone())
+                    .map(|_| None),
+            },
+            _ => Err(RaptorsError::actor(self.id, "no workload parked")),
+        };
+        // failed calls tell nothing about the cost of the opcode
+        if let (Some(learner), Some(RaptorMessage::PayloadMSG(_msg)), Ok(_)) =
+            (&self.learner, parked.as_ref(), &status)
+        {
+            learner.observe(&_msg.op().name(), started.elapsed());
+        }
+        info!("::actor#{}::exit-computation", self.id);
+        status
+    }
+
     #[tracing::instrument(name = "actor::on_compute", skip(self, workload))]
-    fn on_simulate(&mut self, workload: U) -> Result<(), String> {
-        self.executor.mock_compute(workload);
+    fn on_simulate(&mut self, workload: U) -> Result<(), RaptorsError> {
+        self.executor
+            .mock_compute(workload)
+            .map_err(|e| e.at_actor(self.id))?;
+        Ok(())
+    }
+
+    // a tensor whose lock is poisoned was left half-written by a panicking
+    // writer, computing on it would spread garbage or panic the actor
+    fn check_operands(&self, op: &O, operands: &[&Arc<RwLock<U>>]) -> Result<(), RaptorsError> {
+        if operands.iter().any(|x| x.is_poisoned()) {
+            return Err(RaptorsError::executor(self.id, op, "operand lock poisoned"));
+        }
         Ok(())
     }
 
@@ -233,9 +424,11 @@ where
         op: O,
         lhs: Arc<RwLock<U>>,
         rhs: Arc<RwLock<U>>,
-    ) -> Result<U, String> {
-        let outs = self.executor.binary_compute(op, lhs, rhs);
-        Ok(outs)
+    ) -> Result<U, RaptorsError> {
+        self.check_operands(&op, &[&lhs, &rhs])?;
+        self.executor
+            .binary_compute(op, lhs, rhs)
+            .map_err(|e| e.at_actor(self.id))
     }
 
     #[tracing::instrument(name = "actor::on_binary_compute", skip(self, lhs, rhs))]
@@ -245,15 +438,19 @@ where
         lhs: Arc<RwLock<U>>,
         rhs: Arc<RwLock<U>>,
         out: Arc<RwLock<U>>,
-    ) -> Result<(), String> {
-        let outs = self.executor.binary_compute_v2(op, lhs, rhs, out);
-        Ok(outs)
+    ) -> Result<(), RaptorsError> {
+        self.check_operands(&op, &[&lhs, &rhs, &out])?;
+        self.executor
+            .binary_compute_v2(op, lhs, rhs, out)
+            .map_err(|e| e.at_actor(self.id))
     }
 
     #[tracing::instrument(name = "actor::on_unary_compute", skip(self, operand))]
-    fn on_unary_compute(&mut self, op: O, operand: Arc<RwLock<U>>) -> Result<U, String> {
-        let outs = self.executor.unary_compute(op, operand);
-        Ok(outs)
+    fn on_unary_compute(&mut self, op: O, operand: Arc<RwLock<U>>) -> Result<U, RaptorsError> {
+        self.check_operands(&op, &[&operand])?;
+        self.executor
+            .unary_compute(op, operand)
+            .map_err(|e| e.at_actor(self.id))
     }
 
     // v2 consumes output, mutable into inner value, and returns status
@@ -263,9 +460,11 @@ where
         op: O,
         operand: Arc<RwLock<U>>,
         result: Arc<RwLock<U>>,
-    ) -> Result<(), String> {
-        let status = self.executor.unary_compute_v2(op, operand, result);
-        Ok(status)
+    ) -> Result<(), RaptorsError> {
+        self.check_operands(&op, &[&operand, &result])?;
+        self.executor
+            .unary_compute_v2(op, operand, result)
+            .map_err(|e| e.at_actor(self.id))
     }
 }
 
@@ -324,5 +523,4 @@ where
 
 // unit tests
 #[cfg(test)]
-
 mod tests {}
@@ -0,0 +1,128 @@
+// LICENSE PLACEHOLDER
+use std::panic;
+use std::sync::Arc;
+use std::thread;
+
+use tokio::sync::Semaphore;
+
+use crate::errors::RaptorsError;
+
+/// Definition: BlockingPool runs the executor calls of the actors on the
+/// blocking threads of the runtime, so that the async side of actors and the
+/// event loop of the system keep being polled while kernels compute.
+///
+/// At most `size` calls run at the same time, the others wait for a slot
+/// without blocking any worker thread.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let pool = BlockingPool::new(2);
+///     let sum = pool.run(|| (1..=4).sum::<usize>()).await.unwrap();
+///     assert_eq!(sum, 10);
+///     assert_eq!(pool.size(), 2);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BlockingPool {
+    size: usize,
+    permits: Arc<Semaphore>,
+}
+
+impl Default for BlockingPool {
+    fn default() -> Self {
+        Self::new(default_size())
+    }
+}
+
+impl BlockingPool {
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            size,
+            permits: Arc::new(Semaphore::new(size)),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// slots not taken by a running call
+    pub fn available(&self) -> usize {
+        self.permits.available_permits()
+    }
+
+    /// run the call on a blocking thread once a slot is free, a panic of the
+    /// call is resumed on the awaiting task so that supervision still sees it
+    pub async fn run<F, R>(&self, f: F) -> Result<R, RaptorsError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        // the semaphore is never closed
+        let _permit = self.permits.acquire().await.ok();
+        match tokio::task::spawn_blocking(f).await {
+            Ok(ret) => Ok(ret),
+            Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+            Err(_) => Err(RaptorsError::system("blocking pool is shutting down")),
+        }
+    }
+}
+
+// one slot per core by default
+pub(crate) fn default_size() -> usize {
+    thread::available_parallelism()
+        .map(|x| x.get())
+        .unwrap_or(1)
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn blocking_pool_keeps_runtime_responsive_test() {
+        let pool = BlockingPool::new(1);
+        let busy = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(|| thread::sleep(Duration::from_millis(500))).await }
+        });
+        // the current-thread runtime is free to run timers meanwhile
+        let now = Instant::now();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(now.elapsed() < Duration::from_millis(400));
+        assert!(busy.await.unwrap().is_ok());
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[tokio::test]
+    async fn blocking_pool_bounds_concurrent_calls_test() {
+        let pool = BlockingPool::new(0);
+        assert_eq!(pool.size(), 1);
+        let now = Instant::now();
+        let calls: Vec<_> = (0..2)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(
+                    async move { pool.run(|| thread::sleep(Duration::from_millis(100))).await },
+                )
+            })
+            .collect();
+        for call in calls {
+            call.await.unwrap().unwrap();
+        }
+        assert!(now.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn blocking_pool_resumes_panics_test() {
+        let pool = BlockingPool::new(1);
+        let call = tokio::spawn(async move { pool.run(|| panic!("kernel panicked")).await });
+        assert!(call.await.unwrap_err().is_panic());
+    }
+}
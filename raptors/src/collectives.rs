@@ -0,0 +1,470 @@
+// LICENSE PLACEHOLDER
+use std::fmt::Debug;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::cost_model::OpCodeLike;
+use crate::errors::RaptorsError;
+use crate::executor_types::ExecutorLike;
+use crate::graph::{GraphCompletion, TaskGraph, TensorId};
+use crate::system::ActorSystemHandle;
+use crate::tensor_types::{Chunked, TensorLike};
+
+// ids of the chunks held by every rank, in rank order
+type RankChunks = Vec<Vec<TensorId>>;
+
+/// Definition: ActorGroup is a set of actors of a system that perform
+/// collective operations together, the rank of an actor is its position in
+/// the group and the i-th tensor passed to a collective belongs to rank i.
+///
+/// Every collective is submitted as a TaskGraph whose nodes are pinned to the
+/// actors of the group: tensors move from one actor to another through the
+/// copy opcode and are reduced with the compute opcode given to the
+/// collective. The returned completion resolves once the results are written.
+///
+/// ```
+/// use std::sync::{Arc, RwLock};
+/// use raptors::prelude::*;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut sys_config = SystemConfig::new("Raptors", "info");
+///     sys_config.set_ranks(2);
+///     let mut system = SystemBuilder::new()
+///         .build_with_config::<CpuExecutor, CpuTensor, MockOpCode>(sys_config).unwrap();
+///     let tensors: Vec<_> = [1.0, 2.0]
+///         .iter()
+///         .map(|x| Arc::new(RwLock::new(CpuTensor::from_f32(vec![1], vec![*x]).unwrap())))
+///         .collect();
+///
+///     let group = ActorGroup::new(vec![0, 1], MockOpCode::IdentityOp).unwrap();
+///     let done = group
+///         .all_reduce(&mut system, MockOpCode::AddOp, &tensors)
+///         .await
+///         .unwrap();
+///     assert!(done.await.is_ok());
+///     for tensor in tensors.iter() {
+///         assert_eq!(tensor.read().unwrap().as_f32(), Some(&[3.0][..]));
+///     }
+///     system.shutdown().await.unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActorGroup<O> {
+    actors: Vec<usize>,
+    copy_op: O,
+}
+
+impl<O> ActorGroup<O>
+where
+    O: 'static + OpCodeLike + Debug + Send + Sync,
+{
+    /// group of the actors of the ids in rank order, the copy opcode must
+    /// compute a tensor equal to its input
+    pub fn new(actors: Vec<usize>, copy_op: O) -> Result<Self, RaptorsError> {
+        if actors.is_empty() {
+            return Err(RaptorsError::scheduling("actor group is empty"));
+        }
+        if let Some(twice) = actors
+            .iter()
+            .enumerate()
+            .find(|(rank, actor)| actors[..*rank].contains(actor))
+        {
+            return Err(RaptorsError::scheduling(format!(
+                "actor #{} is in the group twice",
+                twice.1
+            )));
+        }
+        Ok(Self { actors, copy_op })
+    }
+
+    pub fn size(&self) -> usize {
+        self.actors.len()
+    }
+
+    pub fn actors(&self) -> &[usize] {
+        &self.actors
+    }
+
+    pub fn rank_of(&self, actor: usize) -> Option<usize> {
+        self.actors.iter().position(|x| *x == actor)
+    }
+
+    /// reduce the tensors of all ranks with the op, every rank gets the result
+    /// in its own tensor; this is a ring all-reduce, so the op must be
+    /// commutative and associative. The tensors are read when the graph is
+    /// submitted and written back once the completion resolves.
+    pub async fn all_reduce<T, U>(
+        &self,
+        system: &mut ActorSystemHandle<T, U, O>,
+        op: O,
+        tensors: &[Arc<RwLock<U>>],
+    ) -> Result<GraphCompletion, RaptorsError>
+    where
+        T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync,
+        U: 'static + TensorLike + Chunked + Clone + Default + Send + Sync + Debug,
+    {
+        let (graph, results) = self.ring_all_reduce_graph(op, tensors)?;
+        let results = results
+            .iter()
+            .map(|chunks| {
+                chunks
+                    .iter()
+                    .map(|id| graph.tensor(*id).expect("chunk of the graph"))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let tensors = tensors.to_vec();
+        let done = graph.submit(system).await?;
+        Ok(done.then(move || {
+            for (tensor, chunks) in tensors.iter().zip(results.iter()) {
+                let chunks: Vec<U> = chunks
+                    .iter()
+                    .map(|x| x.read().unwrap_or_else(PoisonError::into_inner).clone())
+                    .collect();
+                tensor
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .join(&chunks)?;
+            }
+            Ok(())
+        }))
+    }
+
+    /// copy the tensor of the root rank to the tensors of the other ranks
+    pub async fn broadcast<T, U>(
+        &self,
+        system: &mut ActorSystemHandle<T, U, O>,
+        root: usize,
+        tensors: &[Arc<RwLock<U>>],
+    ) -> Result<GraphCompletion, RaptorsError>
+    where
+        T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync,
+        U: 'static + TensorLike + Clone + Default + Send + Sync + Debug,
+    {
+        self.broadcast_graph(root, tensors)?.submit(system).await
+    }
+
+    /// hand the i-th chunk held by the root rank over to the tensor of rank i,
+    /// the copies run on the actor of the root
+    pub async fn scatter<T, U>(
+        &self,
+        system: &mut ActorSystemHandle<T, U, O>,
+        root: usize,
+        chunks: &[Arc<RwLock<U>>],
+        tensors: &[Arc<RwLock<U>>],
+    ) -> Result<GraphCompletion, RaptorsError>
+    where
+        T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync,
+        U: 'static + TensorLike + Clone + Default + Send + Sync + Debug,
+    {
+        self.scatter_graph(root, chunks, tensors)?
+            .submit(system)
+            .await
+    }
+
+    /// collect the tensor of rank i into the i-th chunk held by the root rank
+    pub async fn gather<T, U>(
+        &self,
+        system: &mut ActorSystemHandle<T, U, O>,
+        root: usize,
+        tensors: &[Arc<RwLock<U>>],
+        chunks: &[Arc<RwLock<U>>],
+    ) -> Result<GraphCompletion, RaptorsError>
+    where
+        T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync,
+        U: 'static + TensorLike + Clone + Default + Send + Sync + Debug,
+    {
+        self.gather_graph(root, tensors, chunks)?
+            .submit(system)
+            .await
+    }
+
+    // ring all-reduce: every tensor is split into one chunk per rank. In step
+    // k of the reduce-scatter rank i sends its chunk (i - k) mod p to rank
+    // i + 1, which reduces it into its own one; after p - 1 steps rank i holds
+    // the total of chunk (i + 1) mod p. The all-gather then passes the totals
+    // around the ring in p - 1 more steps.
+    //
+    // Sends are copies pinned on the sending rank, reductions are pinned on
+    // the receiving one. Returns the ids of the chunks every rank ends with.
+    fn ring_all_reduce_graph<U>(
+        &self,
+        op: O,
+        tensors: &[Arc<RwLock<U>>],
+    ) -> Result<(TaskGraph<U, O>, RankChunks), RaptorsError>
+    where
+        U: 'static + TensorLike + Chunked + Clone + Default + Send + Sync + Debug,
+    {
+        self.check_len("tensors", tensors)?;
+        let size = self.size();
+        let mut graph = TaskGraph::new();
+        let mut chunks: RankChunks = tensors
+            .iter()
+            .map(|x| {
+                x.read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .split(size)
+                    .into_iter()
+                    .map(|chunk| graph.add_tensor(chunk))
+                    .collect()
+            })
+            .collect();
+
+        for step in 0..size - 1 {
+            let mut next = chunks.clone();
+            for rank in 0..size {
+                let (to, chunk) = ((rank + 1) % size, (rank + size - step) % size);
+                let relay = graph.add_tensor(U::default());
+                self.add_copy(&mut graph, rank, chunks[rank][chunk], relay)?;
+                let reduced = graph.add_tensor(U::default());
+                let node = graph.add_binary(op.clone(), relay, chunks[to][chunk], reduced)?;
+                graph.pin(node, self.actors[to])?;
+                next[to][chunk] = reduced;
+            }
+            chunks = next;
+        }
+        for step in 0..size - 1 {
+            let mut next = chunks.clone();
+            for (rank, held) in chunks.iter().enumerate() {
+                let (to, chunk) = ((rank + 1) % size, (rank + 1 + size - step) % size);
+                let received = graph.add_tensor(U::default());
+                self.add_copy(&mut graph, rank, held[chunk], received)?;
+                next[to][chunk] = received;
+            }
+            chunks = next;
+        }
+        Ok((graph, chunks))
+    }
+
+    fn broadcast_graph<U>(
+        &self,
+        root: usize,
+        tensors: &[Arc<RwLock<U>>],
+    ) -> Result<TaskGraph<U, O>, RaptorsError>
+    where
+        U: 'static + TensorLike + Clone + Default + Send + Sync + Debug,
+    {
+        self.check_root(root)?;
+        self.check_len("tensors", tensors)?;
+        let mut graph = TaskGraph::new();
+        let source = graph.add_shared(tensors[root].clone());
+        for (rank, tensor) in tensors.iter().enumerate().filter(|(x, _)| *x != root) {
+            let output = graph.add_shared(tensor.clone());
+            self.add_copy(&mut graph, rank, source, output)?;
+        }
+        Ok(graph)
+    }
+
+    fn scatter_graph<U>(
+        &self,
+        root: usize,
+        chunks: &[Arc<RwLock<U>>],
+        tensors: &[Arc<RwLock<U>>],
+    ) -> Result<TaskGraph<U, O>, RaptorsError>
+    where
+        U: 'static + TensorLike + Clone + Default + Send + Sync + Debug,
+    {
+        self.check_root(root)?;
+        self.check_len("chunks", chunks)?;
+        self.check_len("tensors", tensors)?;
+        let mut graph = TaskGraph::new();
+        for (chunk, tensor) in chunks.iter().zip(tensors.iter()) {
+            let source = graph.add_shared(chunk.clone());
+            let output = graph.add_shared(tensor.clone());
+            self.add_copy(&mut graph, root, source, output)?;
+        }
+        Ok(graph)
+    }
+
+    fn gather_graph<U>(
+        &self,
+        root: usize,
+        tensors: &[Arc<RwLock<U>>],
+        chunks: &[Arc<RwLock<U>>],
+    ) -> Result<TaskGraph<U, O>, RaptorsError>
+    where
+        U: 'static + TensorLike + Clone + Default + Send + Sync + Debug,
+    {
+        self.check_root(root)?;
+        self.check_len("tensors", tensors)?;
+        self.check_len("chunks", chunks)?;
+        let mut graph = TaskGraph::new();
+        for (tensor, chunk) in tensors.iter().zip(chunks.iter()) {
+            let source = graph.add_shared(tensor.clone());
+            let output = graph.add_shared(chunk.clone());
+            self.add_copy(&mut graph, root, source, output)?;
+        }
+        Ok(graph)
+    }
+
+    // copy the tensor on the actor of the rank
+    fn add_copy<U>(
+        &self,
+        graph: &mut TaskGraph<U, O>,
+        rank: usize,
+        inp: TensorId,
+        out: TensorId,
+    ) -> Result<(), RaptorsError>
+    where
+        U: 'static + TensorLike + Clone + Send + Sync + Debug,
+    {
+        let node = graph.add_unary(self.copy_op.clone(), inp, out)?;
+        graph.pin(node, self.actors[rank])
+    }
+
+    fn check_root(&self, root: usize) -> Result<(), RaptorsError> {
+        if root >= self.size() {
+            return Err(RaptorsError::scheduling(format!(
+                "root rank {} is out of a group of {}",
+                root,
+                self.size()
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_len<U>(&self, what: &str, tensors: &[U]) -> Result<(), RaptorsError> {
+        if tensors.len() != self.size() {
+            return Err(RaptorsError::scheduling(format!(
+                "got {} {} for a group of {}",
+                tensors.len(),
+                what,
+                self.size()
+            )));
+        }
+        Ok(())
+    }
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    fn shared(shape: Vec<usize>, data: Vec<f64>) -> Arc<RwLock<CpuTensor>> {
+        Arc::new(RwLock::new(CpuTensor::from_f64(shape, data).unwrap()))
+    }
+
+    fn read(tensor: &Arc<RwLock<CpuTensor>>) -> CpuTensor {
+        tensor.read().unwrap().clone()
+    }
+
+    fn cpu_system(ranks: usize) -> ActorSystemHandle<CpuExecutor, CpuTensor, MockOpCode> {
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(ranks);
+        SystemBuilder::new().build_with_config(sys_config).unwrap()
+    }
+
+    #[test]
+    fn actor_group_rejects_bad_groups_test() {
+        assert!(ActorGroup::new(vec![], MockOpCode::IdentityOp).is_err());
+        assert_eq!(
+            ActorGroup::new(vec![0, 2, 0], MockOpCode::IdentityOp),
+            Err(RaptorsError::scheduling("actor #0 is in the group twice"))
+        );
+        let group = ActorGroup::new(vec![3, 1], MockOpCode::IdentityOp).unwrap();
+        assert_eq!(group.rank_of(1), Some(1));
+        assert_eq!(group.rank_of(0), None);
+
+        let tensors = vec![shared(vec![1], vec![1.0])];
+        assert_eq!(
+            group
+                .ring_all_reduce_graph(MockOpCode::AddOp, &tensors)
+                .err(),
+            Some(RaptorsError::scheduling("got 1 tensors for a group of 2"))
+        );
+        assert!(group.broadcast_graph(2, &tensors).is_err());
+        // p - 1 steps of p sends and reductions, then p - 1 steps of p sends
+        let tensors: Vec<_> = (0..2).map(|_| shared(vec![3], vec![1.0; 3])).collect();
+        let (graph, chunks) = group
+            .ring_all_reduce_graph(MockOpCode::AddOp, &tensors)
+            .unwrap();
+        assert_eq!(graph.nodes(), 6);
+        assert!(chunks.iter().all(|x| x.len() == 2));
+        // the root hands the chunks over, the gathering root collects them
+        let group = ActorGroup::new(vec![2, 0, 1], MockOpCode::IdentityOp).unwrap();
+        let tensors: Vec<_> = (0..3).map(|_| shared(vec![1], vec![1.0])).collect();
+        let graph = group.scatter_graph(1, &tensors, &tensors).unwrap();
+        assert!((0..3).all(|node| graph.placement(node) == Some(0)));
+        let graph = group.gather_graph(2, &tensors, &tensors).unwrap();
+        assert!((0..3).all(|node| graph.placement(node) == Some(1)));
+    }
+
+    #[tokio::test]
+    async fn all_reduce_matches_sequential_fold_test() {
+        let exec = CpuExecutor::new();
+        // 7 elements do not divide evenly by any of the group sizes but one
+        for (size, shape) in (1..=4).flat_map(|x| [(x, vec![2, 3]), (x, vec![7])]) {
+            let numel: usize = shape.iter().product();
+            // small integers keep every order of the ops exact
+            let inputs: Vec<CpuTensor> = (0..size)
+                .map(|rank| {
+                    let data = (0..numel).map(|x| ((x + rank) % 3 + 1) as f64).collect();
+                    CpuTensor::from_f64(shape.clone(), data).unwrap()
+                })
+                .collect();
+            for op in [MockOpCode::AddOp, MockOpCode::MulOp] {
+                let expected = inputs[1..].iter().fold(inputs[0].clone(), |acc, x| {
+                    exec.binary(op, &acc, x).unwrap()
+                });
+                let tensors: Vec<_> = inputs
+                    .iter()
+                    .map(|x| Arc::new(RwLock::new(x.clone())))
+                    .collect();
+
+                let mut system = cpu_system(size);
+                let actors = (0..size).rev().collect();
+                let group = ActorGroup::new(actors, MockOpCode::IdentityOp).unwrap();
+                let done = group.all_reduce(&mut system, op, &tensors).await.unwrap();
+                assert_eq!(done.await, Ok(()));
+                for tensor in tensors.iter() {
+                    assert_eq!(read(tensor), expected);
+                }
+                let summary = system.shutdown().await.unwrap();
+                assert_eq!(summary.tasks_completed, 3 * size * (size - 1));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn broadcast_scatter_gather_test() {
+        let mut system = cpu_system(3);
+        let group = ActorGroup::new(vec![0, 1, 2], MockOpCode::IdentityOp).unwrap();
+
+        let tensors: Vec<_> = (0..3)
+            .map(|rank| shared(vec![2], vec![rank as f64; 2]))
+            .collect();
+        let done = group.broadcast(&mut system, 1, &tensors).await.unwrap();
+        assert_eq!(done.await, Ok(()));
+        for tensor in tensors.iter() {
+            assert_eq!(read(tensor).as_f64(), Some(&[1.0, 1.0][..]));
+        }
+
+        // scatter the chunks of rank 2, then gather them back into new ones
+        let chunks: Vec<_> = (0..3)
+            .map(|rank| shared(vec![1, 2], vec![rank as f64, -(rank as f64)]))
+            .collect();
+        let done = group
+            .scatter(&mut system, 2, &chunks, &tensors)
+            .await
+            .unwrap();
+        assert_eq!(done.await, Ok(()));
+        for (chunk, tensor) in chunks.iter().zip(tensors.iter()) {
+            assert_eq!(read(tensor), read(chunk));
+        }
+        let gathered: Vec<_> = (0..3)
+            .map(|_| Arc::new(RwLock::new(CpuTensor::default())))
+            .collect();
+        let done = group
+            .gather(&mut system, 0, &tensors, &gathered)
+            .await
+            .unwrap();
+        assert_eq!(done.await, Ok(()));
+        for (chunk, tensor) in chunks.iter().zip(gathered.iter()) {
+            assert_eq!(read(tensor), read(chunk));
+        }
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 2 + 3 + 3);
+    }
+}
@@ -0,0 +1,298 @@
+// LICENSE PLACEHOLDER
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::cost_model::{MockOpCode, OpCodeLike};
+use crate::executor_types::conv_dims;
+use crate::tensor_types::TensorMeta;
+
+// flops per element of exp and sin, a polynomial approximation each
+const TRANSCENDENTAL_FLOPS: f64 = 8.0;
+
+/// Definition: CostFunction estimates the cost of an opcode on operands of
+/// the given shapes and dtypes, unlike the per-opcode table of CostModel it
+/// tells a 4x4 matmul from a 4096x4096 one.
+///
+/// Costs are in microseconds, the cost model converts them into its unit.
+/// None means the function has no estimate, e.g. the operands carry no
+/// metadata, the cost model falls back to its table then. The shipped
+/// functions know the ops of MockOpCode, other opcode types bring their own.
+pub trait CostFunction<O = MockOpCode>: Send + Sync + Debug
+where
+    O: OpCodeLike,
+{
+    fn cost(&self, op: &O, operands: &[TensorMeta]) -> Option<usize>;
+}
+
+/// arithmetic and memory traffic of an op, operands are read once and the
+/// output written once
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Work {
+    pub flops: f64,
+    pub bytes: f64,
+}
+
+impl Work {
+    /// work of the op on the operands, shapes are checked as the kernels of
+    /// CpuExecutor do, None if they do not fit the op
+    pub fn of(op: &MockOpCode, operands: &[TensorMeta]) -> Option<Self> {
+        let first = operands.first()?;
+        let (flops, out_numel) = match (op, operands) {
+            (MockOpCode::IdentityOp | MockOpCode::ReshapeOp, _) => (0, first.numel()),
+            (MockOpCode::ExpOp | MockOpCode::SinOp, [inp]) => {
+                let numel = inp.numel();
+                return Some(Self {
+                    flops: TRANSCENDENTAL_FLOPS * numel as f64,
+                    bytes: (2 * inp.bytes()) as f64,
+                });
+            }
+            (
+                MockOpCode::AddOp | MockOpCode::SubOp | MockOpCode::MulOp | MockOpCode::DivOp,
+                [lhs, rhs],
+            ) => {
+                let numel = lhs.numel().max(rhs.numel());
+                (numel, numel)
+            }
+            (MockOpCode::MatmulOp, [lhs, rhs]) => {
+                match (lhs.shape.as_slice(), rhs.shape.as_slice()) {
+                    (&[m, k], &[k2, n]) if k == k2 => (2 * m * k * n, m * n),
+                    _ => return None,
+                }
+            }
+            (MockOpCode::ConvOp, [input, kernel]) => {
+                let ([n, c, h, w], [o, _, kh, kw]) = conv_dims(&input.shape, &kernel.shape)?;
+                let (oh, ow) = (h - kh + 1, w - kw + 1);
+                (2 * n * o * oh * ow * c * kh * kw, n * o * oh * ow)
+            }
+            _ => return None,
+        };
+        let read: usize = operands.iter().map(|x| x.bytes()).sum();
+        Some(Self {
+            flops: flops as f64,
+            bytes: (read + out_numel * first.dtype.size_of()) as f64,
+        })
+    }
+
+    /// the problem size tables are keyed by, the flops or, for the ops that
+    /// only move data, the bytes
+    pub fn size(&self) -> f64 {
+        if self.flops > 0.0 {
+            self.flops
+        } else {
+            self.bytes
+        }
+    }
+}
+
+/// Definition: Roofline is the analytic cost function of a device with the
+/// given peak flops and memory bandwidth, an op takes the longer of its
+/// compute and memory time; costs are in microseconds.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// // 1 GFLOP/s, 1 GB/s
+/// let roofline = Roofline::new(1e9, 1e9);
+/// let small = vec![TensorMeta::new(vec![4, 4], DType::F32); 2];
+/// let large = vec![TensorMeta::new(vec![512, 512], DType::F32); 2];
+/// assert_eq!(roofline.cost(&MockOpCode::MatmulOp, &small), Some(1));
+/// assert_eq!(roofline.cost(&MockOpCode::MatmulOp, &large), Some(268436));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Roofline {
+    // flop per second
+    peak_flops: f64,
+    // bytes per second
+    bandwidth: f64,
+}
+
+impl Default for Roofline {
+    // a single core of a commodity cpu
+    fn default() -> Self {
+        Self::new(1e10, 1e10)
+    }
+}
+
+impl Roofline {
+    pub fn new(peak_flops: f64, bandwidth: f64) -> Self {
+        Self {
+            peak_flops: peak_flops.max(f64::MIN_POSITIVE),
+            bandwidth: bandwidth.max(f64::MIN_POSITIVE),
+        }
+    }
+
+    /// microseconds the work takes on the device
+    pub fn time(&self, work: &Work) -> f64 {
+        (work.flops / self.peak_flops).max(work.bytes / self.bandwidth) * 1e6
+    }
+}
+
+impl CostFunction for Roofline {
+    fn cost(&self, op: &MockOpCode, operands: &[TensorMeta]) -> Option<usize> {
+        Work::of(op, operands).map(|work| self.time(&work).ceil() as usize)
+    }
+}
+
+/// Definition: TableInterpolation estimates an op from the times measured at
+/// a few problem sizes, in microseconds, linearly between the two nearest ones
+/// and along the nearest segment outside them.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// let mut table = TableInterpolation::new();
+/// table.insert(MockOpCode::AddOp, 1000.0, 10);
+/// table.insert(MockOpCode::AddOp, 3000.0, 20);
+/// assert_eq!(table.interpolate(&MockOpCode::AddOp, 2000.0), Some(15.0));
+/// assert_eq!(table.interpolate(&MockOpCode::AddOp, 5000.0), Some(30.0));
+/// assert_eq!(table.interpolate(&MockOpCode::SubOp, 5000.0), None);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TableInterpolation {
+    // (problem size, cost) of each op, ascending in size
+    points: HashMap<MockOpCode, Vec<(f64, f64)>>,
+}
+
+impl TableInterpolation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the op costs `cost` at the problem size, see `Work::size`, a measure
+    /// at a known size replaces the former one
+    pub fn insert(&mut self, op: MockOpCode, size: f64, cost: usize) {
+        let points = self.points.entry(op).or_default();
+        let idx = points.partition_point(|(x, _)| *x < size);
+        match points.get_mut(idx) {
+            Some(point) if point.0 == size => point.1 = cost as f64,
+            _ => points.insert(idx, (size, cost as f64)),
+        }
+    }
+
+    /// insert the cost measured on the operands, returns false if their
+    /// shapes do not fit the op
+    pub fn record(&mut self, op: MockOpCode, operands: &[TensorMeta], cost: usize) -> bool {
+        match Work::of(&op, operands) {
+            Some(work) => {
+                self.insert(op, work.size(), cost);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn interpolate(&self, op: &MockOpCode, size: f64) -> Option<f64> {
+        let points = self.points.get(op)?;
+        let cost = match points.as_slice() {
+            [] => return None,
+            // a single measure scales with the size
+            [(x, y)] if *x > 0.0 => y * size / x,
+            [(_, y)] => *y,
+            _ => {
+                let idx = points
+                    .partition_point(|(x, _)| *x < size)
+                    .clamp(1, points.len() - 1);
+                let ((x0, y0), (x1, y1)) = (points[idx - 1], points[idx]);
+                y0 + (y1 - y0) * (size - x0) / (x1 - x0)
+            }
+        };
+        Some(cost.max(0.0))
+    }
+}
+
+impl CostFunction for TableInterpolation {
+    fn cost(&self, op: &MockOpCode, operands: &[TensorMeta]) -> Option<usize> {
+        let work = Work::of(op, operands)?;
+        self.interpolate(op, work.size())
+            .map(|cost| cost.round() as usize)
+    }
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor_types::DType;
+
+    fn meta(shape: &[usize]) -> TensorMeta {
+        TensorMeta::new(shape.to_vec(), DType::F32)
+    }
+
+    #[test]
+    fn work_of_ops_test() {
+        let work = Work::of(&MockOpCode::MatmulOp, &[meta(&[2, 3]), meta(&[3, 4])]).unwrap();
+        assert_eq!(work.flops, 48.0);
+        assert_eq!(work.bytes, ((6 + 12 + 8) * 4) as f64);
+        assert!(Work::of(&MockOpCode::MatmulOp, &[meta(&[2, 3]), meta(&[2, 3])]).is_none());
+
+        // scalars broadcast
+        let work = Work::of(&MockOpCode::AddOp, &[meta(&[1]), meta(&[8, 8])]).unwrap();
+        assert_eq!(work.flops, 64.0);
+
+        let work = Work::of(
+            &MockOpCode::ConvOp,
+            &[meta(&[1, 2, 5, 5]), meta(&[3, 2, 3, 3])],
+        )
+        .unwrap();
+        assert_eq!(work.flops, (2 * 3 * 3 * 3 * 2 * 3 * 3) as f64);
+
+        let work = Work::of(
+            &MockOpCode::ReshapeOp,
+            &[TensorMeta::new(vec![4, 4], DType::F64)],
+        )
+        .unwrap();
+        assert_eq!(work.flops, 0.0);
+        assert_eq!(work.size(), 256.0);
+        assert!(Work::of(&MockOpCode::ExpOp, &[]).is_none());
+    }
+
+    #[test]
+    fn roofline_is_bound_by_compute_or_memory_test() {
+        let roofline = Roofline::new(1e9, 1e9);
+        // 2 flops per 12 bytes moved, memory bound
+        let add = [meta(&[1000]), meta(&[1000])];
+        assert_eq!(roofline.cost(&MockOpCode::AddOp, &add), Some(12));
+        // 2n^3 flops per 12n^2 bytes, compute bound
+        let matmul = [meta(&[100, 100]), meta(&[100, 100])];
+        assert_eq!(roofline.cost(&MockOpCode::MatmulOp, &matmul), Some(2000));
+        let f64_matmul = vec![TensorMeta::new(vec![100, 100], DType::F64); 2];
+        assert_eq!(
+            roofline.cost(&MockOpCode::MatmulOp, &f64_matmul),
+            Some(2000)
+        );
+        assert_eq!(roofline.cost(&MockOpCode::MatmulOp, &[]), None);
+    }
+
+    #[test]
+    fn table_interpolation_test() {
+        let mut table = TableInterpolation::new();
+        assert!(table.record(
+            MockOpCode::MatmulOp,
+            &[meta(&[10, 10]), meta(&[10, 10])],
+            20
+        ));
+        assert!(!table.record(MockOpCode::MatmulOp, &[meta(&[10, 10])], 20));
+        // a single measure scales with the flops
+        let operands = [meta(&[20, 10]), meta(&[10, 10])];
+        assert_eq!(table.cost(&MockOpCode::MatmulOp, &operands), Some(40));
+
+        assert!(table.record(
+            MockOpCode::MatmulOp,
+            &[meta(&[20, 10]), meta(&[10, 10])],
+            50
+        ));
+        assert!(table.record(
+            MockOpCode::MatmulOp,
+            &[meta(&[10, 10]), meta(&[10, 10])],
+            30
+        ));
+        // between and below the measures
+        let operands = [meta(&[15, 10]), meta(&[10, 10])];
+        assert_eq!(table.cost(&MockOpCode::MatmulOp, &operands), Some(40));
+        let operands = [meta(&[1, 10]), meta(&[10, 10])];
+        assert_eq!(table.cost(&MockOpCode::MatmulOp, &operands), Some(12));
+        assert_eq!(table.interpolate(&MockOpCode::MatmulOp, 0.0), Some(10.0));
+        assert_eq!(table.interpolate(&MockOpCode::MatmulOp, -1e9), Some(0.0));
+        assert_eq!(table.cost(&MockOpCode::AddOp, &operands), None);
+    }
+}
@@ -0,0 +1,354 @@
+// LICENSE PLACEHOLDER
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cost_model::{CostModel, MockOpCode, OpCodeLike};
+
+/// how the learner turns the measured times of an opcode into its cost
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Estimator {
+    // exponentially weighted moving average, alpha is the weight of the newest
+    // measure, clamped into (0, 1]
+    Ewma { alpha: f64 },
+    // median of the latest measures, robust to the odd stall
+    WindowedMedian { window: usize },
+    Min,
+    Max,
+}
+
+impl Default for Estimator {
+    fn default() -> Self {
+        Estimator::Ewma { alpha: 0.2 }
+    }
+}
+
+/// what the learner knows about an opcode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LearnedCost {
+    pub estimate: Duration,
+    pub samples: usize,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+#[derive(Debug)]
+struct OpStats {
+    samples: usize,
+    min: Duration,
+    max: Duration,
+    // in seconds
+    ewma: f64,
+    latest: VecDeque<Duration>,
+    // the estimate handed out by the latest `take_changed`
+    reported: Option<Duration>,
+}
+
+impl OpStats {
+    fn new(elapsed: Duration) -> Self {
+        Self {
+            samples: 0,
+            min: elapsed,
+            max: elapsed,
+            ewma: elapsed.as_secs_f64(),
+            latest: VecDeque::new(),
+            reported: None,
+        }
+    }
+
+    fn observe(&mut self, estimator: Estimator, elapsed: Duration) {
+        self.samples += 1;
+        self.min = self.min.min(elapsed);
+        self.max = self.max.max(elapsed);
+        match estimator {
+            Estimator::Ewma { alpha } => {
+                let alpha = alpha.clamp(f64::EPSILON, 1.0);
+                self.ewma += alpha * (elapsed.as_secs_f64() - self.ewma);
+            }
+            Estimator::WindowedMedian { window } => {
+                if self.latest.len() == window.max(1) {
+                    self.latest.pop_front();
+                }
+                self.latest.push_back(elapsed);
+            }
+            Estimator::Min | Estimator::Max => (),
+        }
+    }
+
+    fn estimate(&self, estimator: Estimator) -> Duration {
+        match estimator {
+            Estimator::Ewma { .. } => Duration::from_secs_f64(self.ewma),
+            Estimator::WindowedMedian { .. } => {
+                let mut latest: Vec<Duration> = self.latest.iter().copied().collect();
+                latest.sort();
+                latest[latest.len() / 2]
+            }
+            Estimator::Min => self.min,
+            Estimator::Max => self.max,
+        }
+    }
+
+    fn learned(&self, estimator: Estimator) -> LearnedCost {
+        LearnedCost {
+            estimate: self.estimate(estimator),
+            samples: self.samples,
+            min: self.min,
+            max: self.max,
+        }
+    }
+}
+
+/// Definition: CostLearner collects the times the actors measure around their
+/// executor calls, keyed by opcode name, and estimates the cost of each
+/// opcode from them; clones share the same table.
+///
+/// Actors observe from their own threads, an observation only locks the
+/// stats of its opcode once the opcode is known.
+///
+/// ```
+/// use std::time::Duration;
+/// use raptors::prelude::*;
+///
+/// let learner = CostLearner::new(Estimator::Max, CostModel::new());
+/// learner.observe("AddOp", Duration::from_micros(30));
+/// learner.clone().observe("AddOp", Duration::from_micros(40));
+/// assert_eq!(learner.estimate("AddOp"), Some(Duration::from_micros(40)));
+/// assert_eq!(learner.snapshot()["AddOp"].samples, 2);
+/// // learned costs are in microseconds
+/// assert_eq!(learner.cost_model().estimate_op(&MockOpCode::AddOp), 40);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CostLearner<O = MockOpCode>
+where
+    O: OpCodeLike,
+{
+    estimator: Estimator,
+    // costs of the opcodes not measured yet
+    base: CostModel<O>,
+    stats: Arc<RwLock<HashMap<String, Mutex<OpStats>>>>,
+}
+
+impl<O> CostLearner<O>
+where
+    O: OpCodeLike,
+{
+    pub fn new(estimator: Estimator, base: CostModel<O>) -> Self {
+        Self {
+            estimator,
+            base,
+            stats: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn estimator(&self) -> Estimator {
+        self.estimator
+    }
+
+    /// record one execution of the opcode that took `elapsed`
+    pub fn observe(&self, op: &str, elapsed: Duration) {
+        {
+            let stats = self.stats.read().unwrap_or_else(PoisonError::into_inner);
+            if let Some(op_stats) = stats.get(op) {
+                op_stats
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .observe(self.estimator, elapsed);
+                return;
+            }
+        }
+        let mut stats = self.stats.write().unwrap_or_else(PoisonError::into_inner);
+        stats
+            .entry(op.to_string())
+            .or_insert_with(|| Mutex::new(OpStats::new(elapsed)))
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .observe(self.estimator, elapsed);
+    }
+
+    /// estimated time of the opcode, None until it is observed once
+    pub fn estimate(&self, op: &str) -> Option<Duration> {
+        self.stats
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(op)
+            .map(|op_stats| {
+                op_stats
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .estimate(self.estimator)
+            })
+    }
+
+    /// copy of the learned table, ordered by opcode name
+    pub fn snapshot(&self) -> BTreeMap<String, LearnedCost> {
+        self.stats
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(op, op_stats)| {
+                let learned = op_stats
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .learned(self.estimator);
+                (op.clone(), learned)
+            })
+            .collect()
+    }
+
+    /// the learned opcodes whose estimate changed since the latest call, with
+    /// their estimate; the system merges them into the model of its policy
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use raptors::prelude::*;
+    ///
+    /// let learner = CostLearner::new(Estimator::Max, CostModel::new());
+    /// learner.observe("AddOp", Duration::from_micros(30));
+    /// let changed = vec![(MockOpCode::AddOp, Duration::from_micros(30))];
+    /// assert_eq!(learner.take_changed(), changed);
+    /// learner.observe("AddOp", Duration::from_micros(20));
+    /// assert!(learner.take_changed().is_empty());
+    /// ```
+    pub fn take_changed(&self) -> Vec<(O, Duration)> {
+        let stats = self.stats.read().unwrap_or_else(PoisonError::into_inner);
+        let mut changed = Vec::new();
+        for (op, op_stats) in stats.iter() {
+            let mut op_stats = op_stats.lock().unwrap_or_else(PoisonError::into_inner);
+            let estimate = op_stats.estimate(self.estimator);
+            if op_stats.reported == Some(estimate) {
+                continue;
+            }
+            op_stats.reported = Some(estimate);
+            // names observed are the ones of O, skip any stranger all the same
+            if let Some(op) = O::from_name(op) {
+                changed.push((op, estimate));
+            }
+        }
+        changed
+    }
+
+    /// the base model with the costs of the learned opcodes replaced by their
+    /// estimates, converted into the unit of the base
+    pub fn cost_model(&self) -> CostModel<O> {
+        let mut cost_model = self.base.clone();
+        for (op, learned) in self.snapshot() {
+            if let Some(op) = O::from_name(&op) {
+                cost_model.update_learned(op, learned.estimate);
+            }
+        }
+        cost_model
+    }
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn micros(us: u64) -> Duration {
+        Duration::from_micros(us)
+    }
+
+    fn learn(estimator: Estimator, measures: &[u64]) -> Option<Duration> {
+        let learner = CostLearner::new(estimator, CostModel::new());
+        for us in measures {
+            learner.observe("ExpOp", micros(*us));
+        }
+        learner.estimate("ExpOp")
+    }
+
+    #[test]
+    fn estimators_test() {
+        let measures = [100, 300, 200, 1000, 200];
+        assert_eq!(learn(Estimator::Min, &measures), Some(micros(100)));
+        assert_eq!(learn(Estimator::Max, &measures), Some(micros(1000)));
+        assert_eq!(
+            learn(Estimator::WindowedMedian { window: 3 }, &measures),
+            Some(micros(200))
+        );
+        assert_eq!(
+            learn(Estimator::WindowedMedian { window: 0 }, &measures),
+            Some(micros(200))
+        );
+        // the first measure seeds the average
+        assert_eq!(
+            learn(Estimator::Ewma { alpha: 0.5 }, &[100, 300]),
+            Some(micros(200))
+        );
+        assert_eq!(
+            learn(Estimator::Ewma { alpha: 1.0 }, &measures),
+            Some(micros(200))
+        );
+        assert_eq!(learn(Estimator::default(), &[]), None);
+    }
+
+    #[test]
+    fn learner_is_shared_across_threads_test() {
+        let learner = CostLearner::new(Estimator::Min, CostModel::new());
+        let workers: Vec<_> = (1..=4_u64)
+            .map(|worker| {
+                let learner = learner.clone();
+                thread::spawn(move || {
+                    for round in 0..50 {
+                        learner.observe("AddOp", micros(worker * 10 + round));
+                        learner.observe("ConvOp", micros(worker * 100));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        let snapshot = learner.snapshot();
+        assert_eq!(snapshot.keys().collect::<Vec<_>>(), vec!["AddOp", "ConvOp"]);
+        assert_eq!(snapshot["AddOp"].samples, 200);
+        assert_eq!(snapshot["AddOp"].min, micros(10));
+        assert_eq!(snapshot["AddOp"].max, micros(89));
+        assert_eq!(snapshot["ConvOp"].estimate, micros(100));
+    }
+
+    #[test]
+    fn learner_reports_changed_estimates_once_test() {
+        let learner = CostLearner::new(Estimator::Min, CostModel::new());
+        assert!(learner.take_changed().is_empty());
+        learner.observe("ConvOp", micros(7));
+        learner.observe("AddOp", micros(3));
+        let mut changed = learner.take_changed();
+        changed.sort_by_key(|(op, _)| op.id());
+        assert_eq!(
+            changed,
+            vec![
+                (MockOpCode::AddOp, micros(3)),
+                (MockOpCode::ConvOp, micros(7))
+            ]
+        );
+        // clones share what has been reported
+        learner.clone().observe("ConvOp", micros(9));
+        assert!(learner.take_changed().is_empty());
+        learner.observe("ConvOp", micros(5));
+        learner.observe("CustomOp", micros(1));
+        assert_eq!(
+            learner.clone().take_changed(),
+            vec![(MockOpCode::ConvOp, micros(5))]
+        );
+    }
+
+    #[test]
+    fn learned_costs_override_base_test() {
+        let learner = CostLearner::new(Estimator::Max, CostModel::new());
+        learner.observe("ConvOp", micros(7));
+        learner.observe("CustomOp", micros(9));
+        let cost_model = learner.cost_model();
+        assert_eq!(cost_model.estimate_op(&MockOpCode::ConvOp), 7);
+        assert_eq!(cost_model.estimate_op(&MockOpCode::AddOp), 11);
+
+        let mut base = CostModel::new();
+        base.set_unit(micros(2));
+        let learner = CostLearner::new(Estimator::Max, base);
+        learner.observe("ConvOp", micros(8));
+        assert_eq!(learner.cost_model().estimate_op(&MockOpCode::ConvOp), 4);
+    }
+}
@@ -1,7 +1,18 @@
 // LICENSE PLACEHOLDER
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::Hash;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 
-use crate::tensor_types::MockTensor;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::cost_function::CostFunction;
+use crate::errors::RaptorsError;
+use crate::tensor_types::{MockTensor, TensorMeta};
 
 // Definition for Opcode
 /// ```
@@ -11,13 +22,97 @@ use crate::tensor_types::MockTensor;
 /// assert_eq!(MockOpCode::default(), MockOpCode::IdentityOp);
 /// ```
 ///
-pub trait OpCodeLike {}
+/// Opcode types of the user plug into the cost models, the profiles and the
+/// scheduling policies by their names:
+///
+/// ```
+/// use std::collections::HashMap;
+/// use raptors::prelude::*;
+///
+/// #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// enum GraphOp {
+///     Gemm,
+///     Relu,
+/// }
+///
+/// impl OpCodeLike for GraphOp {
+///     fn name(&self) -> String {
+///         format!("{:?}", self)
+///     }
+///
+///     fn from_name(name: &str) -> Option<Self> {
+///         match name {
+///             "Gemm" => Some(GraphOp::Gemm),
+///             "Relu" => Some(GraphOp::Relu),
+///             _ => None,
+///         }
+///     }
+///
+///     fn id(&self) -> usize {
+///         self.clone() as usize
+///     }
+///
+///     fn arity(&self) -> usize {
+///         match self {
+///             GraphOp::Gemm => 2,
+///             GraphOp::Relu => 1,
+///         }
+///     }
+/// }
+///
+/// let model = CostModel::set_model(HashMap::from([(GraphOp::Gemm, 40)]));
+/// assert_eq!(model.estimate_op(&GraphOp::Gemm), 40);
+/// // the mean of the known costs
+/// assert_eq!(model.estimate_op(&GraphOp::Relu), 40);
+/// ```
+// Clone is required to keep a workload replayable after an actor failure,
+// Eq and Hash to key the cost tables by opcode
+pub trait OpCodeLike: Clone + Eq + Hash {
+    /// name of the opcode in cost profiles and learned tables
+    fn name(&self) -> String;
 
-impl OpCodeLike for MockOpCode {}
+    /// the opcode of the name, the inverse of `name`
+    fn from_name(name: &str) -> Option<Self>;
+
+    /// dense id of the opcode, e.g. its index in the enum
+    fn id(&self) -> usize;
+
+    /// number of operands the opcode computes on
+    fn arity(&self) -> usize;
+}
+
+impl OpCodeLike for MockOpCode {
+    fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        name.parse().ok()
+    }
+
+    fn id(&self) -> usize {
+        *self as usize
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            MockOpCode::IdentityOp | MockOpCode::ExpOp | MockOpCode::SinOp => 1,
+            // the tensor and the target shape
+            MockOpCode::ReshapeOp => 2,
+            MockOpCode::AddOp
+            | MockOpCode::SubOp
+            | MockOpCode::MulOp
+            | MockOpCode::DivOp
+            | MockOpCode::ConvOp
+            | MockOpCode::MatmulOp => 2,
+        }
+    }
+}
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
 // Copy trait is necessary, otherwise ownership will transit into the cost model
 pub enum MockOpCode {
+    #[default]
     IdentityOp,
     AddOp,
     SubOp,
@@ -30,18 +125,139 @@ pub enum MockOpCode {
     SinOp,
 }
 
-impl Default for MockOpCode {
-    fn default() -> Self {
-        MockOpCode::IdentityOp
+impl FromStr for MockOpCode {
+    type Err = RaptorsError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "IdentityOp" => Ok(MockOpCode::IdentityOp),
+            "AddOp" => Ok(MockOpCode::AddOp),
+            "SubOp" => Ok(MockOpCode::SubOp),
+            "MulOp" => Ok(MockOpCode::MulOp),
+            "DivOp" => Ok(MockOpCode::DivOp),
+            "ConvOp" => Ok(MockOpCode::ConvOp),
+            "ExpOp" => Ok(MockOpCode::ExpOp),
+            "ReshapeOp" => Ok(MockOpCode::ReshapeOp),
+            "MatmulOp" => Ok(MockOpCode::MatmulOp),
+            "SinOp" => Ok(MockOpCode::SinOp),
+            _ => Err(RaptorsError::config(format!("unknown opcode {}", name))),
+        }
+    }
+}
+
+// an entry of a cost profile is either the cost of an opcode or the section
+// of an executor kind, which holds the costs measured on that kind
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ProfileEntry {
+    Cost(usize),
+    Kind(BTreeMap<String, usize>),
+}
+
+type Profile = BTreeMap<String, ProfileEntry>;
+
+#[derive(Clone, Copy, Debug)]
+enum ProfileFormat {
+    Json,
+    Toml,
+}
+
+impl ProfileFormat {
+    fn of(path: &Path) -> Result<Self, RaptorsError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(ProfileFormat::Json),
+            Some("toml") => Ok(ProfileFormat::Toml),
+            _ => Err(RaptorsError::config(format!(
+                "cost profile {} is neither .json nor .toml",
+                path.display()
+            ))),
+        }
     }
 }
 
-/// Definition: The estimator helps to compute the estimated cost for different ops.
+fn parse_costs<O: OpCodeLike>(
+    costs: BTreeMap<String, usize>,
+    section: &str,
+) -> Result<HashMap<O, usize>, RaptorsError> {
+    costs
+        .into_iter()
+        .map(|(name, cost)| match O::from_name(&name) {
+            Some(op) => Ok((op, cost)),
+            None => Err(RaptorsError::config(format!(
+                "unknown opcode {} in section {}",
+                name, section
+            ))),
+        })
+        .collect()
+}
+
+fn named_costs<O: OpCodeLike>(costs: &HashMap<O, usize>) -> BTreeMap<String, usize> {
+    costs.iter().map(|(op, cost)| (op.name(), *cost)).collect()
+}
+
+/// the cost of an opcode the table has no cost for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CostFallback {
+    // the mean of the costs in the table, 1 for an empty table
+    #[default]
+    MeanOfKnown,
+    Default(usize),
+}
+
+/// Definition: The estimator helps to compute the estimated cost for different ops,
+/// of any opcode type; the default table is the one of MockOpCode.
 ///
 /// backdoors for mocking tests are also provided by this class.
-#[derive(Clone, Debug, PartialEq)]
-pub struct CostModel {
-    cost_model: HashMap<MockOpCode, usize>,
+///
+/// Costs can be loaded from JSON or TOML profiles keyed by opcode name, a
+/// table of opcodes named after an executor kind holds the costs measured on
+/// that kind, they override the shared ones for the actors of that kind; the
+/// kind of an actor is the name its executor is registered under, see
+/// `LeastLoaded` for a policy estimating with it.
+///
+/// ```toml
+/// AddOp = 11
+/// MatmulOp = 57
+///
+/// [cpu]
+/// MatmulOp = 23
+/// ```
+///
+/// A cost function, e.g. `Roofline`, makes the estimates of workloads aware
+/// of the shapes and dtypes of their operands, the table is the fallback for
+/// the workloads it has no estimate for. Profiles hold the table only.
+///
+/// Costs are counted in the unit of the model, a microsecond by default, the
+/// estimates of the cost function and the times measured by a `CostLearner`
+/// are converted into it, so that the policies compare like with like.
+#[derive(Clone, Debug)]
+pub struct CostModel<O = MockOpCode>
+where
+    O: OpCodeLike,
+{
+    cost_model: HashMap<O, usize>,
+    kinds: BTreeMap<String, HashMap<O, usize>>,
+    function: Option<Arc<dyn CostFunction<O>>>,
+    fallback: CostFallback,
+    // the time a cost of 1 stands for
+    unit: Duration,
+    // estimates of each opcode missing in the table, shared by the clones
+    missing: Arc<Mutex<HashMap<O, usize>>>,
+}
+
+// models share a cost function only by sharing the same instance
+impl<O: OpCodeLike> PartialEq for CostModel<O> {
+    fn eq(&self, other: &Self) -> bool {
+        let same_function = match (&self.function, &other.function) {
+            (Some(lhs), Some(rhs)) => Arc::ptr_eq(lhs, rhs),
+            (lhs, rhs) => lhs.is_none() && rhs.is_none(),
+        };
+        self.cost_model == other.cost_model
+            && self.kinds == other.kinds
+            && self.fallback == other.fallback
+            && self.unit == other.unit
+            && same_function
+    }
 }
 
 impl Default for CostModel {
@@ -57,37 +273,297 @@ impl Default for CostModel {
         cost_model.insert(MockOpCode::ReshapeOp, 37);
         cost_model.insert(MockOpCode::MatmulOp, 57);
         cost_model.insert(MockOpCode::SinOp, 127);
-        return Self {
-            cost_model: cost_model,
-        };
+        Self::set_model(cost_model)
     }
 }
 
 /// TODO set a helper to build the estimator
-/// TODO estimator read files, like json, to update the cost model
 //
-impl CostModel {
+impl CostModel<MockOpCode> {
     pub fn new() -> Self {
         CostModel::default()
     }
 
-    // TODO support load cost model from deserialize from proto files
-    pub fn set_model(cost_model: HashMap<MockOpCode, usize>) -> Self {
-        return Self {
-            cost_model: cost_model,
-        };
+    pub fn estimate(&self, workload: &MockTensor) -> usize {
+        self.estimate_op(&workload.op())
+    }
+}
+
+impl<O> CostModel<O>
+where
+    O: OpCodeLike,
+{
+    pub fn set_model(cost_model: HashMap<O, usize>) -> Self {
+        Self {
+            cost_model,
+            kinds: BTreeMap::new(),
+            function: None,
+            fallback: CostFallback::default(),
+            unit: Duration::from_micros(1),
+            missing: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn set_fallback(&mut self, fallback: CostFallback) {
+        self.fallback = fallback;
     }
 
-    pub fn cost_model(&self) -> HashMap<MockOpCode, usize> {
+    pub fn fallback(&self) -> CostFallback {
+        self.fallback
+    }
+
+    pub fn set_unit(&mut self, unit: Duration) {
+        self.unit = unit.max(Duration::from_nanos(1));
+    }
+
+    pub fn unit(&self) -> Duration {
+        self.unit
+    }
+
+    /// cost of a workload that takes `elapsed`, in the unit of the model
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use raptors::prelude::*;
+    ///
+    /// let mut model = CostModel::new();
+    /// assert_eq!(model.cost_of(Duration::from_millis(2)), 2000);
+    /// model.set_unit(Duration::from_millis(1));
+    /// assert_eq!(model.cost_of(Duration::from_millis(2)), 2);
+    /// ```
+    pub fn cost_of(&self, elapsed: Duration) -> usize {
+        (elapsed.as_secs_f64() / self.unit.as_secs_f64()).round() as usize
+    }
+
+    /// estimate the workloads with shaped operands by the function
+    pub fn set_function(&mut self, function: Arc<dyn CostFunction<O>>) {
+        self.function = Some(function);
+    }
+
+    pub fn function(&self) -> Option<&Arc<dyn CostFunction<O>>> {
+        self.function.as_ref()
+    }
+
+    /// load the profile at the path, the format follows the extension, either
+    /// .json or .toml; the opcodes missing in the profile get the fallback cost
+    ///
+    /// ```
+    /// use raptors::prelude::*;
+    ///
+    /// let name = format!("raptors-{}-doc-profile.json", std::process::id());
+    /// let path = std::env::temp_dir().join(name);
+    /// std::fs::write(&path, r#"{ "AddOp": 3, "cpu": { "AddOp": 1 } }"#).unwrap();
+    /// let model = CostModel::from_path(&path).unwrap();
+    /// assert_eq!(model.estimate_op(&MockOpCode::AddOp), 3);
+    /// assert_eq!(model.estimate_op_on("cpu", &MockOpCode::AddOp), 1);
+    ///
+    /// std::fs::write(&path, r#"{ "FooOp": 3 }"#).unwrap();
+    /// assert!(CostModel::<MockOpCode>::from_path(&path).is_err());
+    /// ```
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, RaptorsError> {
+        let path = path.as_ref();
+        let format = ProfileFormat::of(path)?;
+        let text = fs::read_to_string(path).map_err(|e| {
+            RaptorsError::config(format!(
+                "fail to read cost profile {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let profile: Profile = match format {
+            ProfileFormat::Json => serde_json::from_str(&text).map_err(|e| e.to_string()),
+            ProfileFormat::Toml => toml::from_str(&text).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| {
+            RaptorsError::config(format!("malformed cost profile {}: {}", path.display(), e))
+        })?;
+        Self::from_profile(profile)
+    }
+
+    fn from_profile(profile: Profile) -> Result<Self, RaptorsError> {
+        let mut model = Self::set_model(HashMap::new());
+        for (key, entry) in profile {
+            match entry {
+                ProfileEntry::Cost(cost) => {
+                    let op = O::from_name(&key)
+                        .ok_or_else(|| RaptorsError::config(format!("unknown opcode {}", key)))?;
+                    model.cost_model.insert(op, cost);
+                }
+                ProfileEntry::Kind(costs) => {
+                    let costs = parse_costs(costs, &key)?;
+                    model.kinds.insert(key, costs);
+                }
+            }
+        }
+        Ok(model)
+    }
+
+    /// write the model as a profile that `from_path` loads back
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RaptorsError> {
+        let path = path.as_ref();
+        let format = ProfileFormat::of(path)?;
+        let mut profile: Profile = named_costs(&self.cost_model)
+            .into_iter()
+            .map(|(name, cost)| (name, ProfileEntry::Cost(cost)))
+            .collect();
+        for (kind, costs) in self.kinds.iter() {
+            profile.insert(kind.clone(), ProfileEntry::Kind(named_costs(costs)));
+        }
+        let text = match format {
+            ProfileFormat::Json => {
+                serde_json::to_string_pretty(&profile).map_err(|e| e.to_string())
+            }
+            ProfileFormat::Toml => toml::to_string(&profile).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| RaptorsError::config(format!("fail to encode cost profile: {}", e)))?;
+        fs::write(path, text).map_err(|e| {
+            RaptorsError::config(format!(
+                "fail to write cost profile {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    pub fn cost_model(&self) -> HashMap<O, usize> {
         self.cost_model.clone()
     }
 
-    pub fn estimate(&self, workload: &MockTensor) -> usize {
-        *self.cost_model.get(&workload.op()).unwrap()
+    /// executor kinds with a section of their own
+    pub fn kinds(&self) -> Vec<String> {
+        self.kinds.keys().cloned().collect()
     }
 
-    // TODO support update with moving average strategy
-    pub fn update_model(&mut self, op: MockOpCode, new_cost: usize) -> () {
+    /// set the cost of the op on the actors of the executor kind
+    pub fn update_kind(&mut self, kind: &str, op: O, new_cost: usize) {
+        self.kinds
+            .entry(kind.to_string())
+            .or_default()
+            .insert(op, new_cost);
+    }
+
+    /// cost of the op on an actor of the executor kind, the shared cost is used
+    /// if the kind has no cost of its own for the op
+    pub fn estimate_op_on(&self, kind: &str, op: &O) -> usize {
+        match self.kinds.get(kind).and_then(|costs| costs.get(op)) {
+            Some(cost) => *cost,
+            None => self.estimate_op(op),
+        }
+    }
+
+    /// cost of the op by the table, the fallback cost if the table has none;
+    /// the miss is recorded, see `missing`
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use raptors::prelude::*;
+    ///
+    /// let mut model = CostModel::set_model(HashMap::from([(MockOpCode::AddOp, 10)]));
+    /// assert!(model.try_estimate_op(&MockOpCode::ConvOp).is_err());
+    /// assert_eq!(model.estimate_op(&MockOpCode::ConvOp), 10);
+    /// model.set_fallback(CostFallback::Default(100));
+    /// assert_eq!(model.estimate_op(&MockOpCode::ConvOp), 100);
+    /// assert_eq!(model.missing()[&MockOpCode::ConvOp], 2);
+    /// ```
+    pub fn estimate_op(&self, op: &O) -> usize {
+        match self.try_estimate_op(op) {
+            Ok(cost) => cost,
+            Err(_) => {
+                let cost = self.fallback_cost();
+                self.record_missing(op, cost);
+                cost
+            }
+        }
+    }
+
+    /// cost of the op by the table, an error if the table has none
+    pub fn try_estimate_op(&self, op: &O) -> Result<usize, RaptorsError> {
+        self.cost_model
+            .get(op)
+            .copied()
+            .ok_or_else(|| RaptorsError::config(format!("no cost for opcode {}", op.name())))
+    }
+
+    fn fallback_cost(&self) -> usize {
+        match self.fallback {
+            CostFallback::Default(cost) => cost,
+            CostFallback::MeanOfKnown if self.cost_model.is_empty() => 1,
+            CostFallback::MeanOfKnown => {
+                self.cost_model.values().sum::<usize>() / self.cost_model.len()
+            }
+        }
+    }
+
+    // warn once per opcode, the scheduler estimates workloads over and over
+    fn record_missing(&self, op: &O, cost: usize) {
+        let mut missing = self.missing.lock().unwrap_or_else(PoisonError::into_inner);
+        let cnt = missing.entry(op.clone()).or_insert(0);
+        *cnt += 1;
+        if *cnt == 1 {
+            warn!(
+                "::cost-model::no cost for {}, estimated as {} by {:?}",
+                op.name(),
+                cost,
+                self.fallback
+            );
+        } else {
+            debug!(
+                "::cost-model::no cost for {}, estimated as {}",
+                op.name(),
+                cost
+            );
+        }
+    }
+
+    /// how many times each opcode missing in the table has been estimated
+    pub fn missing(&self) -> HashMap<O, usize> {
+        self.missing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// cost of the op on the operands by the cost function, by the table if
+    /// there is no function or it has no estimate
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use raptors::prelude::*;
+    ///
+    /// let mut model = CostModel::new();
+    /// let operands = vec![TensorMeta::new(vec![64, 64], DType::F32); 2];
+    /// assert_eq!(model.estimate_with(&MockOpCode::MatmulOp, &operands), 57);
+    /// model.set_function(Arc::new(Roofline::new(1e9, 1e9)));
+    /// assert_eq!(model.estimate_with(&MockOpCode::MatmulOp, &operands), 525);
+    /// assert_eq!(model.estimate_with(&MockOpCode::MatmulOp, &[]), 57);
+    /// // the microseconds of the function are converted into the unit
+    /// model.set_unit(Duration::from_micros(100));
+    /// assert_eq!(model.estimate_with(&MockOpCode::MatmulOp, &operands), 5);
+    /// ```
+    pub fn estimate_with(&self, op: &O, operands: &[TensorMeta]) -> usize {
+        match self.function.as_ref().and_then(|f| f.cost(op, operands)) {
+            Some(micros) => self.cost_of(Duration::from_micros(micros as u64)),
+            None => self.estimate_op(op),
+        }
+    }
+
+    /// as `estimate_with` on an actor of the executor kind, the table of the
+    /// kind is the fallback of the cost function
+    pub fn estimate_with_on(&self, kind: &str, op: &O, operands: &[TensorMeta]) -> usize {
+        match self.function.as_ref().and_then(|f| f.cost(op, operands)) {
+            Some(micros) => self.cost_of(Duration::from_micros(micros as u64)),
+            None => self.estimate_op_on(kind, op),
+        }
+    }
+
+    /// set the cost of the op to the time it is measured to take
+    pub fn update_learned(&mut self, op: O, elapsed: Duration) {
+        let cost = self.cost_of(elapsed);
+        self.update_model(op, cost);
+    }
+
+    pub fn update_model(&mut self, op: O, new_cost: usize) {
         match self.cost_model.get_mut(&op) {
             Some(cost) => {
                 *cost = new_cost;
@@ -99,6 +575,40 @@ impl CostModel {
     }
 }
 
+// opcodes of a user graph, the unit tests of the crate plug them in where
+// an opcode type other than MockOpCode is needed
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum GraphOp {
+    Gemm,
+    Relu,
+    Softmax,
+}
+
+#[cfg(test)]
+impl OpCodeLike for GraphOp {
+    fn name(&self) -> String {
+        format!("{:?}", self).to_lowercase()
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [GraphOp::Gemm, GraphOp::Relu, GraphOp::Softmax]
+            .into_iter()
+            .find(|op| op.name() == name)
+    }
+
+    fn id(&self) -> usize {
+        *self as usize
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            GraphOp::Gemm => 2,
+            GraphOp::Relu | GraphOp::Softmax => 1,
+        }
+    }
+}
+
 // tests
 #[cfg(test)]
 mod tests {
@@ -171,7 +681,7 @@ mod tests {
             Some((&MockOpCode::IdentityOp, &8))
         );
 
-        assert_eq!(est.cost_model.contains_key(&MockOpCode::ConvOp), false);
+        assert!(!est.cost_model.contains_key(&MockOpCode::ConvOp));
         est.update_model(MockOpCode::ConvOp, 100);
         assert!(est.cost_model.contains_key(&MockOpCode::ConvOp));
         assert_eq!(
@@ -179,4 +689,147 @@ mod tests {
             Some((&MockOpCode::ConvOp, &100))
         );
     }
+
+    fn profile_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("raptors-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn opcode_names_test() {
+        assert_eq!(MockOpCode::MatmulOp.name(), "MatmulOp");
+        assert_eq!("MatmulOp".parse::<MockOpCode>(), Ok(MockOpCode::MatmulOp));
+        assert_eq!(
+            "matmul".parse::<MockOpCode>(),
+            Err(RaptorsError::config("unknown opcode matmul"))
+        );
+    }
+
+    #[test]
+    fn profiles_round_trip_test() {
+        let mut est = CostModel::new();
+        est.update_kind("cpu", MockOpCode::MatmulOp, 23);
+        est.update_kind("gpu", MockOpCode::ConvOp, 5);
+        for name in ["profile.json", "profile.toml"] {
+            let path = profile_path(name);
+            est.save(&path).unwrap();
+            assert_eq!(CostModel::from_path(&path), Ok(est.clone()));
+        }
+        assert_eq!(est.kinds(), vec!["cpu", "gpu"]);
+        assert_eq!(est.estimate_op_on("cpu", &MockOpCode::MatmulOp), 23);
+        assert_eq!(est.estimate_op_on("gpu", &MockOpCode::MatmulOp), 57);
+        assert_eq!(est.estimate_op_on("tpu", &MockOpCode::ConvOp), 107);
+    }
+
+    #[test]
+    fn load_handwritten_profiles_test() {
+        let path = profile_path("handwritten.toml");
+        fs::write(&path, "AddOp = 3\nSinOp = 9\n\n[cpu]\nAddOp = 1\n").unwrap();
+        let est = CostModel::from_path(&path).unwrap();
+        assert_eq!(est.cost_model().len(), 2);
+        assert_eq!(est.estimate(&MockTensor::new(MockOpCode::SinOp)), 9);
+        assert_eq!(est.estimate_op_on("cpu", &MockOpCode::AddOp), 1);
+
+        let path = profile_path("handwritten.json");
+        fs::write(&path, r#"{ "ExpOp": 4, "gpu": { "ConvOp": 2 } }"#).unwrap();
+        let est = CostModel::from_path(&path).unwrap();
+        assert_eq!(est.estimate_op_on("gpu", &MockOpCode::ConvOp), 2);
+        assert_eq!(est.estimate_op_on("gpu", &MockOpCode::ExpOp), 4);
+    }
+
+    #[test]
+    fn invalid_profiles_test() {
+        let path = profile_path("unknown.json");
+        fs::write(&path, r#"{ "AddOp": 3, "cpu": { "FooOp": 1 } }"#).unwrap();
+        assert_eq!(
+            CostModel::<MockOpCode>::from_path(&path),
+            Err(RaptorsError::config("unknown opcode FooOp in section cpu"))
+        );
+
+        let path = profile_path("negative.json");
+        fs::write(&path, r#"{ "AddOp": -3 }"#).unwrap();
+        assert!(matches!(
+            CostModel::<MockOpCode>::from_path(&path),
+            Err(RaptorsError::Config { .. })
+        ));
+
+        assert!(CostModel::<MockOpCode>::from_path(profile_path("absent.toml")).is_err());
+        assert!(CostModel::new().save(profile_path("profile.yaml")).is_err());
+    }
+
+    #[test]
+    fn estimate_partial_table_test() {
+        let mut model = HashMap::new();
+        model.insert(MockOpCode::AddOp, 10);
+        model.insert(MockOpCode::SubOp, 21);
+        let mut est = CostModel::set_model(model);
+        let shared = est.clone();
+
+        assert_eq!(est.try_estimate_op(&MockOpCode::AddOp), Ok(10));
+        assert_eq!(
+            est.try_estimate_op(&MockOpCode::ExpOp),
+            Err(RaptorsError::config("no cost for opcode ExpOp"))
+        );
+        assert_eq!(est.estimate(&MockTensor::new(MockOpCode::ExpOp)), 15);
+        assert_eq!(est.estimate_op_on("cpu", &MockOpCode::ExpOp), 15);
+        est.set_fallback(CostFallback::Default(3));
+        assert_eq!(est.estimate_op(&MockOpCode::ConvOp), 3);
+        let operands = [crate::tensor_types::TensorMeta::new(
+            vec![2],
+            crate::tensor_types::DType::F32,
+        )];
+        assert_eq!(est.estimate_with(&MockOpCode::ConvOp, &operands), 3);
+        // a failed estimate by the try_ variant is not a miss
+        assert_eq!(shared.missing().get(&MockOpCode::ExpOp), Some(&2));
+        assert_eq!(shared.missing().get(&MockOpCode::ConvOp), Some(&2));
+        assert!(!shared.missing().contains_key(&MockOpCode::AddOp));
+
+        assert_eq!(
+            CostModel::set_model(HashMap::new()).estimate_op(&MockOpCode::AddOp),
+            1
+        );
+    }
+
+    #[test]
+    fn partial_profile_estimates_every_opcode_test() {
+        let path = profile_path("partial.toml");
+        fs::write(&path, "MatmulOp = 40\n\n[cpu]\nConvOp = 9\n").unwrap();
+        let est = CostModel::from_path(&path).unwrap();
+        for name in ["IdentityOp", "AddOp", "ConvOp", "MatmulOp", "SinOp"] {
+            let op: MockOpCode = name.parse().unwrap();
+            assert_eq!(est.estimate_op(&op), 40);
+        }
+        assert_eq!(est.estimate_op_on("cpu", &MockOpCode::ConvOp), 9);
+        assert_eq!(est.missing().len(), 4);
+    }
+
+    #[test]
+    fn user_opcode_profiles_test() {
+        let path = profile_path("graph.toml");
+        fs::write(
+            &path,
+            "gemm = 40
+relu = 2
+
+[gpu]
+gemm = 4
+",
+        )
+        .unwrap();
+        let est = CostModel::<GraphOp>::from_path(&path).unwrap();
+        assert_eq!(est.estimate_op(&GraphOp::Gemm), 40);
+        assert_eq!(est.estimate_op_on("gpu", &GraphOp::Gemm), 4);
+        assert_eq!(est.estimate_op_on("gpu", &GraphOp::Relu), 2);
+        assert_eq!(est.estimate_op(&GraphOp::Softmax), 21);
+        assert_eq!(est.missing(), HashMap::from([(GraphOp::Softmax, 1)]));
+
+        let path = profile_path("graph.json");
+        est.save(&path).unwrap();
+        assert_eq!(CostModel::from_path(&path), Ok(est));
+
+        fs::write(&path, r#"{ "gemm": 40, "Relu": 2 }"#).unwrap();
+        assert_eq!(
+            CostModel::<GraphOp>::from_path(&path),
+            Err(RaptorsError::config("unknown opcode Relu"))
+        );
+    }
 }
@@ -0,0 +1,704 @@
+// LICENSE PLACEHOLDER
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Debug;
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::cost_model::{CostModel, OpCodeLike};
+use crate::messages::RaptorMessage;
+use crate::scheduling::{Fifo, SchedulingPolicy};
+use crate::tensor_types::TensorLike;
+
+/// Definition: PendingQueue holds the workloads waiting for an actor, in
+/// arrival order; requeued workloads of failed actors go to the front.
+///
+/// Both ends are O(1), the policy may pick a workload anywhere in between.
+#[derive(Debug)]
+pub struct PendingQueue<U, O>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    msgs: VecDeque<RaptorMessage<U, O>>,
+}
+
+impl<U, O> Default for PendingQueue<U, O>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    fn default() -> Self {
+        Self {
+            msgs: VecDeque::new(),
+        }
+    }
+}
+
+impl<U, O> PendingQueue<U, O>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.msgs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.msgs.is_empty()
+    }
+
+    pub fn push(&mut self, msg: RaptorMessage<U, O>) {
+        self.msgs.push_back(msg);
+    }
+
+    pub fn requeue(&mut self, msg: RaptorMessage<U, O>) {
+        self.msgs.push_front(msg);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RaptorMessage<U, O>> {
+        self.msgs.iter()
+    }
+
+    /// the workloads in arrival order as one slice, for the policy to pick from
+    pub fn make_contiguous(&mut self) -> &[RaptorMessage<U, O>] {
+        self.msgs.make_contiguous()
+    }
+
+    /// take every pending workload out, in arrival order
+    pub fn take_all(&mut self) -> Vec<RaptorMessage<U, O>> {
+        std::mem::take(&mut self.msgs).into()
+    }
+
+    // take the workloads matching the predicate out, in arrival order
+    fn take_if(
+        &mut self,
+        mut pred: impl FnMut(&RaptorMessage<U, O>) -> bool,
+    ) -> Vec<RaptorMessage<U, O>> {
+        let (taken, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.msgs)
+            .into_iter()
+            .partition(|x| pred(x));
+        self.msgs = kept.into();
+        taken
+    }
+
+    fn get(&self, idx: usize) -> Option<&RaptorMessage<U, O>> {
+        self.msgs.get(idx)
+    }
+
+    fn take(&mut self, idx: usize) -> Option<RaptorMessage<U, O>> {
+        self.msgs.remove(idx)
+    }
+
+    // merge the workloads taken out back in, every workload left in the queue
+    // or taken out comes with its former position
+    fn restore(&mut self, origins: VecDeque<usize>, taken: Vec<(usize, RaptorMessage<U, O>)>) {
+        let mut msgs: Vec<_> = origins
+            .into_iter()
+            .zip(std::mem::take(&mut self.msgs))
+            .chain(taken)
+            .collect();
+        msgs.sort_by_key(|(origin, _)| *origin);
+        self.msgs = msgs.into_iter().map(|(_, msg)| msg).collect();
+    }
+}
+
+// executor typeid of the pool a workload is issued to, None for any actor
+fn pool_of<U, O>(msg: &RaptorMessage<U, O>) -> Option<usize>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    match msg {
+        RaptorMessage::PoolMSG(pool, _) => Some(*pool),
+        _ => None,
+    }
+}
+
+/// outcome of routing a workload
+#[derive(Debug)]
+pub enum Route<U, O>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    // deliver the msg to the actor of the id
+    Deliver(usize, RaptorMessage<U, O>),
+    // no actor can take it now, the msg is pending
+    Delayed,
+    // no live actor can ever take it
+    Dropped(RaptorMessage<U, O>),
+}
+
+/// Definition: Dispatcher owns the book-keeping of the actor system about
+/// which actor takes which workload: live and available actors, workloads
+/// in flight per actor and the pending queue, with the scheduling policy
+/// making the decisions.
+///
+/// It never sends a msg, it tells the system where to deliver instead.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// let mut dispatcher = Dispatcher::<MockTensor, MockOpCode>::new();
+/// dispatcher.add_actor(0, 0);
+/// assert!(matches!(dispatcher.route(build_msg!("add-op")), Route::Deliver(0, _)));
+/// assert!(matches!(dispatcher.route(build_msg!("exp-op")), Route::Delayed));
+/// dispatcher.on_available(0);
+/// assert_eq!(dispatcher.drain().len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct Dispatcher<U, O>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    policy: Box<dyn SchedulingPolicy<U, O>>,
+    // executor typeid of each live actor
+    actors: BTreeMap<usize, usize>,
+    availables: Vec<usize>,
+    // workloads delivered to each actor but not acknowledged yet
+    outstanding: HashMap<usize, usize>,
+    pending: PendingQueue<U, O>,
+}
+
+impl<U, O> Default for Dispatcher<U, O>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    fn default() -> Self {
+        Self::new_with_policy(Box::new(Fifo::new()))
+    }
+}
+
+impl<U, O> Dispatcher<U, O>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn new_with_policy(policy: Box<dyn SchedulingPolicy<U, O>>) -> Self {
+        Self {
+            policy,
+            actors: BTreeMap::new(),
+            availables: vec![],
+            outstanding: HashMap::new(),
+            pending: PendingQueue::new(),
+        }
+    }
+
+    pub fn set_policy(&mut self, policy: Box<dyn SchedulingPolicy<U, O>>) {
+        self.policy = policy;
+    }
+
+    pub fn set_cost_model(&mut self, cost_model: &CostModel<O>) {
+        self.policy.set_cost_model(cost_model);
+    }
+
+    pub fn learn_cost(&mut self, op: &O, elapsed: Duration) {
+        self.policy.learn_cost(op, elapsed);
+    }
+
+    pub fn availables(&self) -> &[usize] {
+        &self.availables
+    }
+
+    pub fn pending(&self) -> &PendingQueue<U, O> {
+        &self.pending
+    }
+
+    pub fn outstanding(&self, actor: usize) -> usize {
+        self.outstanding.get(&actor).copied().unwrap_or(0)
+    }
+
+    pub fn is_live(&self, actor: usize) -> bool {
+        self.actors.contains_key(&actor)
+    }
+
+    /// drained when no workload is waiting for an actor nor running on one
+    pub fn is_drained(&self) -> bool {
+        self.pending.is_empty() && self.outstanding.values().all(|x| *x == 0)
+    }
+
+    /// a new or restarted actor; it is available once no workload is
+    /// outstanding on it
+    pub fn add_actor(&mut self, actor: usize, typeid: usize) {
+        self.actors.insert(actor, typeid);
+        if self.outstanding(actor) == 0 && !self.availables.contains(&actor) {
+            self.availables.push(actor);
+        }
+    }
+
+    /// the actor runs an executor registered under the kind, as `add_actor`
+    pub fn add_actor_of_kind(&mut self, actor: usize, typeid: usize, kind: &str) {
+        self.add_actor(actor, typeid);
+        self.policy.on_actor_kind(actor, kind);
+    }
+
+    /// the actor is halted, the workloads queued to it are still acknowledged;
+    /// returns the pending pool workloads no actor is left to take
+    pub fn remove_actor(&mut self, actor: usize) -> Vec<RaptorMessage<U, O>> {
+        self.forget_actor(actor);
+        self.take_stranded()
+    }
+
+    /// the actor is gone together with its mailbox, returns the number of
+    /// workloads lost with it; it may be restarted, its pool keeps waiting
+    pub fn lose_actor(&mut self, actor: usize) -> usize {
+        self.forget_actor(actor);
+        self.outstanding.remove(&actor).unwrap_or(0)
+    }
+
+    /// the actor is replaced by a fresh one under the same id; the former one
+    /// finishes the workloads queued to it on its own, they are not waited for
+    pub fn retire_actor(&mut self, actor: usize) {
+        self.forget_actor(actor);
+        self.outstanding.remove(&actor);
+    }
+
+    pub fn remove_all(&mut self) -> Vec<RaptorMessage<U, O>> {
+        let actors: Vec<usize> = self.actors.keys().copied().collect();
+        for actor in actors {
+            self.forget_actor(actor);
+        }
+        self.take_stranded()
+    }
+
+    fn forget_actor(&mut self, actor: usize) {
+        self.actors.remove(&actor);
+        self.availables.retain(|x| *x != actor);
+        self.policy.on_actor_lost(actor);
+    }
+
+    // pending pool workloads whose pool has no live actor, as routing drops
+    // the ones issued to such a pool
+    fn take_stranded(&mut self) -> Vec<RaptorMessage<U, O>> {
+        let pools: Vec<usize> = self.actors.values().copied().collect();
+        self.pending.take_if(|msg| match msg {
+            RaptorMessage::PoolMSG(pool, _) => !pools.contains(pool),
+            _ => false,
+        })
+    }
+
+    pub fn requeue(&mut self, msg: RaptorMessage<U, O>) {
+        self.pending.requeue(msg);
+    }
+
+    /// take every pending workload out of the queue, e.g. to drop them
+    pub fn take_pending(&mut self) -> Vec<RaptorMessage<U, O>> {
+        self.pending.take_all()
+    }
+
+    // live actors that may take the workload, a pool workload only goes to the
+    // actors of its executor typeid
+    fn candidates(&self, msg: &RaptorMessage<U, O>) -> Vec<usize> {
+        self.actors
+            .iter()
+            .filter(|(_, typeid)| match msg {
+                RaptorMessage::PoolMSG(pool, _) => *typeid == pool,
+                _ => true,
+            })
+            .map(|(actor, _)| *actor)
+            .collect()
+    }
+
+    // ask the scheduling policy for the actor to take the workload, the chosen
+    // actor is polled out from the avlb-queue if it is idle
+    fn select_actor(&mut self, msg: &RaptorMessage<U, O>) -> Option<usize> {
+        let lives = self.candidates(msg);
+        let availables: Vec<usize> = self
+            .availables
+            .iter()
+            .filter(|x| lives.contains(x))
+            .copied()
+            .collect();
+        let idx = self.policy.select_actor(msg, &availables, &lives)?;
+        self.availables.retain(|x| *x != idx);
+        Some(idx)
+    }
+
+    // the workload is on its way to the actor
+    fn assign(&mut self, actor: usize, msg: &RaptorMessage<U, O>) {
+        self.policy.on_assigned(actor, msg);
+        *self.outstanding.entry(actor).or_insert(0) += 1;
+    }
+
+    /// find the actor to take the workload, it is delayed if none is free;
+    /// workloads pinned to an actor that is not live are dropped
+    pub fn route(&mut self, msg: RaptorMessage<U, O>) -> Route<U, O> {
+        if let RaptorMessage::PinnedMSG(actor, _) = msg {
+            if !self.is_live(actor) {
+                return Route::Dropped(msg);
+            }
+            // pinned by a static schedule, the policy is bypassed
+            self.availables.retain(|x| *x != actor);
+            self.assign(actor, &msg);
+            return Route::Deliver(actor, msg);
+        }
+        if self.candidates(&msg).is_empty() && matches!(msg, RaptorMessage::PoolMSG(..)) {
+            return Route::Dropped(msg);
+        }
+        match self.select_actor(&msg) {
+            Some(actor) => {
+                self.assign(actor, &msg);
+                Route::Deliver(actor, msg)
+            }
+            None => {
+                info!("::dispatcher::not-find avlb-actor, delay the workload");
+                self.pending.push(msg);
+                Route::Delayed
+            }
+        }
+    }
+
+    /// hand pending workloads over to available actors, as many as possible;
+    /// once no actor takes a workload of a pool, the other workloads of that
+    /// pool are skipped until the next drain, as assigning never frees an actor
+    pub fn drain(&mut self) -> Vec<(usize, RaptorMessage<U, O>)> {
+        let mut deliveries = vec![];
+        // position of each pending workload as the drain started, the skipped
+        // ones are put back in that order
+        let mut origins: VecDeque<usize> = (0..self.pending.len()).collect();
+        let mut skipped = vec![];
+        while let Some(picked) = self.policy.select_workload(self.pending.make_contiguous()) {
+            let (msg, origin) = match (self.pending.take(picked), origins.remove(picked)) {
+                (Some(msg), Some(origin)) => (msg, origin),
+                _ => break,
+            };
+            if let Some(actor) = self.select_actor(&msg) {
+                self.assign(actor, &msg);
+                deliveries.push((actor, msg));
+                continue;
+            }
+            let pool = pool_of(&msg);
+            skipped.push((origin, msg));
+            for idx in (0..self.pending.len()).rev() {
+                if self.pending.get(idx).map(pool_of) == Some(pool) {
+                    let msg = self.pending.take(idx).expect("index in the queue");
+                    skipped.push((origins.remove(idx).expect("one origin per workload"), msg));
+                }
+            }
+        }
+        if !skipped.is_empty() {
+            self.pending.restore(origins, skipped);
+        }
+        deliveries
+    }
+
+    /// the actor acknowledged its oldest workload
+    pub fn on_available(&mut self, actor: usize) {
+        let outstanding = self.outstanding.entry(actor).or_insert(0);
+        *outstanding = outstanding.saturating_sub(1);
+        // a halted actor still acks the workloads queued to it, only live
+        // actors become available again
+        if *outstanding == 0 && self.is_live(actor) && !self.availables.contains(&actor) {
+            self.availables.push(actor);
+        }
+        self.policy.on_completed(actor);
+    }
+
+    /// the workload routed to the actor never reached it
+    pub fn on_undelivered(&mut self, actor: usize, msg: &RaptorMessage<U, O>) {
+        let outstanding = self.outstanding.entry(actor).or_insert(0);
+        *outstanding = outstanding.saturating_sub(1);
+        self.policy.on_undelivered(actor, msg);
+    }
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost_model::MockOpCode;
+    use crate::messages::LoadfreeMessage;
+    use crate::scheduling::{LeastLoaded, ShortestJobFirst};
+    use crate::tensor_types::MockTensor;
+
+    fn mock_msg(op: MockOpCode) -> RaptorMessage<MockTensor, MockOpCode> {
+        RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(MockTensor::new(op)))
+    }
+
+    fn delivered_to(route: Route<MockTensor, MockOpCode>) -> Option<usize> {
+        match route {
+            Route::Deliver(actor, _) => Some(actor),
+            _ => None,
+        }
+    }
+
+    fn pending_ops(pending: &PendingQueue<MockTensor, MockOpCode>) -> Vec<MockOpCode> {
+        pending
+            .iter()
+            .map(|msg| match msg {
+                RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(x)) => x.op(),
+                others => panic!("expect a mock workload, got {:?}", others),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pending_queue_requeues_to_the_front_test() {
+        let mut pending = PendingQueue::new();
+        pending.push(mock_msg(MockOpCode::AddOp));
+        pending.push(mock_msg(MockOpCode::SubOp));
+        pending.requeue(mock_msg(MockOpCode::ExpOp));
+        pending.push(mock_msg(MockOpCode::MulOp));
+        assert_eq!(
+            pending_ops(&pending),
+            vec![
+                MockOpCode::ExpOp,
+                MockOpCode::AddOp,
+                MockOpCode::SubOp,
+                MockOpCode::MulOp
+            ]
+        );
+        assert_eq!(pending.make_contiguous().len(), 4);
+
+        let msg = pending.take(2).unwrap();
+        assert!(pending.take(3).is_none());
+        // the workloads left keep their order, the one taken goes first
+        pending.restore(VecDeque::from([1, 2, 3]), vec![(0, msg)]);
+        assert_eq!(
+            pending_ops(&pending),
+            vec![
+                MockOpCode::SubOp,
+                MockOpCode::ExpOp,
+                MockOpCode::AddOp,
+                MockOpCode::MulOp
+            ]
+        );
+        let taken = pending.take_if(|msg| {
+            matches!(
+                msg,
+                RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(x))
+                    if x.op() == MockOpCode::ExpOp
+            )
+        });
+        assert_eq!(taken.len(), 1);
+        assert_eq!(pending.take_all().len(), 3);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn dispatcher_delays_without_available_actor_test() {
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add_actor(0, 0);
+        dispatcher.add_actor(1, 0);
+        assert_eq!(
+            delivered_to(dispatcher.route(mock_msg(MockOpCode::AddOp))),
+            Some(0)
+        );
+        assert_eq!(
+            delivered_to(dispatcher.route(mock_msg(MockOpCode::AddOp))),
+            Some(1)
+        );
+        assert!(matches!(
+            dispatcher.route(mock_msg(MockOpCode::ExpOp)),
+            Route::Delayed
+        ));
+        assert_eq!(dispatcher.pending().len(), 1);
+        assert_eq!(dispatcher.outstanding(0), 1);
+        assert!(!dispatcher.is_drained());
+    }
+
+    #[test]
+    fn dispatcher_drops_workloads_pinned_to_dead_actors_test() {
+        let pinned = |actor| {
+            let (respond_to, _) = tokio::sync::oneshot::channel();
+            RaptorMessage::PinnedMSG(
+                actor,
+                crate::messages::PayloadMessage::UnaryComputeFunctorMsg {
+                    op: MockOpCode::ExpOp,
+                    inp: std::sync::Arc::new(std::sync::RwLock::new(MockTensor::default())),
+                    respond_to,
+                },
+            )
+        };
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add_actor(0, 0);
+        dispatcher.add_actor(1, 0);
+        dispatcher.remove_actor(1);
+        assert_eq!(delivered_to(dispatcher.route(pinned(0))), Some(0));
+        assert!(matches!(dispatcher.route(pinned(1)), Route::Dropped(_)));
+        assert!(matches!(dispatcher.route(pinned(5)), Route::Dropped(_)));
+        assert_eq!(dispatcher.outstanding(1), 0);
+    }
+
+    #[test]
+    fn dispatcher_tells_policy_the_executor_kinds_test() {
+        let mut model = CostModel::new();
+        model.update_kind("gpu", MockOpCode::ConvOp, 5);
+        let mut dispatcher = Dispatcher::new_with_policy(Box::new(LeastLoaded::new(model)));
+        dispatcher.add_actor_of_kind(0, 0, "cpu");
+        dispatcher.add_actor_of_kind(1, 3, "gpu");
+        // the convs queue up on the gpu rather than run on the idle cpu
+        for _ in 0..2 {
+            assert_eq!(
+                delivered_to(dispatcher.route(mock_msg(MockOpCode::ConvOp))),
+                Some(1)
+            );
+        }
+        assert_eq!(dispatcher.outstanding(1), 2);
+        assert_eq!(dispatcher.availables(), &[0]);
+    }
+
+    #[test]
+    fn dispatcher_drains_several_pending_workloads_test() {
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add_actor(0, 0);
+        dispatcher.add_actor(1, 0);
+        for _ in 0..4 {
+            dispatcher.route(mock_msg(MockOpCode::AddOp));
+        }
+        assert_eq!(dispatcher.pending().len(), 2);
+        dispatcher.on_available(0);
+        dispatcher.on_available(1);
+        let deliveries = dispatcher.drain();
+        assert_eq!(
+            deliveries.iter().map(|(x, _)| *x).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert!(dispatcher.pending().is_empty());
+        for actor in [0, 1, 0, 1] {
+            dispatcher.on_available(actor);
+        }
+        assert!(dispatcher.is_drained());
+        assert_eq!(dispatcher.availables(), &[0, 1]);
+    }
+
+    #[test]
+    fn dispatcher_skips_pools_without_actor_test() {
+        let pool_msg = || {
+            let (respond_to, _) = tokio::sync::oneshot::channel();
+            RaptorMessage::PoolMSG(
+                3,
+                crate::messages::PayloadMessage::UnaryComputeFunctorMsg {
+                    op: MockOpCode::ExpOp,
+                    inp: std::sync::Arc::new(std::sync::RwLock::new(MockTensor::default())),
+                    respond_to,
+                },
+            )
+        };
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add_actor(0, 0);
+        dispatcher.add_actor(1, 3);
+        assert_eq!(delivered_to(dispatcher.route(pool_msg())), Some(1));
+        assert_eq!(
+            delivered_to(dispatcher.route(mock_msg(MockOpCode::AddOp))),
+            Some(0)
+        );
+        dispatcher.route(pool_msg());
+        dispatcher.route(mock_msg(MockOpCode::ExpOp));
+        dispatcher.route(pool_msg());
+        dispatcher.route(mock_msg(MockOpCode::SinOp));
+
+        // the pool of actor #1 is tried once, the exp goes to actor #0 and the
+        // sin finds no actor left
+        dispatcher.on_available(0);
+        let deliveries = dispatcher.drain();
+        assert!(matches!(
+            deliveries.as_slice(),
+            [(0, RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(x)))]
+                if x.op() == MockOpCode::ExpOp
+        ));
+        let pools: Vec<_> = dispatcher.pending().iter().map(pool_of).collect();
+        assert_eq!(pools, vec![Some(3), Some(3), None]);
+        dispatcher.on_available(1);
+        assert!(matches!(
+            dispatcher.drain().as_slice(),
+            [(1, RaptorMessage::PoolMSG(3, _))]
+        ));
+        assert_eq!(dispatcher.pending().len(), 2);
+    }
+
+    #[test]
+    fn dispatcher_follows_the_policy_test() {
+        let mut dispatcher =
+            Dispatcher::new_with_policy(Box::new(ShortestJobFirst::new(CostModel::new())));
+        dispatcher.add_actor(0, 0);
+        dispatcher.route(mock_msg(MockOpCode::ConvOp));
+        dispatcher.route(mock_msg(MockOpCode::ConvOp));
+        dispatcher.route(mock_msg(MockOpCode::AddOp));
+        dispatcher.on_available(0);
+        let deliveries = dispatcher.drain();
+        assert!(matches!(
+            deliveries.as_slice(),
+            [(0, RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(x)))]
+                if x.op() == MockOpCode::AddOp
+        ));
+    }
+
+    #[test]
+    fn dispatcher_tracks_lost_actors_test() {
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add_actor(0, 0);
+        dispatcher.route(mock_msg(MockOpCode::AddOp));
+        dispatcher.route(mock_msg(MockOpCode::AddOp));
+        assert_eq!(dispatcher.lose_actor(0), 1);
+        // halted actors never become available again
+        dispatcher.on_available(0);
+        assert!(dispatcher.availables().is_empty());
+        assert!(dispatcher.drain().is_empty());
+        assert_eq!(dispatcher.take_pending().len(), 1);
+
+        // a restarted actor takes the requeued workload first
+        dispatcher.add_actor(0, 0);
+        dispatcher.route(mock_msg(MockOpCode::AddOp));
+        dispatcher.requeue(mock_msg(MockOpCode::ExpOp));
+        dispatcher.on_available(0);
+        assert!(matches!(
+            dispatcher.drain().as_slice(),
+            [(0, RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(x)))]
+                if x.op() == MockOpCode::ExpOp
+        ));
+    }
+
+    #[test]
+    fn dispatcher_drops_workloads_of_an_emptied_pool_test() {
+        let pool_msg = || {
+            let (respond_to, _) = tokio::sync::oneshot::channel();
+            RaptorMessage::PoolMSG(
+                3,
+                crate::messages::PayloadMessage::UnaryComputeFunctorMsg {
+                    op: MockOpCode::ExpOp,
+                    inp: std::sync::Arc::new(std::sync::RwLock::new(MockTensor::default())),
+                    respond_to,
+                },
+            )
+        };
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add_actor(0, 0);
+        dispatcher.add_actor(1, 3);
+        dispatcher.add_actor(2, 3);
+        for _ in 0..3 {
+            dispatcher.route(pool_msg());
+        }
+        dispatcher.route(mock_msg(MockOpCode::AddOp));
+        dispatcher.route(mock_msg(MockOpCode::AddOp));
+        assert_eq!(dispatcher.pending().len(), 2);
+
+        // another actor of the pool is left
+        assert!(dispatcher.remove_actor(1).is_empty());
+        let stranded = dispatcher.remove_actor(2);
+        assert!(matches!(
+            stranded.as_slice(),
+            [RaptorMessage::PoolMSG(3, _)]
+        ));
+        assert_eq!(dispatcher.pending().len(), 1);
+        // lost actors may be restarted, their pool is kept waiting
+        dispatcher.add_actor(2, 3);
+        dispatcher.route(pool_msg());
+        dispatcher.route(pool_msg());
+        assert_eq!(dispatcher.lose_actor(2), 1);
+        assert_eq!(dispatcher.pending().len(), 3);
+        assert_eq!(dispatcher.remove_all().len(), 2);
+        assert_eq!(dispatcher.pending().len(), 1);
+    }
+}
@@ -0,0 +1,196 @@
+// LICENSE PLACEHOLDER
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Definition: RaptorsError is the error type shared by all raptors components,
+/// variants tell which layer the failure comes from and carry the context
+/// needed to track it down.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// let err = RaptorsError::Executor {
+///     actor_id: 1,
+///     opcode: format!("{:?}", MockOpCode::AddOp),
+///     msg: "operand lock poisoned".to_string(),
+/// };
+/// assert_eq!(err.actor_id(), Some(1));
+/// assert_eq!(
+///     err.to_string(),
+///     "executor error on actor #1 computing AddOp: operand lock poisoned"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RaptorsError {
+    // failures of the actor system itself, e.g. bad orders or a dead event loop
+    System {
+        msg: String,
+    },
+    // failures of an actor that are not caused by its executor
+    Actor {
+        actor_id: usize,
+        msg: String,
+    },
+    // failures reported while an actor's executor computes an opcode
+    Executor {
+        actor_id: usize,
+        opcode: String,
+        msg: String,
+    },
+    Mailbox {
+        msg: String,
+    },
+    Scheduling {
+        msg: String,
+    },
+    // malformed tensors, e.g. data not matching the shape
+    Tensor {
+        msg: String,
+    },
+    // failures of the connection to a peer system, e.g. broken frames
+    Transport {
+        msg: String,
+    },
+    // malformed settings, e.g. cost profiles naming unknown opcodes
+    Config {
+        msg: String,
+    },
+}
+
+impl RaptorsError {
+    pub fn system(msg: impl Into<String>) -> Self {
+        RaptorsError::System { msg: msg.into() }
+    }
+
+    pub fn actor(actor_id: usize, msg: impl Into<String>) -> Self {
+        RaptorsError::Actor {
+            actor_id,
+            msg: msg.into(),
+        }
+    }
+
+    pub fn executor(actor_id: usize, opcode: &impl fmt::Debug, msg: impl Into<String>) -> Self {
+        RaptorsError::Executor {
+            actor_id,
+            opcode: format!("{:?}", opcode),
+            msg: msg.into(),
+        }
+    }
+
+    /// error raised by an executor kernel, executors are not aware of the actor
+    /// that runs them, the actor fills the id in with `at_actor`
+    pub fn kernel(opcode: &impl fmt::Debug, msg: impl Into<String>) -> Self {
+        RaptorsError::executor(0, opcode, msg)
+    }
+
+    /// attach the id of the actor the error happened on
+    pub fn at_actor(self, id: usize) -> Self {
+        match self {
+            RaptorsError::Actor { msg, .. } => RaptorsError::Actor { actor_id: id, msg },
+            RaptorsError::Executor { opcode, msg, .. } => RaptorsError::Executor {
+                actor_id: id,
+                opcode,
+                msg,
+            },
+            others => others,
+        }
+    }
+
+    pub fn mailbox(msg: impl Into<String>) -> Self {
+        RaptorsError::Mailbox { msg: msg.into() }
+    }
+
+    pub fn scheduling(msg: impl Into<String>) -> Self {
+        RaptorsError::Scheduling { msg: msg.into() }
+    }
+
+    pub fn tensor(msg: impl Into<String>) -> Self {
+        RaptorsError::Tensor { msg: msg.into() }
+    }
+
+    pub fn transport(msg: impl Into<String>) -> Self {
+        RaptorsError::Transport { msg: msg.into() }
+    }
+
+    pub fn config(msg: impl Into<String>) -> Self {
+        RaptorsError::Config { msg: msg.into() }
+    }
+
+    /// id of the actor the error happened on, if any
+    pub fn actor_id(&self) -> Option<usize> {
+        match self {
+            RaptorsError::Actor { actor_id, .. } | RaptorsError::Executor { actor_id, .. } => {
+                Some(*actor_id)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RaptorsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RaptorsError::System { msg } => write!(f, "system error: {}", msg),
+            RaptorsError::Actor { actor_id, msg } => {
+                write!(f, "actor error on actor #{}: {}", actor_id, msg)
+            }
+            RaptorsError::Executor {
+                actor_id,
+                opcode,
+                msg,
+            } => write!(
+                f,
+                "executor error on actor #{} computing {}: {}",
+                actor_id, opcode, msg
+            ),
+            RaptorsError::Mailbox { msg } => write!(f, "mailbox error: {}", msg),
+            RaptorsError::Scheduling { msg } => write!(f, "scheduling error: {}", msg),
+            RaptorsError::Tensor { msg } => write!(f, "tensor error: {}", msg),
+            RaptorsError::Transport { msg } => write!(f, "transport error: {}", msg),
+            RaptorsError::Config { msg } => write!(f, "config error: {}", msg),
+        }
+    }
+}
+
+impl Error for RaptorsError {}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost_model::MockOpCode;
+
+    #[test]
+    fn error_display_test() {
+        assert_eq!(
+            RaptorsError::system("halt cmd out of actor id range").to_string(),
+            "system error: halt cmd out of actor id range"
+        );
+        assert_eq!(
+            RaptorsError::actor(3, "unknown msg").to_string(),
+            "actor error on actor #3: unknown msg"
+        );
+        assert_eq!(
+            RaptorsError::mailbox("full").to_string(),
+            "mailbox error: full"
+        );
+    }
+
+    #[test]
+    fn error_actor_id_test() {
+        assert_eq!(RaptorsError::actor(3, "unknown msg").actor_id(), Some(3));
+        assert_eq!(RaptorsError::scheduling("no actor").actor_id(), None);
+    }
+
+    #[test]
+    fn kernel_error_at_actor_test() {
+        let err = RaptorsError::kernel(&MockOpCode::AddOp, "shape mismatch").at_actor(5);
+        assert_eq!(err.actor_id(), Some(5));
+        assert_eq!(
+            RaptorsError::system("dead").at_actor(5),
+            RaptorsError::system("dead")
+        );
+    }
+}
@@ -0,0 +1,195 @@
+// LICENSE PLACEHOLDER
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::errors::RaptorsError;
+use crate::executor_types::{DynExecutor, ExecutorLike};
+
+type ExecutorFactory<T> = Arc<dyn Fn() -> T + Send + Sync>;
+
+struct RegistryEntry<T> {
+    name: String,
+    // executors of kinds without a factory are built by `T::new_with_typeid`
+    factory: Option<ExecutorFactory<T>>,
+}
+
+/// Definition: ExecutorRegistry maps executor typeids and kind names to the
+/// factories the actor system builds the executors of its actors with.
+///
+/// The kinds "mock", "vulkan" and "blas" are known from the start with the
+/// typeids 0, 1 and 2.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut registry = ExecutorRegistry::<DynExecutor<CpuTensor, MockOpCode>>::new();
+///     registry.register_dyn(0, "mock", MockExecutor::<CpuTensor>::new).unwrap();
+///     registry.register_dyn(3, "cpu", CpuExecutor::new).unwrap();
+///     assert_eq!(registry.typeid_of("cpu"), Some(3));
+///
+///     let mut sys_config = SystemConfig::new("Raptors", "info");
+///     sys_config.set_ranks(2);
+///     let mut system = SystemBuilder::new().build_with_registry(sys_config, registry).unwrap();
+///     system.spawn("cpu", 2).await.unwrap();
+///     assert!(system.spawn("tpu", 1).await.is_err());
+///     system.shutdown().await.unwrap();
+/// }
+/// ```
+pub struct ExecutorRegistry<T> {
+    entries: BTreeMap<usize, RegistryEntry<T>>,
+}
+
+impl<T> Default for ExecutorRegistry<T> {
+    fn default() -> Self {
+        let mut entries = BTreeMap::new();
+        for (typeid, name) in [(0, "mock"), (1, "vulkan"), (2, "blas")] {
+            entries.insert(
+                typeid,
+                RegistryEntry {
+                    name: name.to_string(),
+                    factory: None,
+                },
+            );
+        }
+        Self { entries }
+    }
+}
+
+impl<T> fmt::Debug for ExecutorRegistry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().map(|(k, v)| (k, &v.name)))
+            .finish()
+    }
+}
+
+impl<T: ExecutorLike> ExecutorRegistry<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// register the factory of an executor kind; a known kind without factory
+    /// may be given one, but neither a typeid nor a name is registered twice
+    pub fn register<F>(&mut self, typeid: usize, name: &str, factory: F) -> Result<(), RaptorsError>
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        if let Some(other) = self.typeid_of(name).filter(|x| *x != typeid) {
+            return Err(RaptorsError::system(format!(
+                "executor kind {} is registered with typeid {} already",
+                name, other
+            )));
+        }
+        match self.entries.get(&typeid) {
+            Some(entry) if entry.factory.is_some() || entry.name != name => {
+                Err(RaptorsError::system(format!(
+                    "typeid {} is registered for executor kind {} already",
+                    typeid, entry.name
+                )))
+            }
+            _ => {
+                self.entries.insert(
+                    typeid,
+                    RegistryEntry {
+                        name: name.to_string(),
+                        factory: Some(Arc::new(factory)),
+                    },
+                );
+                Ok(())
+            }
+        }
+    }
+
+    pub fn typeid_of(&self, name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .find(|(_, entry)| entry.name == name)
+            .map(|(typeid, _)| *typeid)
+    }
+
+    pub fn name_of(&self, typeid: usize) -> Option<&str> {
+        self.entries.get(&typeid).map(|entry| entry.name.as_str())
+    }
+
+    pub fn typeids(&self) -> Vec<usize> {
+        self.entries.keys().copied().collect()
+    }
+
+    /// executor for an actor of the typeid
+    pub fn build(&self, typeid: usize) -> T {
+        match self.entries.get(&typeid).and_then(|x| x.factory.as_ref()) {
+            Some(factory) => factory(),
+            None => T::new_with_typeid(typeid),
+        }
+    }
+}
+
+impl<U, O> ExecutorRegistry<DynExecutor<U, O>> {
+    /// register a concrete executor kind to be hosted as a DynExecutor
+    pub fn register_dyn<E, F>(
+        &mut self,
+        typeid: usize,
+        name: &str,
+        factory: F,
+    ) -> Result<(), RaptorsError>
+    where
+        E: ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync + 'static,
+        F: Fn() -> E + Send + Sync + 'static,
+    {
+        self.register(typeid, name, move || DynExecutor::new(typeid, factory()))
+    }
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn registry_builtin_kinds_test() {
+        let registry = ExecutorRegistry::<MockExecutor>::new();
+        assert_eq!(registry.typeid_of("mock"), Some(0));
+        assert_eq!(registry.typeid_of("blas"), Some(2));
+        assert_eq!(registry.name_of(1), Some("vulkan"));
+        assert_eq!(registry.typeid_of("cpu"), None);
+        assert_eq!(registry.typeids(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn registry_rejects_duplicates_test() {
+        let mut registry = ExecutorRegistry::<CpuExecutor>::new();
+        assert!(registry.register(2, "blas", CpuExecutor::new).is_ok());
+        assert_eq!(
+            registry.register(2, "blas", CpuExecutor::new),
+            Err(RaptorsError::system(
+                "typeid 2 is registered for executor kind blas already"
+            ))
+        );
+        assert_eq!(
+            registry.register(5, "vulkan", CpuExecutor::new),
+            Err(RaptorsError::system(
+                "executor kind vulkan is registered with typeid 1 already"
+            ))
+        );
+        assert!(registry.register(1, "cpu", CpuExecutor::new).is_err());
+        assert!(registry.register(3, "cpu", CpuExecutor::new).is_ok());
+    }
+
+    #[test]
+    fn registry_builds_dyn_executors_test() {
+        let mut registry = ExecutorRegistry::<DynExecutor<CpuTensor, MockOpCode>>::new();
+        registry.register_dyn(3, "cpu", CpuExecutor::new).unwrap();
+        assert_eq!(registry.build(3).typeid(), 3);
+        // a kind without factory can not compute
+        let mut blas = registry.build(2);
+        let inp = std::sync::Arc::new(std::sync::RwLock::new(CpuTensor::default()));
+        assert_eq!(
+            blas.unary_compute(MockOpCode::ExpOp, inp),
+            Err(RaptorsError::system("no executor registered for typeid 2"))
+        );
+    }
+}
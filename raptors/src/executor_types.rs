@@ -1,10 +1,13 @@
 // LICENSE PLACEHOLDER
 //
+use std::fmt;
+use std::marker::PhantomData;
 use std::sync::{Arc, RwLock};
 use std::{thread, time};
 
 use crate::cost_model::MockOpCode;
-use crate::tensor_types::{MockTensor, TensorLike};
+use crate::errors::RaptorsError;
+use crate::tensor_types::{CpuData, CpuScalar, CpuTensor, MockTensor, TensorLike};
 
 // desugarized trait bounds in trait
 // trait-name {
@@ -14,37 +17,49 @@ use crate::tensor_types::{MockTensor, TensorLike};
 //     type AssociateTypeA: TraitB;
 //     fn func(&self) -> AssociateTypeA;
 // }
+//
+// compute methods are fallible, kernels report failures with RaptorsError::kernel
+// and the actor running the executor propagates them to the submitter
 pub trait ExecutorLike {
     type TensorType;
     type OpCodeType;
-    fn new_with_typeid(typeid: usize) -> Self;
-    fn init(&mut self) -> ();
-    fn mock_compute(&mut self, arg: Self::TensorType) -> Self::TensorType;
+    // executors of different kinds are hosted together behind trait objects,
+    // see DynExecutor, only a concrete executor can be built from its typeid
+    fn new_with_typeid(typeid: usize) -> Self
+    where
+        Self: Sized;
+    fn init(&mut self);
+    fn mock_compute(&mut self, arg: Self::TensorType) -> Result<Self::TensorType, RaptorsError>;
     fn unary_compute(
         &mut self,
         op: Self::OpCodeType,
         arg: Arc<RwLock<Self::TensorType>>,
-    ) -> Self::TensorType;
+    ) -> Result<Self::TensorType, RaptorsError>;
     fn unary_compute_v2(
         &mut self,
         op: Self::OpCodeType,
         arg: Arc<RwLock<Self::TensorType>>,
         out: Arc<RwLock<Self::TensorType>>,
-    ) -> ();
+    ) -> Result<(), RaptorsError>;
     // TODO need to support monomorphism for SupportedDataType
     fn binary_compute(
         &mut self,
         op: Self::OpCodeType,
         lhs: Arc<RwLock<Self::TensorType>>,
         rhs: Arc<RwLock<Self::TensorType>>,
-    ) -> Self::TensorType;
+    ) -> Result<Self::TensorType, RaptorsError>;
     fn binary_compute_v2(
         &mut self,
         op: Self::OpCodeType,
         lhs: Arc<RwLock<Self::TensorType>>,
         rhs: Arc<RwLock<Self::TensorType>>,
         out: Arc<RwLock<Self::TensorType>>,
-    ) -> ();
+    ) -> Result<(), RaptorsError>;
+    /// data published to a topic the actor subscribes to, e.g. updated
+    /// parameters; ignored unless the executor keeps such state
+    fn on_published(&mut self, _topic: &str, _data: Self::TensorType) -> Result<(), RaptorsError> {
+        Ok(())
+    }
 }
 
 // wrap a dedicated executor module that only consider how to do computations
@@ -52,70 +67,88 @@ pub trait ExecutorLike {
 // TODO(long-term):
 // as a interface, make refactor as Trait and expose to CRT level,
 // make CRT vm to impl this trait
-#[derive(Debug)]
-pub struct MockExecutor {}
+//
+// the tensors are only cloned, hence mock actors can join a pool of any tensor type
+#[derive(Debug, Default)]
+pub struct MockExecutor<U = MockTensor> {
+    _marker: PhantomData<U>,
+}
 
-impl MockExecutor {
+impl<U> MockExecutor<U> {
     pub fn new() -> Self {
-        return Self {};
+        Self {
+            _marker: PhantomData,
+        }
     }
 
     // TODO handle op
-    pub fn mock_unary<T: TensorLike + Clone>(&mut self, op: MockOpCode, arg: Arc<RwLock<T>>) -> T {
-        thread::sleep(time::Duration::from_millis((1000) as u64));
-        let _y: T = (*arg).read().unwrap().clone();
-        _y
+    pub fn mock_unary<T: TensorLike + Clone>(
+        &mut self,
+        op: MockOpCode,
+        arg: Arc<RwLock<T>>,
+    ) -> Result<T, RaptorsError> {
+        thread::sleep(time::Duration::from_millis(1000));
+        let _y: T = (*arg)
+            .read()
+            .map_err(|_| RaptorsError::kernel(&op, "operand lock poisoned"))?
+            .clone();
+        Ok(_y)
     }
 
     pub fn mock_unary_v2<T: TensorLike + Clone>(
         &mut self,
-        op: MockOpCode,
-        arg: Arc<RwLock<T>>,
-        ret: Arc<RwLock<T>>,
-    ) -> () {
-        thread::sleep(time::Duration::from_millis((1000) as u64));
+        _op: MockOpCode,
+        _arg: Arc<RwLock<T>>,
+        _ret: Arc<RwLock<T>>,
+    ) -> Result<(), RaptorsError> {
+        thread::sleep(time::Duration::from_millis(1000));
+        Ok(())
     }
 
     pub fn mock_binary<T: TensorLike + Clone>(
         &mut self,
         op: MockOpCode,
         lhs: Arc<RwLock<T>>,
-        rhs: Arc<RwLock<T>>,
-    ) -> T {
-        thread::sleep(time::Duration::from_millis((2000) as u64));
-        let _y: T = (*lhs).read().unwrap().clone();
-        _y
+        _rhs: Arc<RwLock<T>>,
+    ) -> Result<T, RaptorsError> {
+        thread::sleep(time::Duration::from_millis(2000));
+        let _y: T = (*lhs)
+            .read()
+            .map_err(|_| RaptorsError::kernel(&op, "operand lock poisoned"))?
+            .clone();
+        Ok(_y)
     }
 
     pub fn mock_binary_v2<T: TensorLike + Clone>(
         &mut self,
-        op: MockOpCode,
-        lhs: Arc<RwLock<T>>,
-        rhs: Arc<RwLock<T>>,
-        out: Arc<RwLock<T>>,
-    ) -> () {
-        thread::sleep(time::Duration::from_millis((2000) as u64));
+        _op: MockOpCode,
+        _lhs: Arc<RwLock<T>>,
+        _rhs: Arc<RwLock<T>>,
+        _out: Arc<RwLock<T>>,
+    ) -> Result<(), RaptorsError> {
+        thread::sleep(time::Duration::from_millis(2000));
+        Ok(())
     }
 }
 
-impl ExecutorLike for MockExecutor {
+impl<U: TensorLike + Clone> ExecutorLike for MockExecutor<U> {
     type OpCodeType = MockOpCode;
-    type TensorType = MockTensor;
-    fn new_with_typeid(typeid: usize) -> MockExecutor {
+    type TensorType = U;
+    fn new_with_typeid(_typeid: usize) -> MockExecutor<U> {
         Self::new()
     }
 
-    fn init(&mut self) -> () {}
+    fn init(&mut self) {}
 
-    fn mock_compute(&mut self, arg: Self::TensorType) -> Self::TensorType {
-        arg
+    fn mock_compute(&mut self, arg: Self::TensorType) -> Result<Self::TensorType, RaptorsError> {
+        Ok(arg)
     }
 
     fn unary_compute(
         &mut self,
         op: Self::OpCodeType,
         arg: Arc<RwLock<Self::TensorType>>,
-    ) -> Self::TensorType {
+    ) -> Result<Self::TensorType, RaptorsError> {
         self.mock_unary::<Self::TensorType>(op, arg)
     }
 
@@ -124,8 +157,8 @@ impl ExecutorLike for MockExecutor {
         op: Self::OpCodeType,
         arg: Arc<RwLock<Self::TensorType>>,
         out: Arc<RwLock<Self::TensorType>>,
-    ) -> () {
-        self.mock_unary_v2::<Self::TensorType>(op, arg, out);
+    ) -> Result<(), RaptorsError> {
+        self.mock_unary_v2::<Self::TensorType>(op, arg, out)
     }
 
     fn binary_compute(
@@ -133,7 +166,7 @@ impl ExecutorLike for MockExecutor {
         op: Self::OpCodeType,
         lhs: Arc<RwLock<Self::TensorType>>,
         rhs: Arc<RwLock<Self::TensorType>>,
-    ) -> Self::TensorType {
+    ) -> Result<Self::TensorType, RaptorsError> {
         self.mock_binary::<Self::TensorType>(op, lhs, rhs)
     }
 
@@ -143,20 +176,563 @@ impl ExecutorLike for MockExecutor {
         lhs: Arc<RwLock<Self::TensorType>>,
         rhs: Arc<RwLock<Self::TensorType>>,
         out: Arc<RwLock<Self::TensorType>>,
-    ) -> () {
-        self.mock_binary_v2::<Self::TensorType>(op, lhs, rhs, out);
+    ) -> Result<(), RaptorsError> {
+        self.mock_binary_v2::<Self::TensorType>(op, lhs, rhs, out)
+    }
+}
+
+pub type BoxedExecutor<U, O> = Box<dyn ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync>;
+
+/// Definition: DynExecutor hosts any executor computing on the same tensor and
+/// opcode types, it lets one actor system run heterogeneous actor pools.
+///
+/// Built from a typeid alone it has no executor to run, every computation
+/// fails then; register a factory for the typeid in the ExecutorRegistry.
+///
+/// ```
+/// use std::sync::{Arc, RwLock};
+/// use raptors::prelude::*;
+///
+/// let mut exec = DynExecutor::new(3, CpuExecutor::new());
+/// let inp = Arc::new(RwLock::new(CpuTensor::from_f32(vec![1], vec![0.]).unwrap()));
+/// assert_eq!(exec.typeid(), 3);
+/// assert!(exec.unary_compute(MockOpCode::ExpOp, inp.clone()).is_ok());
+///
+/// let mut unregistered = DynExecutor::<CpuTensor, MockOpCode>::new_with_typeid(4);
+/// assert!(unregistered.unary_compute(MockOpCode::ExpOp, inp).is_err());
+/// ```
+pub struct DynExecutor<U, O> {
+    typeid: usize,
+    inner: Option<BoxedExecutor<U, O>>,
+}
+
+impl<U, O> DynExecutor<U, O> {
+    pub fn new(
+        typeid: usize,
+        executor: impl ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            typeid,
+            inner: Some(Box::new(executor)),
+        }
+    }
+
+    pub fn typeid(&self) -> usize {
+        self.typeid
+    }
+
+    fn inner(&mut self) -> Result<&mut BoxedExecutor<U, O>, RaptorsError> {
+        let typeid = self.typeid;
+        self.inner.as_mut().ok_or_else(|| {
+            RaptorsError::system(format!("no executor registered for typeid {}", typeid))
+        })
+    }
+}
+
+impl<U, O> fmt::Debug for DynExecutor<U, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynExecutor")
+            .field("typeid", &self.typeid)
+            .field("registered", &self.inner.is_some())
+            .finish()
+    }
+}
+
+impl<U, O> ExecutorLike for DynExecutor<U, O> {
+    type OpCodeType = O;
+    type TensorType = U;
+    fn new_with_typeid(typeid: usize) -> Self {
+        Self {
+            typeid,
+            inner: None,
+        }
+    }
+
+    fn init(&mut self) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.init();
+        }
+    }
+
+    fn mock_compute(&mut self, arg: U) -> Result<U, RaptorsError> {
+        self.inner()?.mock_compute(arg)
+    }
+
+    fn unary_compute(&mut self, op: O, arg: Arc<RwLock<U>>) -> Result<U, RaptorsError> {
+        self.inner()?.unary_compute(op, arg)
+    }
+
+    fn unary_compute_v2(
+        &mut self,
+        op: O,
+        arg: Arc<RwLock<U>>,
+        out: Arc<RwLock<U>>,
+    ) -> Result<(), RaptorsError> {
+        self.inner()?.unary_compute_v2(op, arg, out)
+    }
+
+    fn binary_compute(
+        &mut self,
+        op: O,
+        lhs: Arc<RwLock<U>>,
+        rhs: Arc<RwLock<U>>,
+    ) -> Result<U, RaptorsError> {
+        self.inner()?.binary_compute(op, lhs, rhs)
+    }
+
+    fn binary_compute_v2(
+        &mut self,
+        op: O,
+        lhs: Arc<RwLock<U>>,
+        rhs: Arc<RwLock<U>>,
+        out: Arc<RwLock<U>>,
+    ) -> Result<(), RaptorsError> {
+        self.inner()?.binary_compute_v2(op, lhs, rhs, out)
+    }
+
+    fn on_published(&mut self, topic: &str, data: U) -> Result<(), RaptorsError> {
+        self.inner()?.on_published(topic, data)
+    }
+}
+
+// run a kernel generic over the element type on operands of the same dtype
+macro_rules! with_same_dtype {
+    ($op:expr, $lhs:expr, $rhs:expr, |$a:ident, $b:ident| $body:expr) => {
+        match ($lhs.data(), $rhs.data()) {
+            (CpuData::F32($a), CpuData::F32($b)) => $body.map(CpuData::F32),
+            (CpuData::F64($a), CpuData::F64($b)) => $body.map(CpuData::F64),
+            (lhs, rhs) => Err(RaptorsError::kernel(
+                &$op,
+                format!("dtype mismatch {:?} vs {:?}", lhs.dtype(), rhs.dtype()),
+            )),
+        }
+    };
+}
+
+/// Definition: CpuExecutor computes every MockOpCode on dense CpuTensors.
+///
+/// add/sub/mul/div are elementwise, a single element operand is broadcast;
+/// matmul takes 2-d operands; conv is a stride-1 unpadded cross-correlation of
+/// a [H, W] input with a [KH, KW] kernel, or a [N, C, H, W] input with a
+/// [O, C, KH, KW] kernel. Reshape flattens as an unary op, as a binary op the
+/// rhs lists the target dims; in the v2 form the output shape is kept.
+///
+/// ```
+/// use std::sync::{Arc, RwLock};
+/// use raptors::prelude::*;
+///
+/// let mut exec = CpuExecutor::new();
+/// let lhs = Arc::new(RwLock::new(CpuTensor::from_f32(vec![1, 2], vec![1., 2.]).unwrap()));
+/// let rhs = Arc::new(RwLock::new(CpuTensor::from_f32(vec![2, 1], vec![3., 4.]).unwrap()));
+/// let out = exec.binary_compute(MockOpCode::MatmulOp, lhs, rhs).unwrap();
+/// assert_eq!(out.as_f32(), Some(&[11.][..]));
+/// ```
+#[derive(Debug, Default)]
+pub struct CpuExecutor {}
+
+impl CpuExecutor {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn unary(&self, op: MockOpCode, arg: &CpuTensor) -> Result<CpuTensor, RaptorsError> {
+        match op {
+            MockOpCode::IdentityOp => Ok(arg.clone()),
+            MockOpCode::ExpOp | MockOpCode::SinOp => map_data(op, arg),
+            MockOpCode::ReshapeOp => arg
+                .reshape(vec![arg.numel()])
+                .map_err(|e| RaptorsError::kernel(&op, e.to_string())),
+            _ => Err(RaptorsError::kernel(&op, "not an unary op")),
+        }
+    }
+
+    pub fn binary(
+        &self,
+        op: MockOpCode,
+        lhs: &CpuTensor,
+        rhs: &CpuTensor,
+    ) -> Result<CpuTensor, RaptorsError> {
+        match op {
+            MockOpCode::AddOp | MockOpCode::SubOp | MockOpCode::MulOp | MockOpCode::DivOp => {
+                let shape = broadcast_shape(op, lhs, rhs)?;
+                let data = with_same_dtype!(op, lhs, rhs, |a, b| Ok(elementwise(op, a, b)))?;
+                CpuTensor::new(shape, data)
+            }
+            MockOpCode::MatmulOp => match (lhs.shape(), rhs.shape()) {
+                (&[m, k], &[k2, n]) if k == k2 => {
+                    let data = with_same_dtype!(op, lhs, rhs, |a, b| Ok(matmul(a, b, m, k, n)))?;
+                    CpuTensor::new(vec![m, n], data)
+                }
+                (lshape, rshape) => Err(RaptorsError::kernel(
+                    &op,
+                    format!("cannot matmul {:?} with {:?}", lshape, rshape),
+                )),
+            },
+            MockOpCode::ConvOp => {
+                let (input, kernel) = conv_dims(lhs.shape(), rhs.shape()).ok_or_else(|| {
+                    RaptorsError::kernel(
+                        &op,
+                        format!("cannot conv {:?} with {:?}", lhs.shape(), rhs.shape()),
+                    )
+                })?;
+                let data = with_same_dtype!(op, lhs, rhs, |a, b| Ok(conv2d(a, b, input, kernel)))?;
+                let (oh, ow) = (input[2] - kernel[2] + 1, input[3] - kernel[3] + 1);
+                let shape = match lhs.shape().len() {
+                    2 => vec![oh, ow],
+                    _ => vec![input[0], kernel[0], oh, ow],
+                };
+                CpuTensor::new(shape, data)
+            }
+            MockOpCode::ReshapeOp => {
+                let dims = match rhs.data() {
+                    CpuData::F32(x) => x.iter().map(|d| d.to_dim()).collect::<Option<Vec<_>>>(),
+                    CpuData::F64(x) => x.iter().map(|d| d.to_dim()).collect::<Option<Vec<_>>>(),
+                };
+                let dims =
+                    dims.ok_or_else(|| RaptorsError::kernel(&op, "shape holds invalid dims"))?;
+                lhs.reshape(dims)
+                    .map_err(|e| RaptorsError::kernel(&op, e.to_string()))
+            }
+            _ => Err(RaptorsError::kernel(&op, "not a binary op")),
+        }
+    }
+}
+
+fn read_operand(op: MockOpCode, arg: &Arc<RwLock<CpuTensor>>) -> Result<CpuTensor, RaptorsError> {
+    Ok(arg
+        .read()
+        .map_err(|_| RaptorsError::kernel(&op, "operand lock poisoned"))?
+        .clone())
+}
+
+fn write_result(
+    op: MockOpCode,
+    out: &Arc<RwLock<CpuTensor>>,
+    result: CpuTensor,
+) -> Result<(), RaptorsError> {
+    *out.write()
+        .map_err(|_| RaptorsError::kernel(&op, "output lock poisoned"))? = result;
+    Ok(())
+}
+
+fn map_data(op: MockOpCode, arg: &CpuTensor) -> Result<CpuTensor, RaptorsError> {
+    let data = match arg.data() {
+        CpuData::F32(x) => CpuData::F32(unary_math(op, x)),
+        CpuData::F64(x) => CpuData::F64(unary_math(op, x)),
+    };
+    CpuTensor::new(arg.shape().to_vec(), data)
+}
+
+fn unary_math<T: CpuScalar>(op: MockOpCode, x: &[T]) -> Vec<T> {
+    match op {
+        MockOpCode::ExpOp => x.iter().map(|v| v.exp()).collect(),
+        _ => x.iter().map(|v| v.sin()).collect(),
+    }
+}
+
+fn broadcast_shape(
+    op: MockOpCode,
+    lhs: &CpuTensor,
+    rhs: &CpuTensor,
+) -> Result<Vec<usize>, RaptorsError> {
+    if lhs.shape() == rhs.shape() || rhs.numel() == 1 {
+        Ok(lhs.shape().to_vec())
+    } else if lhs.numel() == 1 {
+        Ok(rhs.shape().to_vec())
+    } else {
+        Err(RaptorsError::kernel(
+            &op,
+            format!("shape mismatch {:?} vs {:?}", lhs.shape(), rhs.shape()),
+        ))
+    }
+}
+
+fn elementwise<T: CpuScalar>(op: MockOpCode, a: &[T], b: &[T]) -> Vec<T> {
+    let f = |x: T, y: T| match op {
+        MockOpCode::AddOp => x + y,
+        MockOpCode::SubOp => x - y,
+        MockOpCode::MulOp => x * y,
+        _ => x / y,
+    };
+    match (a.len(), b.len()) {
+        (_, 1) => a.iter().map(|x| f(*x, b[0])).collect(),
+        (1, _) => b.iter().map(|y| f(a[0], *y)).collect(),
+        _ => a.iter().zip(b).map(|(x, y)| f(*x, *y)).collect(),
+    }
+}
+
+fn matmul<T: CpuScalar>(a: &[T], b: &[T], m: usize, k: usize, n: usize) -> Vec<T> {
+    let mut out = vec![T::default(); m * n];
+    for i in 0..m {
+        for p in 0..k {
+            let x = a[i * k + p];
+            for j in 0..n {
+                out[i * n + j] += x * b[p * n + j];
+            }
+        }
+    }
+    out
+}
+
+// [N, C, H, W] of the input and [O, C, KH, KW] of the kernel
+pub(crate) fn conv_dims(input: &[usize], kernel: &[usize]) -> Option<([usize; 4], [usize; 4])> {
+    let (input, kernel) = match (input, kernel) {
+        (&[h, w], &[kh, kw]) => ([1, 1, h, w], [1, 1, kh, kw]),
+        (&[n, c, h, w], &[o, kc, kh, kw]) if c == kc => ([n, c, h, w], [o, kc, kh, kw]),
+        _ => return None,
+    };
+    match kernel[2] <= input[2] && kernel[3] <= input[3] {
+        true => Some((input, kernel)),
+        false => None,
+    }
+}
+
+fn conv2d<T: CpuScalar>(x: &[T], w: &[T], input: [usize; 4], kernel: [usize; 4]) -> Vec<T> {
+    let [n, c, h, wd] = input;
+    let [o, _, kh, kw] = kernel;
+    let (oh, ow) = (h - kh + 1, wd - kw + 1);
+    let mut out = vec![T::default(); n * o * oh * ow];
+    for b in 0..n {
+        for f in 0..o {
+            for i in 0..oh {
+                for j in 0..ow {
+                    let mut acc = T::default();
+                    for ch in 0..c {
+                        for ki in 0..kh {
+                            for kj in 0..kw {
+                                let xv = x[((b * c + ch) * h + i + ki) * wd + j + kj];
+                                let wv = w[((f * c + ch) * kh + ki) * kw + kj];
+                                acc += xv * wv;
+                            }
+                        }
+                    }
+                    out[((b * o + f) * oh + i) * ow + j] = acc;
+                }
+            }
+        }
+    }
+    out
+}
+
+impl ExecutorLike for CpuExecutor {
+    type OpCodeType = MockOpCode;
+    type TensorType = CpuTensor;
+    fn new_with_typeid(_typeid: usize) -> CpuExecutor {
+        Self::new()
+    }
+
+    fn init(&mut self) {}
+
+    fn mock_compute(&mut self, arg: Self::TensorType) -> Result<Self::TensorType, RaptorsError> {
+        Ok(arg)
+    }
+
+    fn unary_compute(
+        &mut self,
+        op: Self::OpCodeType,
+        arg: Arc<RwLock<Self::TensorType>>,
+    ) -> Result<Self::TensorType, RaptorsError> {
+        self.unary(op, &read_operand(op, &arg)?)
+    }
+
+    fn unary_compute_v2(
+        &mut self,
+        op: Self::OpCodeType,
+        arg: Arc<RwLock<Self::TensorType>>,
+        out: Arc<RwLock<Self::TensorType>>,
+    ) -> Result<(), RaptorsError> {
+        let arg = read_operand(op, &arg)?;
+        let result = match op {
+            // reshape into the shape the output is allocated with
+            MockOpCode::ReshapeOp => {
+                let shape = read_operand(op, &out)?.shape().to_vec();
+                arg.reshape(shape)
+                    .map_err(|e| RaptorsError::kernel(&op, e.to_string()))?
+            }
+            _ => self.unary(op, &arg)?,
+        };
+        write_result(op, &out, result)
+    }
+
+    fn binary_compute(
+        &mut self,
+        op: Self::OpCodeType,
+        lhs: Arc<RwLock<Self::TensorType>>,
+        rhs: Arc<RwLock<Self::TensorType>>,
+    ) -> Result<Self::TensorType, RaptorsError> {
+        self.binary(op, &read_operand(op, &lhs)?, &read_operand(op, &rhs)?)
+    }
+
+    fn binary_compute_v2(
+        &mut self,
+        op: Self::OpCodeType,
+        lhs: Arc<RwLock<Self::TensorType>>,
+        rhs: Arc<RwLock<Self::TensorType>>,
+        out: Arc<RwLock<Self::TensorType>>,
+    ) -> Result<(), RaptorsError> {
+        let result = self.binary(op, &read_operand(op, &lhs)?, &read_operand(op, &rhs)?)?;
+        write_result(op, &out, result)
     }
 }
 
 // unit tests
 #[cfg(test)]
-
 mod tests {
     use super::*;
-    use std::time;
+    use crate::tensor_types::DType;
 
     #[test]
     fn mock_exector_dummy_test() {
         assert_eq!(0, 0);
     }
+
+    fn shared(tensor: CpuTensor) -> Arc<RwLock<CpuTensor>> {
+        Arc::new(RwLock::new(tensor))
+    }
+
+    fn f64_tensor(shape: Vec<usize>, data: Vec<f64>) -> Arc<RwLock<CpuTensor>> {
+        shared(CpuTensor::from_f64(shape, data).unwrap())
+    }
+
+    #[test]
+    fn cpu_executor_elementwise_test() {
+        let mut exec = CpuExecutor::new();
+        let lhs = f64_tensor(vec![2, 2], vec![1., 2., 3., 4.]);
+        let rhs = f64_tensor(vec![2, 2], vec![4., 3., 2., 1.]);
+        let binary = |exec: &mut CpuExecutor, op| {
+            let out = exec.binary_compute(op, lhs.clone(), rhs.clone()).unwrap();
+            out.as_f64().unwrap().to_vec()
+        };
+        assert_eq!(binary(&mut exec, MockOpCode::AddOp), vec![5., 5., 5., 5.]);
+        assert_eq!(binary(&mut exec, MockOpCode::SubOp), vec![-3., -1., 1., 3.]);
+        assert_eq!(binary(&mut exec, MockOpCode::MulOp), vec![4., 6., 6., 4.]);
+        assert_eq!(
+            binary(&mut exec, MockOpCode::DivOp),
+            vec![0.25, 2. / 3., 1.5, 4.]
+        );
+
+        let scalar = f64_tensor(vec![1], vec![2.]);
+        let out = exec
+            .binary_compute(MockOpCode::MulOp, lhs.clone(), scalar)
+            .unwrap();
+        assert_eq!(out.shape(), &[2, 2]);
+        assert_eq!(out.as_f64(), Some(&[2., 4., 6., 8.][..]));
+    }
+
+    #[test]
+    fn cpu_executor_unary_test() {
+        let mut exec = CpuExecutor::new();
+        let inp = shared(CpuTensor::from_f32(vec![2], vec![0., 1.]).unwrap());
+        let out = exec.unary_compute(MockOpCode::ExpOp, inp.clone()).unwrap();
+        assert_eq!(out.as_f32(), Some(&[1., 1_f32.exp()][..]));
+        let out = exec.unary_compute(MockOpCode::SinOp, inp.clone()).unwrap();
+        assert_eq!(out.as_f32(), Some(&[0., 1_f32.sin()][..]));
+        let out = exec
+            .unary_compute(MockOpCode::IdentityOp, inp.clone())
+            .unwrap();
+        assert_eq!(out, *inp.read().unwrap());
+        assert!(exec.unary_compute(MockOpCode::AddOp, inp).is_err());
+    }
+
+    #[test]
+    fn cpu_executor_matmul_test() {
+        let mut exec = CpuExecutor::new();
+        let lhs = f64_tensor(vec![2, 3], vec![1., 2., 3., 4., 5., 6.]);
+        let rhs = f64_tensor(vec![3, 2], vec![7., 8., 9., 10., 11., 12.]);
+        let out = exec
+            .binary_compute(MockOpCode::MatmulOp, lhs.clone(), rhs)
+            .unwrap();
+        assert_eq!(out.shape(), &[2, 2]);
+        assert_eq!(out.as_f64(), Some(&[58., 64., 139., 154.][..]));
+        assert_eq!(
+            exec.binary_compute(MockOpCode::MatmulOp, lhs.clone(), lhs),
+            Err(RaptorsError::kernel(
+                &MockOpCode::MatmulOp,
+                "cannot matmul [2, 3] with [2, 3]"
+            ))
+        );
+    }
+
+    #[test]
+    fn cpu_executor_conv_test() {
+        let mut exec = CpuExecutor::new();
+        let inp = f64_tensor(vec![3, 3], vec![1., 2., 3., 4., 5., 6., 7., 8., 9.]);
+        let kernel = f64_tensor(vec![2, 2], vec![1., 0., 0., -1.]);
+        let out = exec
+            .binary_compute(MockOpCode::ConvOp, inp, kernel)
+            .unwrap();
+        assert_eq!(out.shape(), &[2, 2]);
+        assert_eq!(out.as_f64(), Some(&[-4., -4., -4., -4.][..]));
+
+        // two channels summed up into two filters
+        let inp = f64_tensor(vec![1, 2, 2, 2], vec![1., 2., 3., 4., 5., 6., 7., 8.]);
+        let kernel = f64_tensor(vec![2, 2, 1, 1], vec![1., 1., 1., -1.]);
+        let out = exec
+            .binary_compute(MockOpCode::ConvOp, inp, kernel)
+            .unwrap();
+        assert_eq!(out.shape(), &[1, 2, 2, 2]);
+        assert_eq!(
+            out.as_f64(),
+            Some(&[6., 8., 10., 12., -4., -4., -4., -4.][..])
+        );
+    }
+
+    #[test]
+    fn cpu_executor_reshape_test() {
+        let mut exec = CpuExecutor::new();
+        let inp = f64_tensor(vec![2, 3], vec![1., 2., 3., 4., 5., 6.]);
+        let out = exec
+            .unary_compute(MockOpCode::ReshapeOp, inp.clone())
+            .unwrap();
+        assert_eq!(out.shape(), &[6]);
+
+        let shape = f64_tensor(vec![2], vec![3., 2.]);
+        let out = exec
+            .binary_compute(MockOpCode::ReshapeOp, inp.clone(), shape)
+            .unwrap();
+        assert_eq!(out.shape(), &[3, 2]);
+
+        let out = shared(CpuTensor::zeros(vec![1, 6], DType::F64));
+        exec.unary_compute_v2(MockOpCode::ReshapeOp, inp, out.clone())
+            .unwrap();
+        assert_eq!(out.read().unwrap().shape(), &[1, 6]);
+        assert_eq!(
+            out.read().unwrap().as_f64(),
+            Some(&[1., 2., 3., 4., 5., 6.][..])
+        );
+    }
+
+    #[test]
+    fn cpu_executor_dtype_mismatch_test() {
+        let mut exec = CpuExecutor::new();
+        let lhs = f64_tensor(vec![1], vec![1.]);
+        let rhs = shared(CpuTensor::from_f32(vec![1], vec![1.]).unwrap());
+        let out = shared(CpuTensor::default());
+        assert_eq!(
+            exec.binary_compute_v2(MockOpCode::AddOp, lhs, rhs, out),
+            Err(RaptorsError::kernel(
+                &MockOpCode::AddOp,
+                "dtype mismatch F64 vs F32"
+            ))
+        );
+    }
+
+    #[test]
+    fn mock_executor_poisoned_operand_test() {
+        let mut exec = MockExecutor::new();
+        let inp = Arc::new(RwLock::new(MockTensor::new(MockOpCode::SinOp)));
+        let writer = inp.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = writer.write().unwrap();
+            panic!("poison the operand");
+        })
+        .join();
+        assert_eq!(
+            exec.unary_compute(MockOpCode::SinOp, inp),
+            Err(RaptorsError::kernel(
+                &MockOpCode::SinOp,
+                "operand lock poisoned"
+            ))
+        );
+    }
 }
@@ -0,0 +1,639 @@
+// LICENSE PLACEHOLDER
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+
+use tokio::sync::oneshot;
+use tracing::info;
+
+use crate::actor_ref::send_order;
+use crate::cost_model::OpCodeLike;
+use crate::errors::RaptorsError;
+use crate::executor_types::ExecutorLike;
+use crate::messages::{PayloadMessage, RaptorMessage, ReadySignal};
+use crate::system::ActorSystemHandle;
+use crate::tensor_types::TensorLike;
+
+/// handle of a tensor added to a TaskGraph
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TensorId(usize);
+
+#[derive(Clone, Debug)]
+enum GraphNode<O> {
+    Unary {
+        op: O,
+        inp: TensorId,
+        out: TensorId,
+    },
+    Binary {
+        op: O,
+        lhs: TensorId,
+        rhs: TensorId,
+        out: TensorId,
+    },
+}
+
+impl<O> GraphNode<O> {
+    fn inputs(&self) -> Vec<TensorId> {
+        match self {
+            GraphNode::Unary { inp, .. } => vec![*inp],
+            GraphNode::Binary { lhs, rhs, .. } => vec![*lhs, *rhs],
+        }
+    }
+
+    fn output(&self) -> TensorId {
+        match self {
+            GraphNode::Unary { out, .. } | GraphNode::Binary { out, .. } => *out,
+        }
+    }
+}
+
+/// Definition: TaskGraph builds a DAG of unary/binary ops over tensors and
+/// submits it to an actor system as NonRet*ComputeFunctorMsg.
+///
+/// Tensors without a producer are inputs of the graph and ready on submission,
+/// every other tensor is written by exactly one op node. Readiness channels,
+/// fan-out senders and the shared outputs are generated on submission. Nodes
+/// pinned to an actor are computed by that actor, the others by any actor the
+/// scheduling policy picks.
+///
+/// A node is issued once its inputs are ready, so that no actor waits for a
+/// producer still pending, whatever order the policy picks workloads in.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     // diamond: var1 -> (var2, var3) -> var4
+///     let mut graph = TaskGraph::<MockTensor, MockOpCode>::new();
+///     let var1 = graph.add_tensor(MockTensor::new(MockOpCode::AddOp));
+///     let var2 = graph.add_tensor(MockTensor::default());
+///     let var3 = graph.add_tensor(MockTensor::default());
+///     let var4 = graph.add_tensor(MockTensor::default());
+///     graph.add_unary(MockOpCode::ExpOp, var1, var2).unwrap();
+///     graph.add_unary(MockOpCode::SinOp, var1, var3).unwrap();
+///     graph.add_binary(MockOpCode::AddOp, var2, var3, var4).unwrap();
+///
+///     let mut system = build_mock_system!("Raptors", 2).unwrap();
+///     let done = graph.submit(&mut system).await.unwrap();
+///     assert!(done.await.is_ok());
+///     system.shutdown().await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TaskGraph<U, O>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    tensors: Vec<Arc<RwLock<U>>>,
+    // index of the node writing each tensor, if any
+    producers: Vec<Option<usize>>,
+    nodes: Vec<GraphNode<O>>,
+    // actor each node is pinned to, if any
+    placements: Vec<Option<usize>>,
+}
+
+impl<U, O> Default for TaskGraph<U, O>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    fn default() -> Self {
+        Self {
+            tensors: vec![],
+            producers: vec![],
+            nodes: vec![],
+            placements: vec![],
+        }
+    }
+}
+
+impl<U, O> TaskGraph<U, O>
+where
+    U: 'static + TensorLike + Clone + Send + Sync + Debug,
+    O: 'static + OpCodeLike + Debug + Send + Sync,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_tensor(&mut self, tensor: U) -> TensorId {
+        self.tensors.push(Arc::new(RwLock::new(tensor)));
+        self.producers.push(None);
+        TensorId(self.tensors.len() - 1)
+    }
+
+    /// add a tensor shared with the caller, several ids may share one tensor
+    /// as long as the graph orders their reads before the writes
+    pub fn add_shared(&mut self, tensor: Arc<RwLock<U>>) -> TensorId {
+        self.tensors.push(tensor);
+        self.producers.push(None);
+        TensorId(self.tensors.len() - 1)
+    }
+
+    /// the shared tensor, outputs hold the results once the graph is done
+    pub fn tensor(&self, id: TensorId) -> Option<Arc<RwLock<U>>> {
+        self.tensors.get(id.0).cloned()
+    }
+
+    /// add a node computing `out = op(inp)`, returns the index of the node
+    pub fn add_unary(
+        &mut self,
+        op: O,
+        inp: TensorId,
+        out: TensorId,
+    ) -> Result<usize, RaptorsError> {
+        self.add_node(GraphNode::Unary { op, inp, out })
+    }
+
+    /// add a node computing `out = op(lhs, rhs)`, returns the index of the node
+    pub fn add_binary(
+        &mut self,
+        op: O,
+        lhs: TensorId,
+        rhs: TensorId,
+        out: TensorId,
+    ) -> Result<usize, RaptorsError> {
+        self.add_node(GraphNode::Binary { op, lhs, rhs, out })
+    }
+
+    fn add_node(&mut self, node: GraphNode<O>) -> Result<usize, RaptorsError> {
+        let mut used = node.inputs();
+        used.push(node.output());
+        if let Some(unknown) = used.iter().find(|x| x.0 >= self.tensors.len()) {
+            return Err(RaptorsError::scheduling(format!(
+                "tensor #{} is not in the graph",
+                unknown.0
+            )));
+        }
+        let out = node.output().0;
+        if let Some(writer) = self.producers[out] {
+            return Err(RaptorsError::scheduling(format!(
+                "tensor #{} is written by node #{} already",
+                out, writer
+            )));
+        }
+        self.nodes.push(node);
+        self.placements.push(None);
+        self.producers[out] = Some(self.nodes.len() - 1);
+        Ok(self.nodes.len() - 1)
+    }
+
+    /// have the node computed by the actor of the id
+    pub fn pin(&mut self, node: usize, actor: usize) -> Result<(), RaptorsError> {
+        match self.placements.get_mut(node) {
+            Some(placement) => {
+                *placement = Some(actor);
+                Ok(())
+            }
+            None => Err(RaptorsError::scheduling(format!(
+                "node #{} is not in the graph",
+                node
+            ))),
+        }
+    }
+
+    /// the actor the node is pinned to, if any
+    pub fn placement(&self, node: usize) -> Option<usize> {
+        self.placements.get(node).copied().flatten()
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// nodes whose output is not read by any other node
+    pub fn sinks(&self) -> Vec<usize> {
+        let succs = successors(&self.deps());
+        (0..self.nodes.len())
+            .filter(|node| succs[*node].is_empty())
+            .collect()
+    }
+
+    // nodes each node depends on, once per input edge
+    fn deps(&self) -> Vec<Vec<usize>> {
+        self.nodes
+            .iter()
+            .map(|node| {
+                node.inputs()
+                    .iter()
+                    .filter_map(|inp| self.producers[inp.0])
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// issue the nodes reading only inputs of the graph, the others are issued
+    /// as soon as their inputs are ready; the returned future resolves once all
+    /// sink nodes are done, with the first failure if any. Await it before
+    /// shutting the system down, nodes issued afterwards are dropped.
+    pub async fn submit<T>(
+        &self,
+        system: &mut ActorSystemHandle<T, U, O>,
+    ) -> Result<GraphCompletion, RaptorsError>
+    where
+        T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync,
+    {
+        let deps = self.deps();
+        let order = topo_order(&deps)?;
+
+        // one readiness channel per input edge, inputs of the graph are ready
+        let mut respond_tos: Vec<Vec<oneshot::Sender<ReadySignal>>> =
+            self.nodes.iter().map(|_| vec![]).collect();
+        let mut checkers: Vec<VecDeque<oneshot::Receiver<ReadySignal>>> = vec![];
+        for node in self.nodes.iter() {
+            let mut inputs = VecDeque::new();
+            for inp in node.inputs() {
+                let (tx, rx) = oneshot::channel();
+                match self.producers[inp.0] {
+                    Some(producer) => respond_tos[producer].push(tx),
+                    None => {
+                        let _ = tx.send(Ok(()));
+                    }
+                }
+                inputs.push_back(rx);
+            }
+            checkers.push(inputs);
+        }
+        let mut sinks = vec![];
+        for node in self.sinks() {
+            let (tx, rx) = oneshot::channel();
+            respond_tos[node].push(tx);
+            sinks.push(rx);
+        }
+
+        let mut respond_tos: Vec<Option<_>> = respond_tos.into_iter().map(Some).collect();
+        for idx in order {
+            let respond_to = respond_tos[idx].take().unwrap_or_default();
+            // the actor is handed checkers relaying the joined readiness of
+            // the inputs, they are resolved by the time the node is issued
+            let inputs: Vec<_> = checkers[idx].drain(..).collect();
+            let (relays, mut relayed): (Vec<_>, VecDeque<_>) =
+                inputs.iter().map(|_| oneshot::channel()).unzip();
+            let mut next_checker = || relayed.pop_front().expect("one checker per input");
+            let msg = match &self.nodes[idx] {
+                GraphNode::Unary { op, inp, out } => PayloadMessage::NonRetUnaryComputeFunctorMsg {
+                    op: op.clone(),
+                    inp: self.tensors[inp.0].clone(),
+                    out: self.tensors[out.0].clone(),
+                    inp_ready_checker: next_checker(),
+                    respond_to,
+                    respond_id: idx,
+                },
+                GraphNode::Binary { op, lhs, rhs, out } => {
+                    PayloadMessage::NonRetBinaryComputeFunctorMsg {
+                        op: op.clone(),
+                        lhs: self.tensors[lhs.0].clone(),
+                        rhs: self.tensors[rhs.0].clone(),
+                        out: self.tensors[out.0].clone(),
+                        lhs_ready_checker: next_checker(),
+                        rhs_ready_checker: next_checker(),
+                        respond_to,
+                        respond_id: idx,
+                    }
+                }
+            };
+            let msg = match self.placements[idx] {
+                Some(actor) => RaptorMessage::PinnedMSG(actor, msg),
+                None => RaptorMessage::PayloadMSG(msg),
+            };
+            let ready = join_ready(inputs);
+            let release = async move {
+                let status = ready
+                    .await
+                    .unwrap_or_else(|_| Err(RaptorsError::system("input dropped before ready")));
+                for relay in relays {
+                    let _ = relay.send(status.clone());
+                }
+                msg
+            };
+            if deps[idx].is_empty() {
+                info!("::task-graph::submit node #{}", idx);
+                system.issue_order(release.await).await;
+            } else {
+                let sendbox = system.sendbox();
+                tokio::spawn(async move {
+                    let msg = release.await;
+                    info!("::task-graph::submit node #{}", idx);
+                    send_order(&sendbox, msg).await;
+                });
+            }
+        }
+        Ok(GraphCompletion {
+            done: join_ready(sinks),
+        })
+    }
+}
+
+/// Definition: GraphCompletion resolves once every sink node of a submitted
+/// TaskGraph is done, a failure of any node is forwarded to the sinks
+/// depending on it.
+#[derive(Debug)]
+pub struct GraphCompletion {
+    done: oneshot::Receiver<ReadySignal>,
+}
+
+impl GraphCompletion {
+    // resolves once the graph is done and `f` has finished the work left
+    pub(crate) fn then<F>(self, f: F) -> GraphCompletion
+    where
+        F: FnOnce() -> ReadySignal + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let status = match self.await {
+                Ok(()) => f(),
+                Err(e) => Err(e),
+            };
+            let _ = tx.send(status);
+        });
+        GraphCompletion { done: rx }
+    }
+}
+
+impl Future for GraphCompletion {
+    type Output = ReadySignal;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.done)
+            .poll(cx)
+            .map(|signal| signal.unwrap_or_else(|_| Err(RaptorsError::system("graph dropped"))))
+    }
+}
+
+// a single readiness signal that waits on all of the inputs, the first
+// failure is forwarded
+pub(crate) fn join_ready(
+    inputs: Vec<oneshot::Receiver<ReadySignal>>,
+) -> oneshot::Receiver<ReadySignal> {
+    let (tx, rx) = oneshot::channel();
+    tokio::spawn(async move {
+        let mut status = Ok(());
+        for input in inputs {
+            let signal = input
+                .await
+                .unwrap_or_else(|_| Err(RaptorsError::system("input dropped before ready")));
+            status = status.and(signal);
+        }
+        let _ = tx.send(status);
+    });
+    rx
+}
+
+pub(crate) fn successors(deps: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut succs = vec![vec![]; deps.len()];
+    for (idx, node_deps) in deps.iter().enumerate() {
+        for dep in node_deps.iter() {
+            succs[*dep].push(idx);
+        }
+    }
+    succs
+}
+
+// Kahn's algorithm over the deps of each node, fails on cycles
+pub(crate) fn topo_order(deps: &[Vec<usize>]) -> Result<Vec<usize>, RaptorsError> {
+    let succs = successors(deps);
+    let mut indegrees: Vec<usize> = deps.iter().map(|x| x.len()).collect();
+    let mut queue: VecDeque<usize> = (0..deps.len()).filter(|x| indegrees[*x] == 0).collect();
+    let mut order = vec![];
+    while let Some(node) = queue.pop_front() {
+        order.push(node);
+        for succ in succs[node].iter() {
+            indegrees[*succ] -= 1;
+            if indegrees[*succ] == 0 {
+                queue.push_back(*succ);
+            }
+        }
+    }
+    if order.len() != deps.len() {
+        return Err(RaptorsError::scheduling("task graph has a cycle"));
+    }
+    Ok(order)
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    fn new_graph() -> TaskGraph<MockTensor, MockOpCode> {
+        TaskGraph::new()
+    }
+
+    #[test]
+    fn graph_rejects_unknown_tensor_test() {
+        let mut graph = new_graph();
+        let var1 = graph.add_tensor(MockTensor::default());
+        assert_eq!(
+            graph.add_unary(MockOpCode::ExpOp, var1, TensorId(3)),
+            Err(RaptorsError::scheduling("tensor #3 is not in the graph"))
+        );
+    }
+
+    #[test]
+    fn graph_rejects_second_writer_test() {
+        let mut graph = new_graph();
+        let var1 = graph.add_tensor(MockTensor::default());
+        let var2 = graph.add_tensor(MockTensor::default());
+        assert_eq!(graph.add_unary(MockOpCode::ExpOp, var1, var2), Ok(0));
+        assert_eq!(
+            graph.add_unary(MockOpCode::SinOp, var1, var2),
+            Err(RaptorsError::scheduling(
+                "tensor #1 is written by node #0 already"
+            ))
+        );
+    }
+
+    #[test]
+    fn graph_sinks_test() {
+        let mut graph = new_graph();
+        let vars: Vec<TensorId> = (0..4)
+            .map(|_| graph.add_tensor(MockTensor::default()))
+            .collect();
+        graph
+            .add_unary(MockOpCode::ExpOp, vars[0], vars[1])
+            .unwrap();
+        graph
+            .add_unary(MockOpCode::SinOp, vars[1], vars[2])
+            .unwrap();
+        graph
+            .add_unary(MockOpCode::SinOp, vars[1], vars[3])
+            .unwrap();
+        assert_eq!(graph.sinks(), vec![1, 2]);
+    }
+
+    #[test]
+    fn topo_order_test() {
+        assert_eq!(
+            topo_order(&[vec![], vec![0], vec![0], vec![1, 2]]),
+            Ok(vec![0, 1, 2, 3])
+        );
+        assert_eq!(
+            topo_order(&[vec![1], vec![0]]),
+            Err(RaptorsError::scheduling("task graph has a cycle"))
+        );
+    }
+
+    #[tokio::test]
+    async fn submit_cyclic_graph_test() {
+        let mut graph = new_graph();
+        let var1 = graph.add_tensor(MockTensor::default());
+        let var2 = graph.add_tensor(MockTensor::default());
+        graph.add_unary(MockOpCode::ExpOp, var1, var2).unwrap();
+        graph.add_unary(MockOpCode::SinOp, var2, var1).unwrap();
+
+        let mut system = build_mock_system!("Raptors", 1).unwrap();
+        assert_eq!(
+            graph.submit(&mut system).await.err(),
+            Some(RaptorsError::scheduling("task graph has a cycle"))
+        );
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 0);
+    }
+
+    #[tokio::test]
+    async fn submit_fan_out_graph_test() {
+        // var1 feeds both sides of a binary op and a unary sink
+        let mut graph = new_graph();
+        let var1 = graph.add_tensor(MockTensor::default());
+        let var2 = graph.add_tensor(MockTensor::default());
+        let var3 = graph.add_tensor(MockTensor::default());
+        let var4 = graph.add_tensor(MockTensor::default());
+        graph.add_unary(MockOpCode::ExpOp, var1, var2).unwrap();
+        graph
+            .add_binary(MockOpCode::AddOp, var2, var2, var3)
+            .unwrap();
+        graph.add_unary(MockOpCode::SinOp, var2, var4).unwrap();
+        assert_eq!(graph.sinks(), vec![1, 2]);
+
+        let mut system = build_mock_system!("Raptors", 2).unwrap();
+        let done = graph.submit(&mut system).await.unwrap();
+        assert_eq!(done.await, Ok(()));
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 3);
+    }
+
+    #[tokio::test]
+    async fn submit_graph_under_shortest_job_first_test() {
+        // the cheaper consumers must not take the only actor before their
+        // producers: exp -> sin -> add
+        let mut graph = new_graph();
+        let vars: Vec<TensorId> = (0..4)
+            .map(|_| graph.add_tensor(MockTensor::default()))
+            .collect();
+        graph
+            .add_unary(MockOpCode::ExpOp, vars[0], vars[1])
+            .unwrap();
+        graph
+            .add_unary(MockOpCode::SinOp, vars[1], vars[2])
+            .unwrap();
+        graph
+            .add_binary(MockOpCode::AddOp, vars[2], vars[0], vars[3])
+            .unwrap();
+
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(1);
+        let mut system = SystemBuilder::new()
+            .build_with_policy::<MockExecutor, MockTensor, MockOpCode>(
+                sys_config,
+                Box::new(ShortestJobFirst::default()),
+            )
+            .unwrap();
+        let done = graph.submit(&mut system).await.unwrap();
+        let done = tokio::time::timeout(std::time::Duration::from_secs(10), done).await;
+        assert_eq!(done.expect("graph hangs"), Ok(()));
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 3);
+    }
+
+    #[tokio::test]
+    async fn submit_graph_on_cpu_executor_test() {
+        // y = exp(x) * sin(x) + x @ w
+        let mut graph = TaskGraph::<CpuTensor, MockOpCode>::new();
+        let x = graph.add_tensor(CpuTensor::from_f64(vec![1, 2], vec![0.5, 1.5]).unwrap());
+        let w = graph.add_tensor(CpuTensor::from_f64(vec![2, 2], vec![1., 2., 3., 4.]).unwrap());
+        let vars: Vec<TensorId> = (0..5)
+            .map(|_| graph.add_tensor(CpuTensor::default()))
+            .collect();
+        graph.add_unary(MockOpCode::ExpOp, x, vars[0]).unwrap();
+        graph.add_unary(MockOpCode::SinOp, x, vars[1]).unwrap();
+        graph
+            .add_binary(MockOpCode::MulOp, vars[0], vars[1], vars[2])
+            .unwrap();
+        graph
+            .add_binary(MockOpCode::MatmulOp, x, w, vars[3])
+            .unwrap();
+        graph
+            .add_binary(MockOpCode::AddOp, vars[2], vars[3], vars[4])
+            .unwrap();
+
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(2);
+        let mut system = SystemBuilder::new()
+            .build_with_config::<CpuExecutor, CpuTensor, MockOpCode>(sys_config)
+            .unwrap();
+        let done = graph.submit(&mut system).await.unwrap();
+        assert_eq!(done.await, Ok(()));
+        system.shutdown().await.unwrap();
+
+        let expected: Vec<f64> = [(0.5_f64, 0.5 + 4.5), (1.5, 1. + 6.)]
+            .iter()
+            .map(|(x, xw)| x.exp() * x.sin() + xw)
+            .collect();
+        let y = graph.tensor(vars[4]).unwrap();
+        let y = y.read().unwrap();
+        assert_eq!(y.shape(), &[1, 2]);
+        assert_eq!(y.as_f64(), Some(&expected[..]));
+    }
+
+    #[tokio::test]
+    async fn submit_pinned_graph_test() {
+        let mut graph = TaskGraph::<CpuTensor, MockOpCode>::new();
+        let lhs = graph.add_tensor(CpuTensor::from_f32(vec![1], vec![1.0]).unwrap());
+        let rhs = graph.add_tensor(CpuTensor::from_f64(vec![1], vec![1.0]).unwrap());
+        let out = graph.add_tensor(CpuTensor::default());
+        let node = graph.add_binary(MockOpCode::AddOp, lhs, rhs, out).unwrap();
+        assert!(graph.pin(node + 1, 0).is_err());
+        graph.pin(node, 2).unwrap();
+
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(3);
+        let mut system = SystemBuilder::new()
+            .build_with_config::<CpuExecutor, CpuTensor, MockOpCode>(sys_config)
+            .unwrap();
+        let done = graph.submit(&mut system).await.unwrap();
+        // the dtype mismatch is reported by the actor the node is pinned to
+        assert_eq!(done.await.unwrap_err().actor_id(), Some(2));
+        system.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn submit_poisoned_graph_test() {
+        let mut graph = new_graph();
+        let var1 = graph.add_tensor(MockTensor::default());
+        let var2 = graph.add_tensor(MockTensor::default());
+        let var3 = graph.add_tensor(MockTensor::default());
+        graph.add_unary(MockOpCode::ExpOp, var1, var2).unwrap();
+        graph.add_unary(MockOpCode::SinOp, var2, var3).unwrap();
+        let writer = graph.tensor(var1).unwrap();
+        let _ = std::thread::spawn(move || {
+            let _guard = writer.write().unwrap();
+            panic!("poison the operand");
+        })
+        .join();
+
+        let mut system = build_mock_system!("Raptors", 1).unwrap();
+        let done = graph.submit(&mut system).await.unwrap();
+        match done.await {
+            Err(RaptorsError::Executor { opcode, .. }) => assert_eq!(opcode, "ExpOp"),
+            others => panic!("expect the root failure, got {:?}", others),
+        }
+        system.shutdown().await.unwrap();
+    }
+}
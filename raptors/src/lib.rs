@@ -1,11 +1,27 @@
+pub mod actor_ref;
+pub mod actor_registry;
 pub mod actors;
+pub mod blocking_pool;
 pub mod builders;
+pub mod collectives;
+pub mod cost_function;
+pub mod cost_learner;
 pub mod cost_model;
+pub mod dispatcher;
+pub mod errors;
+pub mod executor_registry;
 pub mod executor_types;
+pub mod graph;
 pub mod mailbox;
 pub mod messages;
+pub mod scheduling;
+pub mod static_scheduler;
+pub mod supervisor;
 pub mod system;
 pub mod tensor_types;
+pub mod topics;
+pub mod transport;
+pub mod wire;
 
 /// Prelude module for users to import
 ///
@@ -13,16 +29,43 @@ pub mod tensor_types;
 /// use raptors::prelude::*;
 /// ```
 pub mod prelude {
+    pub use crate::actor_ref::ActorRef;
+    pub use crate::actor_registry::ActorRegistry;
     pub use crate::actors::Actor;
-    pub use crate::cost_model::{CostModel, MockOpCode, OpCodeLike};
-    pub use crate::executor_types::{ExecutorLike, MockExecutor};
-    pub use crate::mailbox::{Address, Envelope, Len, Mailbox};
+    pub use crate::blocking_pool::BlockingPool;
+    pub use crate::collectives::ActorGroup;
+    pub use crate::cost_function::{CostFunction, Roofline, TableInterpolation, Work};
+    pub use crate::cost_learner::{CostLearner, Estimator, LearnedCost};
+    pub use crate::cost_model::{CostFallback, CostModel, MockOpCode, OpCodeLike};
+    pub use crate::dispatcher::{Dispatcher, PendingQueue, Route};
+    pub use crate::errors::RaptorsError;
+    pub use crate::executor_registry::ExecutorRegistry;
+    pub use crate::executor_types::{
+        BoxedExecutor, CpuExecutor, DynExecutor, ExecutorLike, MockExecutor,
+    };
+    pub use crate::graph::{GraphCompletion, TaskGraph, TensorId};
+    pub use crate::mailbox::{
+        Address, Envelope, Len, MailReceiver, MailSender, Mailbox, MailboxProbe, OverflowPolicy,
+        Prioritized, Priority,
+    };
     pub use crate::messages::{
         ActorCommand, ActorMsg, LoadfreeMessage, MessageLike, PayloadMessage, RaptorMessage,
-        SystemCommand, SystemMsg,
+        ReadySignal, SystemCommand, SystemMsg,
+    };
+    pub use crate::scheduling::{Fifo, LeastLoaded, SchedulingPolicy, ShortestJobFirst};
+    pub use crate::static_scheduler::{
+        HeftScheduler, StaticRunReport, StaticSchedule, StaticTask, TaskSlot,
+    };
+    pub use crate::supervisor::SupervisorStrategy;
+    pub use crate::system::{
+        ActorSystem, ActorSystemHandle, ShutdownSummary, SystemBuilder, SystemConfig,
+    };
+    pub use crate::tensor_types::{
+        Chunked, CpuData, CpuTensor, DType, MockTensor, MockTensorMsg, TensorLike, TensorMeta,
     };
-    pub use crate::system::{ActorSystem, ActorSystemHandle, SystemBuilder, SystemConfig};
-    pub use crate::tensor_types::{MockTensor, MockTensorMsg, TensorLike};
+    pub use crate::topics::Topics;
+    pub use crate::transport::{Frame, RemoteSystem};
+    pub use crate::wire::{WireKind, WireTensor};
 
     // macros that simplifies the interfaces
     pub use crate::{build_loadfree_msg, build_mock_system, build_msg, try_init_raptors};
@@ -1,10 +1,17 @@
 // LICENSE PLACEHOLDER
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
 use uuid::Uuid;
 
+use crate::errors::RaptorsError;
 use crate::messages::*;
 use crate::prelude::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Address(Uuid);
 
 impl Address {
@@ -17,27 +24,113 @@ impl Address {
     }
 }
 
+impl From<Uuid> for Address {
+    fn from(uuid: Uuid) -> Self {
+        Address(uuid)
+    }
+}
+
+/// msg on its way from the sender to the actor at the receiver address
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Envelope {
-    pub msg: LoadfreeMessage<MockTensor>,
-    // sender: Address,
+pub struct Envelope<M = LoadfreeMessage<MockTensor>> {
+    pub msg: M,
+    pub sender: Address,
     pub receiver: Address,
 }
 
+impl<M> Envelope<M> {
+    pub fn new(sender: Address, receiver: Address, msg: M) -> Self {
+        Self {
+            msg,
+            sender,
+            receiver,
+        }
+    }
+}
+
+/// lanes of a mailbox, msgs of the system lane are taken first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    System,
+    Compute,
+}
+
+/// msgs tell the mailbox lane they go to
+pub trait Prioritized {
+    fn priority(&self) -> Priority {
+        Priority::Compute
+    }
+}
+
+impl<T> Prioritized for LoadfreeMessage<T>
+where
+    T: TensorLike + Clone,
+{
+    fn priority(&self) -> Priority {
+        match self {
+            LoadfreeMessage::SystemMsg(_) | LoadfreeMessage::ActorMsg(_) => Priority::System,
+            // data is ordered with the workloads it applies to
+            LoadfreeMessage::MockTensorMsg(_) | LoadfreeMessage::DataMsg(..) => Priority::Compute,
+        }
+    }
+}
+
+impl<T, O> Prioritized for RaptorMessage<T, O>
+where
+    T: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    fn priority(&self) -> Priority {
+        match self {
+            RaptorMessage::LoadfreeMSG(msg) => msg.priority(),
+            _ => Priority::Compute,
+        }
+    }
+}
+
+/// what a mailbox does with a msg arriving on a full lane
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    // the sender waits until the lane has room
+    #[default]
+    Block,
+    // the oldest msg of the lane is dropped to make room
+    DropOldest,
+    // the msg is refused with a mailbox error
+    Reject,
+}
+
 // wrap a dedicated executor module that only consider how to do computations
 //
 // TODO(long-term):
 // as a interface, make refactor as Trait and expose to CRT level,
 // make CRT vm to impl this trait
+/// Definition: Mailbox is a bounded queue of msgs with one ring buffer per
+/// priority lane, each holding up to `capacity` msgs.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// let mut mbx = Mailbox::<RaptorMessage<MockTensor, MockOpCode>>::with_capacity(
+///     1,
+///     OverflowPolicy::DropOldest,
+/// );
+/// mbx.enqueue(build_msg!("add-op")).unwrap();
+/// // the add-op is displaced by the exp-op
+/// assert!(mbx.enqueue(build_msg!("exp-op")).unwrap().is_some());
+/// mbx.enqueue(build_msg!("halt-all")).unwrap();
+/// assert_eq!(mbx.len(), 2);
+/// assert!(matches!(
+///     mbx.dequeue(),
+///     Some(RaptorMessage::LoadfreeMSG(LoadfreeMessage::SystemMsg(_)))
+/// ));
+/// ```
 #[derive(Debug, PartialEq, Eq)]
-pub struct Mailbox {
-    // TODO replace with ringbuffer or deque
-    // references:
-    // *** circular-queue
-    // *** ringbuffer
-    // *** ringbuf
-    // *** ring_queue
-    mails: Vec<LoadfreeMessage<MockTensor>>,
+pub struct Mailbox<M> {
+    capacity: usize,
+    overflow: OverflowPolicy,
+    system_lane: VecDeque<M>,
+    compute_lane: VecDeque<M>,
 }
 
 // TODO traits tobe put together, this is a trait only for util, not for interface
@@ -47,49 +140,361 @@ pub trait Len {
     fn is_empty(&self) -> bool;
 }
 
-impl Len for Mailbox {
+impl<M> Len for Mailbox<M> {
     fn is_empty(&self) -> bool {
-        self.mails.is_empty()
+        self.system_lane.is_empty() && self.compute_lane.is_empty()
     }
 
     fn len(&self) -> usize {
-        self.mails.len()
+        self.system_lane.len() + self.compute_lane.len()
     }
 }
 
-impl Mailbox {
+impl<M> Default for Mailbox<M> {
+    fn default() -> Self {
+        Self::with_capacity(16, OverflowPolicy::default())
+    }
+}
+
+impl<M> Mailbox<M> {
     pub fn new() -> Self {
-        return Self { mails: vec![] };
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize, overflow: OverflowPolicy) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            overflow,
+            system_lane: VecDeque::with_capacity(capacity),
+            compute_lane: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow
+    }
+
+    fn lane(&mut self, priority: Priority) -> &mut VecDeque<M> {
+        match priority {
+            Priority::System => &mut self.system_lane,
+            Priority::Compute => &mut self.compute_lane,
+        }
+    }
+
+    pub fn is_full(&self, priority: Priority) -> bool {
+        match priority {
+            Priority::System => self.system_lane.len() >= self.capacity,
+            Priority::Compute => self.compute_lane.len() >= self.capacity,
+        }
+    }
+
+    pub fn dequeue(&mut self) -> Option<M> {
+        self.system_lane
+            .pop_front()
+            .or_else(|| self.compute_lane.pop_front())
+    }
+
+    pub fn clear(&mut self) {
+        self.system_lane.clear();
+        self.compute_lane.clear();
+    }
+}
+
+impl<M: Clone> Mailbox<M> {
+    #[allow(dead_code)]
+    fn mails(&self) -> Vec<M> {
+        self.system_lane
+            .iter()
+            .chain(self.compute_lane.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+impl<M: Prioritized> Mailbox<M> {
+    /// put the msg into its lane, returns the msg it displaced if any; a full
+    /// lane refuses msgs unless the policy is DropOldest
+    pub fn enqueue(&mut self, msg: M) -> Result<Option<M>, RaptorsError> {
+        self.try_enqueue(msg).map_err(|(e, _)| e)
+    }
+
+    // as `enqueue`, a refused msg is handed back with the error
+    fn try_enqueue(&mut self, msg: M) -> Result<Option<M>, (RaptorsError, M)> {
+        let priority = msg.priority();
+        let displaced = match (self.is_full(priority), self.overflow) {
+            (false, _) => None,
+            (true, OverflowPolicy::DropOldest) => self.lane(priority).pop_front(),
+            (true, _) => {
+                let e = RaptorsError::mailbox(format!(
+                    "{:?} lane is full with {} msgs",
+                    priority, self.capacity
+                ));
+                return Err((e, msg));
+            }
+        };
+        self.lane(priority).push_back(msg);
+        Ok(displaced)
+    }
+}
+
+// state shared by the two ends of a mailbox channel
+#[derive(Debug)]
+struct Shared<M> {
+    mailbox: Mutex<Mailbox<M>>,
+    // woken on new msgs and on the last sender leaving
+    recv_ready: Notify,
+    // woken when a msg is taken out or the receiver is gone
+    send_ready: Notify,
+    senders: AtomicUsize,
+    closed: AtomicBool,
+}
+
+impl<M> Shared<M> {
+    // a panicking holder leaves the queue itself consistent
+    fn lock(&self) -> MutexGuard<'_, Mailbox<M>> {
+        self.mailbox.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// create a mailbox shared by senders and one receiver, as mpsc channels do
+pub fn channel<M>(capacity: usize, overflow: OverflowPolicy) -> (MailSender<M>, MailReceiver<M>) {
+    let shared = Arc::new(Shared {
+        mailbox: Mutex::new(Mailbox::with_capacity(capacity, overflow)),
+        recv_ready: Notify::new(),
+        send_ready: Notify::new(),
+        senders: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+    });
+    (
+        MailSender {
+            shared: shared.clone(),
+        },
+        MailReceiver { shared },
+    )
+}
+
+/// sending end of a mailbox
+#[derive(Debug)]
+pub struct MailSender<M> {
+    shared: Arc<Shared<M>>,
+}
+
+impl<M> Clone for MailSender<M> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::SeqCst);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<M> Drop for MailSender<M> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shared.recv_ready.notify_one();
+        }
+    }
+}
+
+impl<M: Prioritized> MailSender<M> {
+    /// put the msg into the mailbox, waiting for room if the policy is Block;
+    /// returns the msg it displaced if any
+    pub async fn send(&self, msg: M) -> Result<Option<M>, RaptorsError> {
+        self.deliver(msg).await.map_err(|(e, _)| e)
+    }
+
+    /// as `send`, a refused msg is handed back with the error
+    pub async fn deliver(&self, msg: M) -> Result<Option<M>, (RaptorsError, M)> {
+        loop {
+            let room = self.shared.send_ready.notified();
+            {
+                let mut mailbox = self.shared.lock();
+                if self.is_closed() {
+                    return Err((RaptorsError::mailbox("receiver is gone"), msg));
+                }
+                let must_wait = mailbox.overflow_policy() == OverflowPolicy::Block
+                    && mailbox.is_full(msg.priority());
+                if !must_wait {
+                    let displaced = mailbox.try_enqueue(msg);
+                    self.shared.recv_ready.notify_one();
+                    return displaced;
+                }
+            }
+            room.await;
+        }
+    }
+}
+
+impl<M> MailSender<M> {
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::SeqCst)
+    }
+
+    /// number of msgs waiting in the mailbox
+    pub fn depth(&self) -> usize {
+        self.shared.lock().len()
+    }
+
+    /// a view on the mailbox that does not keep it open
+    pub fn probe(&self) -> MailboxProbe<M> {
+        MailboxProbe {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+/// receiving end of a mailbox, owned by the actor
+#[derive(Debug)]
+pub struct MailReceiver<M> {
+    shared: Arc<Shared<M>>,
+}
+
+impl<M> MailReceiver<M> {
+    /// next msg, system msgs first; None once every sender is gone and the
+    /// mailbox is empty
+    pub async fn recv(&mut self) -> Option<M> {
+        loop {
+            let arrival = self.shared.recv_ready.notified();
+            {
+                let mut mailbox = self.shared.lock();
+                if let Some(msg) = mailbox.dequeue() {
+                    self.shared.send_ready.notify_waiters();
+                    return Some(msg);
+                }
+                if self.shared.senders.load(Ordering::SeqCst) == 0 {
+                    return None;
+                }
+            }
+            arrival.await;
+        }
     }
 
-    fn mails(&self) -> Vec<LoadfreeMessage<MockTensor>> {
-        self.mails.clone()
+    pub fn depth(&self) -> usize {
+        self.shared.lock().len()
     }
+}
 
-    pub fn enqueue(&mut self, msg: LoadfreeMessage<MockTensor>) -> Result<(), String> {
-        self.mails.push(msg);
-        Ok(())
+impl<M> Drop for MailReceiver<M> {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::SeqCst);
+        // msgs left behind are never read, drop them together with their
+        // reply channels
+        self.shared.lock().clear();
+        self.shared.send_ready.notify_waiters();
     }
+}
 
-    pub fn dequeue(&mut self) -> Option<LoadfreeMessage<MockTensor>> {
-        if self.mails.is_empty() {
-            None
-        } else {
-            Some(self.mails.remove(0))
+/// read-only view on a mailbox to observe its depth
+#[derive(Debug)]
+pub struct MailboxProbe<M> {
+    shared: Arc<Shared<M>>,
+}
+
+impl<M> Clone for MailboxProbe<M> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
         }
     }
 }
 
+impl<M> MailboxProbe<M> {
+    pub fn depth(&self) -> usize {
+        self.shared.lock().len()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::SeqCst)
+    }
+}
+
 // unit tests
 #[cfg(test)]
-
 mod tests {
     use super::*;
-    use std::time;
 
     #[test]
     fn create_mailbox_test() {
-        let mbx = Mailbox::new();
+        let mbx = Mailbox::<LoadfreeMessage<MockTensor>>::new();
         assert_eq!(mbx.mails(), vec![]);
+        assert_eq!(mbx.capacity(), 16);
+    }
+
+    #[test]
+    fn mailbox_system_lane_goes_first_test() {
+        let mut mbx = Mailbox::new();
+        mbx.enqueue(build_loadfree_msg!("add-op")).unwrap();
+        mbx.enqueue(build_loadfree_msg!("halt", 1)).unwrap();
+        mbx.enqueue(build_loadfree_msg!("exp-op")).unwrap();
+        assert_eq!(
+            mbx.mails(),
+            vec![
+                build_loadfree_msg!("halt", 1),
+                build_loadfree_msg!("add-op"),
+                build_loadfree_msg!("exp-op"),
+            ]
+        );
+        assert_eq!(mbx.dequeue(), Some(build_loadfree_msg!("halt", 1)));
+        assert_eq!(mbx.dequeue(), Some(build_loadfree_msg!("add-op")));
+        assert_eq!(mbx.len(), 1);
+    }
+
+    #[test]
+    fn mailbox_overflow_policies_test() {
+        let mut mbx = Mailbox::with_capacity(2, OverflowPolicy::Reject);
+        mbx.enqueue(build_loadfree_msg!("add-op")).unwrap();
+        mbx.enqueue(build_loadfree_msg!("sub-op")).unwrap();
+        assert_eq!(
+            mbx.enqueue(build_loadfree_msg!("exp-op")),
+            Err(RaptorsError::mailbox("Compute lane is full with 2 msgs"))
+        );
+        // lanes are bounded separately
+        assert_eq!(mbx.enqueue(build_loadfree_msg!("halt", 0)), Ok(None));
+
+        let mut mbx = Mailbox::with_capacity(2, OverflowPolicy::DropOldest);
+        mbx.enqueue(build_loadfree_msg!("add-op")).unwrap();
+        mbx.enqueue(build_loadfree_msg!("sub-op")).unwrap();
+        assert_eq!(
+            mbx.enqueue(build_loadfree_msg!("exp-op")),
+            Ok(Some(build_loadfree_msg!("add-op")))
+        );
+        assert_eq!(mbx.dequeue(), Some(build_loadfree_msg!("sub-op")));
+    }
+
+    #[tokio::test]
+    async fn mailbox_channel_blocks_when_full_test() {
+        let (sender, mut receiver) =
+            channel::<LoadfreeMessage<MockTensor>>(1, OverflowPolicy::Block);
+        sender.send(build_loadfree_msg!("add-op")).await.unwrap();
+        assert_eq!(sender.depth(), 1);
+
+        let blocked = sender.clone();
+        let pending =
+            tokio::spawn(async move { blocked.send(build_loadfree_msg!("exp-op")).await });
+        tokio::task::yield_now().await;
+        assert!(!pending.is_finished());
+
+        assert_eq!(receiver.recv().await, Some(build_loadfree_msg!("add-op")));
+        assert_eq!(pending.await.unwrap(), Ok(None));
+        assert_eq!(receiver.recv().await, Some(build_loadfree_msg!("exp-op")));
+        drop(sender);
+        assert_eq!(receiver.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn mailbox_channel_closes_with_receiver_test() {
+        let (sender, receiver) = channel::<LoadfreeMessage<MockTensor>>(1, OverflowPolicy::Block);
+        let probe = sender.probe();
+        sender.send(build_loadfree_msg!("add-op")).await.unwrap();
+        assert_eq!(probe.depth(), 1);
+        drop(receiver);
+        assert!(sender.is_closed() && probe.is_closed());
+        assert_eq!(probe.depth(), 0);
+        assert!(sender.send(build_loadfree_msg!("add-op")).await.is_err());
     }
 }
@@ -2,14 +2,21 @@
 use std::sync::{Arc, RwLock};
 // TODO replace Arc, RwLock with tokio Arc/RwLock
 
+use serde::{Deserialize, Serialize};
 use tokio::sync::oneshot;
 
 use crate::cost_model::OpCodeLike;
-use crate::tensor_types::TensorLike;
+use crate::errors::RaptorsError;
+use crate::mailbox::Address;
+use crate::tensor_types::{TensorLike, TensorMeta};
 
 // Message Trait
 pub trait MessageLike {}
 
+// readiness signal of a variable computed by a NonRet*ComputeFunctorMsg, a
+// failure poisons the variable and is forwarded to every dependent
+pub type ReadySignal = Result<(), RaptorsError>;
+
 // Raptor Top-level Message Type
 #[derive(Debug)]
 pub enum RaptorMessage<T, O>
@@ -19,6 +26,13 @@ where
 {
     LoadfreeMSG(LoadfreeMessage<T>),
     PayloadMSG(PayloadMessage<T, O>),
+    // payload pinned to the actor of the given id by a static schedule, the
+    // system delivers it as a PayloadMSG bypassing the scheduling policy
+    PinnedMSG(usize, PayloadMessage<T, O>),
+    // payload for any actor whose executor has the given typeid
+    PoolMSG(usize, PayloadMessage<T, O>),
+    // msg fanned out to the subscribers of the named topic
+    PublishMSG(String, LoadfreeMessage<T>),
 }
 
 impl<T, O> MessageLike for RaptorMessage<T, O>
@@ -27,6 +41,47 @@ where
     O: OpCodeLike,
 {
 }
+
+impl<T, O> RaptorMessage<T, O>
+where
+    T: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    /// workloads are the messages dispatched to an available actor, every one of
+    /// them is acknowledged by the actor with an `ActorCommand::Available` once done
+    pub fn is_workload(&self) -> bool {
+        matches!(
+            self,
+            RaptorMessage::PayloadMSG(_)
+                | RaptorMessage::PinnedMSG(..)
+                | RaptorMessage::PoolMSG(..)
+                | RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(_))
+        )
+    }
+
+    /// the workload is dropped without being computed, its submitter or the
+    /// dependents of its output get the error; other msgs are just dropped
+    pub fn reject(self, e: RaptorsError) {
+        let payload = match self {
+            RaptorMessage::PayloadMSG(payload)
+            | RaptorMessage::PinnedMSG(_, payload)
+            | RaptorMessage::PoolMSG(_, payload) => payload,
+            _ => return,
+        };
+        match payload {
+            PayloadMessage::ComputeFunctorMsg { respond_to, .. }
+            | PayloadMessage::UnaryComputeFunctorMsg { respond_to, .. } => {
+                let _ = respond_to.send(Err(e));
+            }
+            PayloadMessage::NonRetUnaryComputeFunctorMsg { respond_to, .. }
+            | PayloadMessage::NonRetBinaryComputeFunctorMsg { respond_to, .. } => {
+                for x in respond_to {
+                    let _ = x.send(Err(e.clone()));
+                }
+            }
+        }
+    }
+}
 impl<T> MessageLike for LoadfreeMessage<T> where T: TensorLike + Clone {}
 impl<T, O> MessageLike for PayloadMessage<T, O>
 where
@@ -47,7 +102,7 @@ where
 /// }
 /// assert!(test_msg_type(msg.into()));
 ///```
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LoadfreeMessage<T>
 where
     T: TensorLike + Clone,
@@ -55,6 +110,9 @@ where
     SystemMsg(SystemCommand),
     ActorMsg(ActorCommand),
     MockTensorMsg(T),
+    // data published to the named topic, e.g. updated parameters, handed to
+    // the executor of each subscriber
+    DataMsg(String, T),
 }
 
 // PayloadMessage with payloads
@@ -68,19 +126,19 @@ where
         op: O,
         lhs: Arc<RwLock<T>>,
         rhs: Arc<RwLock<T>>,
-        respond_to: oneshot::Sender<T>,
+        respond_to: oneshot::Sender<Result<T, RaptorsError>>,
     },
     UnaryComputeFunctorMsg {
         op: O,
         inp: Arc<RwLock<T>>,
-        respond_to: oneshot::Sender<T>,
+        respond_to: oneshot::Sender<Result<T, RaptorsError>>,
     },
     NonRetUnaryComputeFunctorMsg {
         op: O,
         inp: Arc<RwLock<T>>,
         out: Arc<RwLock<T>>,
-        inp_ready_checker: oneshot::Receiver<u8>,
-        respond_to: Vec<oneshot::Sender<u8>>,
+        inp_ready_checker: oneshot::Receiver<ReadySignal>,
+        respond_to: Vec<oneshot::Sender<ReadySignal>>,
         respond_id: usize,
     },
     NonRetBinaryComputeFunctorMsg {
@@ -88,60 +146,105 @@ where
         lhs: Arc<RwLock<T>>,
         rhs: Arc<RwLock<T>>,
         out: Arc<RwLock<T>>,
-        lhs_ready_checker: oneshot::Receiver<u8>,
-        rhs_ready_checker: oneshot::Receiver<u8>,
-        respond_to: Vec<oneshot::Sender<u8>>,
+        lhs_ready_checker: oneshot::Receiver<ReadySignal>,
+        rhs_ready_checker: oneshot::Receiver<ReadySignal>,
+        respond_to: Vec<oneshot::Sender<ReadySignal>>,
         respond_id: usize,
     },
 }
 
+impl<T, O> PayloadMessage<T, O>
+where
+    T: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    pub fn op(&self) -> &O {
+        match self {
+            PayloadMessage::ComputeFunctorMsg { op, .. }
+            | PayloadMessage::UnaryComputeFunctorMsg { op, .. }
+            | PayloadMessage::NonRetUnaryComputeFunctorMsg { op, .. }
+            | PayloadMessage::NonRetBinaryComputeFunctorMsg { op, .. } => op,
+        }
+    }
+
+    /// metadata of the input operands, empty if any of them holds none or is
+    /// being written, the estimation must not wait for a producer
+    pub fn operand_metas(&self) -> Vec<TensorMeta> {
+        let operands = match self {
+            PayloadMessage::UnaryComputeFunctorMsg { inp, .. }
+            | PayloadMessage::NonRetUnaryComputeFunctorMsg { inp, .. } => vec![inp],
+            PayloadMessage::ComputeFunctorMsg { lhs, rhs, .. }
+            | PayloadMessage::NonRetBinaryComputeFunctorMsg { lhs, rhs, .. } => vec![lhs, rhs],
+        };
+        operands
+            .into_iter()
+            .map(|operand| operand.try_read().ok().and_then(|x| x.meta()))
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default()
+    }
+}
+
 // SystemMsg that received and processed only by actor_system
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct SystemMsg {
     cmd: SystemCommand,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SystemCommand {
     HaltAll,
     HaltOn(usize),
+    // halt the actor at the address
+    Halt(Address),
     // WIP first usize is typeid, second usize is cnt
     Spawn(usize, usize),
+    // typeid, the id reserved for the actor and its name
+    SpawnNamed(usize, usize, String),
+    // topic and the id of the actor to (un)subscribe
+    Subscribe(String, usize),
+    Unsubscribe(String, usize),
+    // stop accepting workloads, drain the delayed ones and join all actors
+    Shutdown,
 }
 
 impl SystemMsg {
     pub fn new(cmd: SystemCommand) -> Self {
-        return Self { cmd: cmd };
+        Self { cmd }
     }
 }
 
-impl<T: TensorLike + Clone> Into<LoadfreeMessage<T>> for SystemCommand {
-    fn into(self) -> LoadfreeMessage<T> {
-        LoadfreeMessage::<T>::SystemMsg(self)
+impl<T: TensorLike + Clone> From<SystemCommand> for LoadfreeMessage<T> {
+    fn from(cmd: SystemCommand) -> LoadfreeMessage<T> {
+        LoadfreeMessage::<T>::SystemMsg(cmd)
     }
 }
 
 // ActorMsg received and processed by working actors
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ActorMsg {
     cmd: ActorCommand,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ActorCommand {
-    Available(usize),
+    // every actor cmd carries the actor id and the generation of the actor
+    // under that id, a restart starts the next generation
+    Available(usize, usize),
+    // lifecycle events reported by the supervisor watching the actor task
+    Stopped(usize, usize),
+    Panicked(usize, usize),
     PLACEHOLDER,
 }
 
 impl ActorMsg {
     pub fn new(cmd: ActorCommand) -> Self {
-        return Self { cmd: cmd };
+        Self { cmd }
     }
 }
 
-impl<T: TensorLike + Clone> Into<LoadfreeMessage<T>> for ActorCommand {
-    fn into(self) -> LoadfreeMessage<T> {
-        LoadfreeMessage::<T>::ActorMsg(self)
+impl<T: TensorLike + Clone> From<ActorCommand> for LoadfreeMessage<T> {
+    fn from(cmd: ActorCommand) -> LoadfreeMessage<T> {
+        LoadfreeMessage::<T>::ActorMsg(cmd)
     }
 }
 
@@ -0,0 +1,604 @@
+// LICENSE PLACEHOLDER
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::time::Duration;
+
+use crate::cost_model::{CostModel, MockOpCode, OpCodeLike};
+use crate::messages::{LoadfreeMessage, RaptorMessage};
+use crate::tensor_types::{TensorLike, TensorMeta};
+
+/// Definition: SchedulingPolicy decides which workload the actor system
+/// dispatches next and to which actor.
+///
+/// The system asks the policy for an actor whenever a workload arrives; if none
+/// is selected the workload is delayed. Once an actor becomes available again,
+/// the policy picks the delayed workload to dispatch.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut sys_config = SystemConfig::new("Raptors", "info");
+///     sys_config.set_ranks(2);
+///     let mut system = SystemBuilder::new().build_with_policy::<MockExecutor, MockTensor, MockOpCode>(
+///         sys_config,
+///         Box::new(ShortestJobFirst::new(CostModel::new())),
+///     ).unwrap();
+///     system.issue_order(build_msg!("exp-op")).await;
+///     system.issue_order(build_msg!("add-op")).await;
+///     let summary = system.shutdown().await.unwrap();
+///     assert_eq!(summary.tasks_completed, 2);
+/// }
+/// ```
+pub trait SchedulingPolicy<U, O>: Send + Debug
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    /// index of the delayed workload to dispatch next
+    fn select_workload(&mut self, delayed: &[RaptorMessage<U, O>]) -> Option<usize> {
+        if delayed.is_empty() {
+            None
+        } else {
+            Some(0)
+        }
+    }
+
+    /// id of the actor to dispatch the workload to, picked among the available
+    /// actors or, for policies that queue workloads on busy actors, the live ones
+    fn select_actor(
+        &mut self,
+        msg: &RaptorMessage<U, O>,
+        availables: &[usize],
+        lives: &[usize],
+    ) -> Option<usize>;
+
+    /// the workload is delivered to the actor
+    fn on_assigned(&mut self, _actor: usize, _msg: &RaptorMessage<U, O>) {}
+
+    /// the actor has finished the oldest workload delivered to it
+    fn on_completed(&mut self, _actor: usize) {}
+
+    /// the workload assigned to the actor never reached it or was displaced
+    /// from its mailbox, it is not computed
+    fn on_undelivered(&mut self, _actor: usize, _msg: &RaptorMessage<U, O>) {}
+
+    /// the actor is gone together with every workload queued to it
+    fn on_actor_lost(&mut self, _actor: usize) {}
+
+    /// the actor runs an executor of the kind, cost-aware policies estimate
+    /// its workloads with the costs of the kind
+    fn on_actor_kind(&mut self, _actor: usize, _kind: &str) {}
+
+    /// the system is configured with a cost profile, cost-aware policies
+    /// estimate workloads with it from now on
+    fn set_cost_model(&mut self, _cost_model: &CostModel<O>) {}
+
+    /// the cost learner has a new estimate of the opcode, cost-aware policies
+    /// merge it into their model
+    fn learn_cost(&mut self, _op: &O, _elapsed: Duration) {}
+}
+
+// estimated cost of a workload on the shapes of its operands, on an actor of
+// the executor kind if known; messages without opcode are free
+fn estimate_msg<U, O>(
+    cost_model: &CostModel<O>,
+    kind: Option<&str>,
+    msg: &RaptorMessage<U, O>,
+) -> usize
+where
+    U: 'static + TensorLike + Clone,
+    O: OpCodeLike,
+{
+    let estimate = |op: &O, operands: &[TensorMeta]| match kind {
+        Some(kind) => cost_model.estimate_with_on(kind, op, operands),
+        None => cost_model.estimate_with(op, operands),
+    };
+    match msg {
+        RaptorMessage::PayloadMSG(payload) => estimate(payload.op(), &payload.operand_metas()),
+        // simulated workloads name their opcode, it is looked up by name in
+        // the model and the tensor is its operand
+        RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(tensor)) => {
+            match tensor.op_name().and_then(|name| O::from_name(&name)) {
+                Some(op) => {
+                    let operands: Vec<_> = tensor.meta().into_iter().collect();
+                    estimate(&op, &operands)
+                }
+                None => 0,
+            }
+        }
+        _ => 0,
+    }
+}
+
+/// first come, first served on the first available actor
+#[derive(Clone, Debug, Default)]
+pub struct Fifo {}
+
+impl Fifo {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<U, O> SchedulingPolicy<U, O> for Fifo
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    fn select_actor(
+        &mut self,
+        _msg: &RaptorMessage<U, O>,
+        availables: &[usize],
+        _lives: &[usize],
+    ) -> Option<usize> {
+        availables.first().copied()
+    }
+}
+
+/// delayed workloads with the lowest estimated cost are dispatched first, ties
+/// are broken by arrival order
+#[derive(Clone, Debug)]
+pub struct ShortestJobFirst<O = MockOpCode>
+where
+    O: OpCodeLike,
+{
+    cost_model: CostModel<O>,
+}
+
+impl Default for ShortestJobFirst {
+    fn default() -> Self {
+        Self::new(CostModel::default())
+    }
+}
+
+impl<O> ShortestJobFirst<O>
+where
+    O: OpCodeLike,
+{
+    pub fn new(cost_model: CostModel<O>) -> Self {
+        Self { cost_model }
+    }
+
+    pub fn cost_model(&self) -> &CostModel<O> {
+        &self.cost_model
+    }
+}
+
+impl<U, O> SchedulingPolicy<U, O> for ShortestJobFirst<O>
+where
+    U: 'static + TensorLike + Clone,
+    O: OpCodeLike + Send + Sync + Debug,
+{
+    fn select_workload(&mut self, delayed: &[RaptorMessage<U, O>]) -> Option<usize> {
+        delayed
+            .iter()
+            .enumerate()
+            .min_by_key(|(idx, msg)| (estimate_msg(&self.cost_model, None, msg), *idx))
+            .map(|(idx, _)| idx)
+    }
+
+    fn set_cost_model(&mut self, cost_model: &CostModel<O>) {
+        self.cost_model = cost_model.clone();
+    }
+
+    fn learn_cost(&mut self, op: &O, elapsed: Duration) {
+        self.cost_model.update_learned(op.clone(), elapsed);
+    }
+
+    fn select_actor(
+        &mut self,
+        _msg: &RaptorMessage<U, O>,
+        availables: &[usize],
+        _lives: &[usize],
+    ) -> Option<usize> {
+        availables.first().copied()
+    }
+}
+
+/// workloads go to the live actor that finishes them the earliest: the lowest
+/// estimated cost assigned to it plus the cost of the workload on its executor
+/// kind; busy actors queue up to `max_queued` workloads in their mailbox
+#[derive(Clone, Debug)]
+pub struct LeastLoaded<O = MockOpCode>
+where
+    O: OpCodeLike,
+{
+    cost_model: CostModel<O>,
+    max_queued: usize,
+    // estimated costs of the workloads assigned to each actor, in delivery order
+    assigned: HashMap<usize, VecDeque<usize>>,
+    // executor kind of each actor, if known
+    kinds: HashMap<usize, String>,
+}
+
+impl Default for LeastLoaded {
+    fn default() -> Self {
+        Self::new(CostModel::default())
+    }
+}
+
+impl<O> LeastLoaded<O>
+where
+    O: OpCodeLike,
+{
+    pub fn new(cost_model: CostModel<O>) -> Self {
+        Self {
+            cost_model,
+            max_queued: 4,
+            assigned: HashMap::new(),
+            kinds: HashMap::new(),
+        }
+    }
+
+    pub fn cost_model(&self) -> &CostModel<O> {
+        &self.cost_model
+    }
+
+    pub fn set_max_queued(&mut self, max_queued: usize) {
+        self.max_queued = max_queued.max(1);
+    }
+
+    /// estimated cost currently assigned to the actor
+    pub fn load(&self, actor: usize) -> usize {
+        self.assigned
+            .get(&actor)
+            .map(|costs| costs.iter().sum())
+            .unwrap_or(0)
+    }
+
+    // estimated cost of the workload on the executor kind of the actor
+    fn estimate_on<U>(&self, actor: usize, msg: &RaptorMessage<U, O>) -> usize
+    where
+        U: 'static + TensorLike + Clone,
+    {
+        let kind = self.kinds.get(&actor).map(|x| x.as_str());
+        estimate_msg(&self.cost_model, kind, msg)
+    }
+
+    fn queued(&self, actor: usize) -> usize {
+        self.assigned
+            .get(&actor)
+            .map(|costs| costs.len())
+            .unwrap_or(0)
+    }
+}
+
+impl<U, O> SchedulingPolicy<U, O> for LeastLoaded<O>
+where
+    U: 'static + TensorLike + Clone,
+    O: OpCodeLike + Send + Sync + Debug,
+{
+    fn select_actor(
+        &mut self,
+        msg: &RaptorMessage<U, O>,
+        availables: &[usize],
+        lives: &[usize],
+    ) -> Option<usize> {
+        // idle actors win the ties, then the lowest id
+        lives
+            .iter()
+            .filter(|actor| self.queued(**actor) < self.max_queued)
+            .min_by_key(|actor| {
+                let finish = self.load(**actor) + self.estimate_on(**actor, msg);
+                (finish, !availables.contains(actor), **actor)
+            })
+            .copied()
+    }
+
+    fn on_assigned(&mut self, actor: usize, msg: &RaptorMessage<U, O>) {
+        let cost = self.estimate_on(actor, msg);
+        self.assigned.entry(actor).or_default().push_back(cost);
+    }
+
+    fn on_completed(&mut self, actor: usize) {
+        if let Some(costs) = self.assigned.get_mut(&actor) {
+            costs.pop_front();
+        }
+    }
+
+    fn on_undelivered(&mut self, actor: usize, msg: &RaptorMessage<U, O>) {
+        let cost = self.estimate_on(actor, msg);
+        if let Some(costs) = self.assigned.get_mut(&actor) {
+            // the newest entry of the cost, costs learned since its assignment
+            // may leave none of it
+            match costs.iter().rposition(|x| *x == cost) {
+                Some(idx) => costs.remove(idx),
+                None => costs.pop_back(),
+            };
+        }
+    }
+
+    fn set_cost_model(&mut self, cost_model: &CostModel<O>) {
+        self.cost_model = cost_model.clone();
+    }
+
+    fn learn_cost(&mut self, op: &O, elapsed: Duration) {
+        self.cost_model.update_learned(op.clone(), elapsed);
+    }
+
+    fn on_actor_lost(&mut self, actor: usize) {
+        self.assigned.remove(&actor);
+        self.kinds.remove(&actor);
+    }
+
+    fn on_actor_kind(&mut self, actor: usize, kind: &str) {
+        self.kinds.insert(actor, kind.to_string());
+    }
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost_function::Roofline;
+    use crate::cost_model::{CostFallback, GraphOp};
+    use crate::messages::PayloadMessage;
+    use crate::tensor_types::{CpuTensor, DType, MockTensor, TensorMeta};
+    use std::sync::{Arc, RwLock};
+    use tokio::sync::oneshot;
+
+    fn mock_msg(op: MockOpCode) -> RaptorMessage<MockTensor, MockOpCode> {
+        RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(MockTensor::new(op)))
+    }
+
+    fn matmul_msg(n: usize) -> RaptorMessage<CpuTensor, MockOpCode> {
+        let (respond_to, _) = oneshot::channel();
+        let operand = || Arc::new(RwLock::new(CpuTensor::zeros(vec![n, n], DType::F32)));
+        RaptorMessage::PayloadMSG(PayloadMessage::ComputeFunctorMsg {
+            op: MockOpCode::MatmulOp,
+            lhs: operand(),
+            rhs: operand(),
+            respond_to,
+        })
+    }
+
+    #[test]
+    fn fifo_keeps_arrival_order_test() {
+        let mut policy = Fifo::new();
+        let delayed = vec![mock_msg(MockOpCode::ConvOp), mock_msg(MockOpCode::AddOp)];
+        assert_eq!(policy.select_workload(&delayed), Some(0));
+        assert_eq!(
+            policy.select_actor(&delayed[0], &[2, 1], &[0, 1, 2]),
+            Some(2)
+        );
+        assert_eq!(policy.select_actor(&delayed[0], &[], &[0, 1, 2]), None);
+    }
+
+    #[test]
+    fn shortest_job_first_picks_cheapest_workload_test() {
+        let mut policy = ShortestJobFirst::new(CostModel::new());
+        let delayed = vec![
+            mock_msg(MockOpCode::ConvOp),
+            mock_msg(MockOpCode::AddOp),
+            mock_msg(MockOpCode::ExpOp),
+            mock_msg(MockOpCode::AddOp),
+        ];
+        assert_eq!(policy.select_workload(&delayed), Some(1));
+        assert_eq!(policy.select_workload(&delayed[2..]), Some(1));
+        assert_eq!(policy.select_workload(&delayed[..0]), None);
+    }
+
+    #[test]
+    fn least_loaded_balances_estimated_cost_test() {
+        let mut policy = LeastLoaded::new(CostModel::new());
+        let conv = mock_msg(MockOpCode::ConvOp);
+        let add = mock_msg(MockOpCode::AddOp);
+
+        // the idle actor is preferred on equal load
+        assert_eq!(policy.select_actor(&conv, &[1], &[0, 1]), Some(1));
+        policy.on_assigned(1, &conv);
+        assert_eq!(policy.select_actor(&add, &[], &[0, 1]), Some(0));
+        policy.on_assigned(0, &add);
+        // both are busy, actor #0 has less work queued
+        assert_eq!(policy.select_actor(&add, &[], &[0, 1]), Some(0));
+        policy.on_assigned(0, &add);
+        assert_eq!(policy.load(0), 22);
+        assert_eq!(policy.load(1), 107);
+
+        SchedulingPolicy::<MockTensor, MockOpCode>::on_completed(&mut policy, 1);
+        assert_eq!(policy.load(1), 0);
+        SchedulingPolicy::<MockTensor, MockOpCode>::on_actor_lost(&mut policy, 0);
+        assert_eq!(policy.load(0), 0);
+    }
+
+    #[test]
+    fn least_loaded_estimates_on_executor_kinds_test() {
+        let mut model = CostModel::new();
+        model.update_kind("gpu", MockOpCode::ConvOp, 5);
+        let mut policy = LeastLoaded::new(model);
+        SchedulingPolicy::<MockTensor, MockOpCode>::on_actor_kind(&mut policy, 0, "cpu");
+        SchedulingPolicy::<MockTensor, MockOpCode>::on_actor_kind(&mut policy, 1, "gpu");
+        let conv = mock_msg(MockOpCode::ConvOp);
+        let add = mock_msg(MockOpCode::AddOp);
+
+        // the gpu finishes convs the earliest, the cpu is left the adds
+        assert_eq!(policy.select_actor(&conv, &[0, 1], &[0, 1]), Some(1));
+        policy.on_assigned(1, &conv);
+        assert_eq!(policy.load(1), 5);
+        assert_eq!(policy.select_actor(&conv, &[0], &[0, 1]), Some(1));
+        assert_eq!(policy.select_actor(&add, &[0], &[0, 1]), Some(0));
+        policy.on_assigned(0, &add);
+        assert_eq!(policy.load(0), 11);
+        // the shared cost is used for kinds with no cost of their own
+        SchedulingPolicy::<MockTensor, MockOpCode>::on_actor_lost(&mut policy, 1);
+        policy.on_assigned(1, &conv);
+        assert_eq!(policy.load(1), 107);
+    }
+
+    #[test]
+    fn least_loaded_forgets_undelivered_workloads_test() {
+        let mut policy = LeastLoaded::new(CostModel::new());
+        let conv = mock_msg(MockOpCode::ConvOp);
+        let add = mock_msg(MockOpCode::AddOp);
+        policy.on_assigned(0, &conv);
+        policy.on_assigned(0, &add);
+        policy.on_assigned(0, &conv);
+        // the add is displaced from the mailbox, the running conv is kept
+        policy.on_undelivered(0, &add);
+        assert_eq!(policy.load(0), 2 * 107);
+        policy.on_undelivered(0, &conv);
+        assert_eq!(policy.load(0), 107);
+        SchedulingPolicy::<MockTensor, MockOpCode>::on_completed(&mut policy, 0);
+        assert_eq!(policy.load(0), 0);
+    }
+
+    #[test]
+    fn least_loaded_bounds_queued_workloads_test() {
+        let mut policy = LeastLoaded::new(CostModel::new());
+        policy.set_max_queued(1);
+        let add = mock_msg(MockOpCode::AddOp);
+        assert_eq!(policy.select_actor(&add, &[0], &[0]), Some(0));
+        policy.on_assigned(0, &add);
+        assert_eq!(policy.select_actor(&add, &[], &[0]), None);
+    }
+
+    #[test]
+    fn policies_take_configured_cost_model_test() {
+        let mut model = CostModel::new();
+        model.update_model(MockOpCode::ConvOp, 1);
+        let delayed = vec![mock_msg(MockOpCode::AddOp), mock_msg(MockOpCode::ConvOp)];
+
+        let mut policy = ShortestJobFirst::new(CostModel::new());
+        assert_eq!(policy.select_workload(&delayed), Some(0));
+        SchedulingPolicy::<MockTensor, MockOpCode>::set_cost_model(&mut policy, &model);
+        assert_eq!(policy.cost_model(), &model);
+        assert_eq!(policy.select_workload(&delayed), Some(1));
+
+        let mut policy = LeastLoaded::new(CostModel::new());
+        SchedulingPolicy::<MockTensor, MockOpCode>::set_cost_model(&mut policy, &model);
+        policy.on_assigned(0, &delayed[1]);
+        assert_eq!(policy.load(0), 1);
+    }
+
+    #[test]
+    fn policies_merge_learned_costs_test() {
+        let mut model = CostModel::new();
+        model.set_unit(Duration::from_micros(10));
+        model.set_fallback(CostFallback::Default(1000));
+        model.set_function(Arc::new(Roofline::new(1e9, 1e9)));
+        let delayed = vec![mock_msg(MockOpCode::AddOp), mock_msg(MockOpCode::ConvOp)];
+
+        let mut policy = ShortestJobFirst::new(model.clone());
+        SchedulingPolicy::<MockTensor, MockOpCode>::learn_cost(
+            &mut policy,
+            &MockOpCode::ConvOp,
+            Duration::from_micros(50),
+        );
+        assert_eq!(policy.select_workload(&delayed), Some(1));
+        // the rest of the model is kept
+        let merged = policy.cost_model();
+        assert_eq!(merged.estimate_op(&MockOpCode::ConvOp), 5);
+        assert_eq!(merged.estimate_op(&MockOpCode::AddOp), 11);
+        assert_eq!(merged.unit(), model.unit());
+        assert_eq!(merged.fallback(), model.fallback());
+        assert!(merged.function().is_some());
+
+        let mut policy = LeastLoaded::new(model);
+        SchedulingPolicy::<MockTensor, MockOpCode>::learn_cost(
+            &mut policy,
+            &MockOpCode::ConvOp,
+            Duration::from_micros(50),
+        );
+        policy.on_assigned(0, &delayed[1]);
+        assert_eq!(policy.load(0), 5);
+    }
+
+    #[test]
+    fn policies_estimate_operand_shapes_test() {
+        let delayed = vec![matmul_msg(64), matmul_msg(4)];
+        // the table costs every matmul the same
+        let mut policy = ShortestJobFirst::new(CostModel::new());
+        assert_eq!(policy.select_workload(&delayed), Some(0));
+
+        let mut model = CostModel::new();
+        model.set_function(Arc::new(Roofline::new(1e9, 1e9)));
+        let mut policy = ShortestJobFirst::new(model.clone());
+        assert_eq!(policy.select_workload(&delayed), Some(1));
+
+        let mut policy = LeastLoaded::new(model);
+        policy.on_assigned(0, &delayed[0]);
+        policy.on_assigned(1, &delayed[1]);
+        assert_eq!(policy.load(0), 525);
+        assert_eq!(policy.load(1), 1);
+    }
+
+    fn graph_msg(op: GraphOp) -> RaptorMessage<CpuTensor, GraphOp> {
+        let (respond_to, _) = oneshot::channel();
+        let operand = || Arc::new(RwLock::new(CpuTensor::zeros(vec![4], DType::F32)));
+        RaptorMessage::PayloadMSG(PayloadMessage::ComputeFunctorMsg {
+            op,
+            lhs: operand(),
+            rhs: operand(),
+            respond_to,
+        })
+    }
+
+    #[test]
+    fn policies_schedule_user_opcodes_test() {
+        let model = CostModel::set_model(HashMap::from([(GraphOp::Gemm, 30), (GraphOp::Relu, 3)]));
+        let delayed = vec![graph_msg(GraphOp::Gemm), graph_msg(GraphOp::Relu)];
+        let mut policy = ShortestJobFirst::new(model.clone());
+        assert_eq!(policy.select_workload(&delayed), Some(1));
+
+        let mut policy = LeastLoaded::new(model);
+        policy.on_assigned(0, &delayed[0]);
+        assert_eq!(policy.select_actor(&delayed[1], &[], &[0, 1]), Some(1));
+        policy.on_assigned(1, &delayed[1]);
+        assert_eq!(policy.load(0), 30);
+        assert_eq!(policy.load(1), 3);
+        // tensors that name no opcode are data only, they are free
+        let mock = RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(CpuTensor::default()));
+        assert_eq!(policy.select_actor(&mock, &[], &[0, 1]), Some(1));
+    }
+
+    // a simulated workload of a user tensor type, on a vector of `len`
+    #[derive(Clone, Debug)]
+    struct SimTensor {
+        op: MockOpCode,
+        len: usize,
+    }
+
+    impl TensorLike for SimTensor {
+        fn meta(&self) -> Option<TensorMeta> {
+            Some(TensorMeta::new(vec![self.len], DType::F32))
+        }
+
+        fn op_name(&self) -> Option<String> {
+            Some(self.op.name())
+        }
+    }
+
+    fn sim_msg(op: MockOpCode, len: usize) -> RaptorMessage<SimTensor, MockOpCode> {
+        RaptorMessage::LoadfreeMSG(LoadfreeMessage::MockTensorMsg(SimTensor { op, len }))
+    }
+
+    #[test]
+    fn policies_estimate_simulated_workloads_of_any_tensor_test() {
+        let delayed = vec![
+            sim_msg(MockOpCode::ExpOp, 1000),
+            sim_msg(MockOpCode::SinOp, 1000),
+        ];
+        let mut policy = ShortestJobFirst::new(CostModel::new());
+        assert_eq!(policy.select_workload(&delayed), Some(1));
+
+        // by the shapes, in the unit of the table
+        let mut model = CostModel::new();
+        model.set_function(Arc::new(Roofline::new(1e9, 1e9)));
+        let delayed = [
+            sim_msg(MockOpCode::ExpOp, 1000),
+            sim_msg(MockOpCode::SinOp, 4000),
+        ];
+        let mut policy = LeastLoaded::new(model.clone());
+        policy.on_assigned(0, &delayed[0]);
+        policy.on_assigned(1, &delayed[1]);
+        assert_eq!(policy.load(0), 8);
+        assert_eq!(policy.load(1), 32);
+
+        model.set_unit(Duration::from_micros(4));
+        let mut policy = LeastLoaded::new(model);
+        policy.on_assigned(1, &delayed[1]);
+        assert_eq!(policy.load(1), 8);
+    }
+}
@@ -0,0 +1,582 @@
+// LICENSE PLACEHOLDER
+use std::fmt::Debug;
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::cost_model::{CostModel, MockOpCode, OpCodeLike};
+use crate::errors::RaptorsError;
+use crate::executor_types::ExecutorLike;
+use crate::graph::{self, TaskGraph, TensorId};
+use crate::system::ActorSystemHandle;
+use crate::tensor_types::{MockTensor, TensorLike};
+
+/// Definition: StaticTask is a node of a static task graph, it computes `op`
+/// once every task listed in `deps` is done; deps refer to the index of the
+/// task in the graph.
+///
+/// When executed the task reads the outputs of its deps in order, or the
+/// input of the run if it has none: one operand makes it a unary op, two make
+/// it a binary one. A dep may be listed twice.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaticTask<O = MockOpCode> {
+    op: O,
+    deps: Vec<usize>,
+}
+
+impl<O> StaticTask<O> {
+    pub fn new(op: O, deps: Vec<usize>) -> Self {
+        Self { op, deps }
+    }
+
+    pub fn op(&self) -> &O {
+        &self.op
+    }
+
+    pub fn deps(&self) -> &[usize] {
+        &self.deps
+    }
+}
+
+/// the actor a task is assigned to and its predicted start and finish time,
+/// times are in cost model units; actors are numbered 0..ranks in the
+/// schedule, see `StaticSchedule::execute` for the actors they run on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskSlot {
+    pub task: usize,
+    pub actor: usize,
+    pub start: usize,
+    pub finish: usize,
+}
+
+/// Definition: StaticSchedule is the actor assignment and order of a task
+/// graph computed ahead of time, slots are sorted by predicted start time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaticSchedule {
+    slots: Vec<TaskSlot>,
+    makespan: usize,
+}
+
+/// predicted makespan of a static schedule in cost model units, next to the
+/// actual one measured while the actor system executes it, and the output of
+/// every task in task order
+#[derive(Clone, Debug, PartialEq)]
+pub struct StaticRunReport<U = MockTensor> {
+    pub predicted_makespan: usize,
+    pub actual_makespan: Duration,
+    pub outputs: Vec<U>,
+}
+
+/// Definition: HeftScheduler is a critical-path list scheduler (HEFT) for
+/// static task graphs.
+///
+/// Tasks are prioritized by their upward rank, the length of the most costly
+/// path from the task to an exit task, then each one is assigned to the actor
+/// that finishes it the earliest, filling idle gaps left between tasks.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     // diamond: exp -> (sin, add) -> mul
+///     let tasks = vec![
+///         StaticTask::new(MockOpCode::ExpOp, vec![]),
+///         StaticTask::new(MockOpCode::SinOp, vec![0]),
+///         StaticTask::new(MockOpCode::AddOp, vec![0]),
+///         StaticTask::new(MockOpCode::MulOp, vec![1, 2]),
+///     ];
+///     let schedule = HeftScheduler::new(CostModel::new()).schedule(&tasks, 2).unwrap();
+///     assert_eq!(schedule.makespan(), 173 + 127 + 71);
+///
+///     let mut system = build_mock_system!("Raptors", 2).unwrap();
+///     let input = MockTensor::new(MockOpCode::ExpOp);
+///     let report = schedule.execute(&tasks, input, &mut system).await.unwrap();
+///     assert_eq!(report.predicted_makespan, schedule.makespan());
+///     system.shutdown().await.unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct HeftScheduler<O = MockOpCode>
+where
+    O: OpCodeLike,
+{
+    cost_model: CostModel<O>,
+    // cost of passing a result between two different actors
+    comm_cost: usize,
+}
+
+impl Default for HeftScheduler {
+    fn default() -> Self {
+        Self::new(CostModel::default())
+    }
+}
+
+impl<O> HeftScheduler<O>
+where
+    O: OpCodeLike,
+{
+    pub fn new(cost_model: CostModel<O>) -> Self {
+        Self {
+            cost_model,
+            comm_cost: 0,
+        }
+    }
+
+    pub fn set_comm_cost(&mut self, comm_cost: usize) {
+        self.comm_cost = comm_cost;
+    }
+
+    /// upward rank of every task
+    pub fn upward_ranks(&self, tasks: &[StaticTask<O>]) -> Result<Vec<usize>, RaptorsError> {
+        let order = topo_order(tasks)?;
+        let succs = successors(tasks);
+        let mut ranks = vec![0; tasks.len()];
+        for &task in order.iter().rev() {
+            let tail = succs[task]
+                .iter()
+                .map(|succ| self.comm_cost + ranks[*succ])
+                .max()
+                .unwrap_or(0);
+            ranks[task] = self.cost_model.estimate_op(&tasks[task].op) + tail;
+        }
+        Ok(ranks)
+    }
+
+    /// assign the tasks to `ranks` actors
+    pub fn schedule(
+        &self,
+        tasks: &[StaticTask<O>],
+        ranks: usize,
+    ) -> Result<StaticSchedule, RaptorsError> {
+        if ranks == 0 {
+            return Err(RaptorsError::scheduling("no actor to schedule tasks on"));
+        }
+        let order = topo_order(tasks)?;
+        let upward_ranks = self.upward_ranks(tasks)?;
+        // a task always ranks above its successors unless some costs are zero,
+        // the topological position keeps the order valid in that case
+        let mut topo_pos = vec![0; tasks.len()];
+        for (pos, task) in order.iter().enumerate() {
+            topo_pos[*task] = pos;
+        }
+        let mut priorities: Vec<usize> = (0..tasks.len()).collect();
+        priorities.sort_by_key(|task| (std::cmp::Reverse(upward_ranks[*task]), topo_pos[*task]));
+
+        let mut placed: Vec<Option<TaskSlot>> = vec![None; tasks.len()];
+        // busy intervals of each actor sorted by start time
+        let mut timelines: Vec<Vec<(usize, usize)>> = vec![vec![]; ranks];
+        for task in priorities {
+            let cost = self.cost_model.estimate_op(&tasks[task].op);
+            let mut best: Option<TaskSlot> = None;
+            for (actor, timeline) in timelines.iter().enumerate() {
+                let ready = tasks[task]
+                    .deps
+                    .iter()
+                    .filter_map(|dep| placed[*dep])
+                    .map(|dep| match dep.actor == actor {
+                        true => dep.finish,
+                        false => dep.finish + self.comm_cost,
+                    })
+                    .max()
+                    .unwrap_or(0);
+                let start = earliest_start(timeline, ready, cost);
+                if best.is_none_or(|slot| start + cost < slot.finish) {
+                    best = Some(TaskSlot {
+                        task,
+                        actor,
+                        start,
+                        finish: start + cost,
+                    });
+                }
+            }
+            let slot = best.expect("at least one actor is there");
+            let timeline = &mut timelines[slot.actor];
+            let pos = timeline.partition_point(|(start, _)| *start <= slot.start);
+            timeline.insert(pos, (slot.start, slot.finish));
+            placed[task] = Some(slot);
+        }
+
+        let mut slots: Vec<TaskSlot> = placed.into_iter().flatten().collect();
+        slots.sort_by_key(|slot| (slot.start, slot.finish, slot.actor));
+        let makespan = slots.iter().map(|slot| slot.finish).max().unwrap_or(0);
+        Ok(StaticSchedule { slots, makespan })
+    }
+}
+
+impl StaticSchedule {
+    pub fn slots(&self) -> &[TaskSlot] {
+        &self.slots
+    }
+
+    pub fn makespan(&self) -> usize {
+        self.makespan
+    }
+
+    pub fn slot_of(&self, task: usize) -> Option<&TaskSlot> {
+        self.slots.iter().find(|slot| slot.task == task)
+    }
+
+    /// tasks assigned to the actor in execution order
+    pub fn actor_order(&self, actor: usize) -> Vec<usize> {
+        self.slots
+            .iter()
+            .filter(|slot| slot.actor == actor)
+            .map(|slot| slot.task)
+            .collect()
+    }
+
+    /// run the tasks on the actors of the system as scheduled and wait until
+    /// all of them are done, the first failure is returned if any; actor #i
+    /// of the schedule is the live actor of the i-th lowest id, ids need not
+    /// be contiguous after halts or named spawns
+    pub async fn execute<T, U, O>(
+        &self,
+        tasks: &[StaticTask<O>],
+        input: U,
+        system: &mut ActorSystemHandle<T, U, O>,
+    ) -> Result<StaticRunReport<U>, RaptorsError>
+    where
+        T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync,
+        U: 'static + TensorLike + Clone + Default + Send + Sync + Debug,
+        O: 'static + OpCodeLike + Debug + Send + Sync,
+    {
+        let (graph, outputs) = self.task_graph(tasks, input, &system.live_ids())?;
+        let now = Instant::now();
+        graph.submit(system).await?.await?;
+        let outputs = outputs
+            .iter()
+            .map(|id| {
+                let tensor = graph.tensor(*id).expect("output of the graph");
+                let output = tensor.read().unwrap_or_else(PoisonError::into_inner);
+                output.clone()
+            })
+            .collect();
+        Ok(StaticRunReport {
+            predicted_makespan: self.makespan,
+            actual_makespan: now.elapsed(),
+            outputs,
+        })
+    }
+
+    // graph of the tasks with one node per slot added in slot order, pinned to
+    // the live actor of the slot; returns the output of every task
+    fn task_graph<U, O>(
+        &self,
+        tasks: &[StaticTask<O>],
+        input: U,
+        live_ids: &[usize],
+    ) -> Result<(TaskGraph<U, O>, Vec<TensorId>), RaptorsError>
+    where
+        U: 'static + TensorLike + Clone + Default + Send + Sync + Debug,
+        O: 'static + OpCodeLike + Debug + Send + Sync,
+    {
+        if self.slots.len() != tasks.len() {
+            return Err(RaptorsError::scheduling(format!(
+                "schedule covers {} tasks, got {}",
+                self.slots.len(),
+                tasks.len()
+            )));
+        }
+        if let Some(slot) = self.slots.iter().find(|slot| slot.actor >= live_ids.len()) {
+            return Err(RaptorsError::scheduling(format!(
+                "task #{} is scheduled on actor #{} out of {} live actors",
+                slot.task,
+                slot.actor,
+                live_ids.len()
+            )));
+        }
+        if let Some(idx) = tasks.iter().position(|task| task.deps.len() > 2) {
+            return Err(RaptorsError::scheduling(format!(
+                "task #{} has {} operands, ops take one or two",
+                idx,
+                tasks[idx].deps.len()
+            )));
+        }
+        topo_order(tasks)?;
+
+        let mut graph = TaskGraph::new();
+        let input = graph.add_tensor(input);
+        let outputs: Vec<TensorId> = tasks
+            .iter()
+            .map(|_| graph.add_tensor(U::default()))
+            .collect();
+        for slot in self.slots.iter() {
+            let task = &tasks[slot.task];
+            let out = outputs[slot.task];
+            let node = match task.deps[..] {
+                [] => graph.add_unary(task.op.clone(), input, out)?,
+                [inp] => graph.add_unary(task.op.clone(), outputs[inp], out)?,
+                [lhs, rhs] => graph.add_binary(task.op.clone(), outputs[lhs], outputs[rhs], out)?,
+                _ => unreachable!("tasks have at most two operands"),
+            };
+            let actor = live_ids[slot.actor];
+            info!(
+                "::static-scheduler::pin task #{} to actor #{}",
+                slot.task, actor
+            );
+            graph.pin(node, actor)?;
+        }
+        Ok((graph, outputs))
+    }
+}
+
+fn successors<O>(tasks: &[StaticTask<O>]) -> Vec<Vec<usize>> {
+    let deps: Vec<Vec<usize>> = tasks.iter().map(|task| task.deps.clone()).collect();
+    graph::successors(&deps)
+}
+
+// fails on unknown deps and cycles
+fn topo_order<O>(tasks: &[StaticTask<O>]) -> Result<Vec<usize>, RaptorsError> {
+    for (idx, task) in tasks.iter().enumerate() {
+        if let Some(dep) = task.deps.iter().find(|dep| **dep >= tasks.len()) {
+            return Err(RaptorsError::scheduling(format!(
+                "task #{} depends on unknown task #{}",
+                idx, dep
+            )));
+        }
+    }
+    let deps: Vec<Vec<usize>> = tasks.iter().map(|task| task.deps.clone()).collect();
+    graph::topo_order(&deps)
+}
+
+// earliest time the actor is idle for `cost` units from `ready` on
+fn earliest_start(timeline: &[(usize, usize)], ready: usize, cost: usize) -> usize {
+    let mut start = ready;
+    for (busy_start, busy_finish) in timeline {
+        if start + cost <= *busy_start {
+            break;
+        }
+        start = start.max(*busy_finish);
+    }
+    start
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost_model::GraphOp;
+    use crate::prelude::*;
+    use std::collections::HashMap;
+
+    fn diamond() -> Vec<StaticTask> {
+        vec![
+            StaticTask::new(MockOpCode::ExpOp, vec![]),
+            StaticTask::new(MockOpCode::SinOp, vec![0]),
+            StaticTask::new(MockOpCode::AddOp, vec![0]),
+            StaticTask::new(MockOpCode::MulOp, vec![1, 2]),
+        ]
+    }
+
+    #[test]
+    fn upward_ranks_follow_critical_path_test() {
+        let heft = HeftScheduler::new(CostModel::new());
+        assert_eq!(
+            heft.upward_ranks(&diamond()).unwrap(),
+            vec![173 + 127 + 71, 127 + 71, 11 + 71, 71]
+        );
+    }
+
+    #[test]
+    fn schedule_diamond_test() {
+        let heft = HeftScheduler::new(CostModel::new());
+        let schedule = heft.schedule(&diamond(), 2).unwrap();
+        // the critical path stays on actor #0, the short branch runs aside
+        assert_eq!(schedule.actor_order(0), vec![0, 1, 3]);
+        assert_eq!(schedule.actor_order(1), vec![2]);
+        assert_eq!(
+            schedule.slot_of(2),
+            Some(&TaskSlot {
+                task: 2,
+                actor: 1,
+                start: 173,
+                finish: 184
+            })
+        );
+        assert_eq!(schedule.makespan(), 371);
+    }
+
+    #[test]
+    fn schedule_with_comm_cost_test() {
+        let mut heft = HeftScheduler::new(CostModel::new());
+        heft.set_comm_cost(200);
+        // moving the short branch away costs more than running it in a row
+        let schedule = heft.schedule(&diamond(), 2).unwrap();
+        assert_eq!(schedule.actor_order(0), vec![0, 1, 2, 3]);
+        assert_eq!(schedule.makespan(), 173 + 127 + 11 + 71);
+    }
+
+    #[test]
+    fn schedule_fills_idle_gaps_test() {
+        let mut heft = HeftScheduler::new(CostModel::new());
+        heft.set_comm_cost(100);
+        let tasks = vec![
+            StaticTask::new(MockOpCode::ConvOp, vec![]),
+            StaticTask::new(MockOpCode::ConvOp, vec![]),
+            StaticTask::new(MockOpCode::ExpOp, vec![0, 1]),
+            StaticTask::new(MockOpCode::AddOp, vec![]),
+        ];
+        let schedule = heft.schedule(&tasks, 2).unwrap();
+        // actor #0 idles while the result of conv #1 is passed over, the add
+        // is slotted into that gap
+        assert_eq!(schedule.slot_of(2).unwrap().start, 107 + 100);
+        assert_eq!(
+            schedule.slot_of(3),
+            Some(&TaskSlot {
+                task: 3,
+                actor: 0,
+                start: 107,
+                finish: 118
+            })
+        );
+        assert_eq!(schedule.makespan(), 107 + 100 + 173);
+    }
+
+    #[test]
+    fn schedule_rejects_bad_graphs_test() {
+        let heft = HeftScheduler::new(CostModel::new());
+        let cyclic = vec![
+            StaticTask::new(MockOpCode::AddOp, vec![1]),
+            StaticTask::new(MockOpCode::AddOp, vec![0]),
+        ];
+        assert_eq!(
+            heft.schedule(&cyclic, 2),
+            Err(RaptorsError::scheduling("task graph has a cycle"))
+        );
+        let dangling = vec![StaticTask::new(MockOpCode::AddOp, vec![3])];
+        assert_eq!(
+            heft.schedule(&dangling, 2),
+            Err(RaptorsError::scheduling(
+                "task #0 depends on unknown task #3"
+            ))
+        );
+        assert!(heft.schedule(&diamond(), 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn execute_schedule_on_system_test() {
+        let tasks = diamond();
+        let schedule = HeftScheduler::new(CostModel::new())
+            .schedule(&tasks, 2)
+            .unwrap();
+        let mut system = build_mock_system!("Raptors", 2).unwrap();
+        let input = MockTensor::new(MockOpCode::ExpOp);
+        let report = schedule.execute(&tasks, input, &mut system).await.unwrap();
+        assert_eq!(report.predicted_makespan, 371);
+        // three mock computations in a row on the critical path
+        assert!(report.actual_makespan >= Duration::from_millis(3000));
+        assert_eq!(report.outputs.len(), 4);
+
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 4);
+    }
+
+    #[tokio::test]
+    async fn execute_schedule_out_of_ranks_test() {
+        let tasks = diamond();
+        let schedule = HeftScheduler::new(CostModel::new())
+            .schedule(&tasks, 3)
+            .unwrap();
+        let mut system = build_mock_system!("Raptors", 1).unwrap();
+        let input = MockTensor::default();
+        assert!(schedule.execute(&tasks, input, &mut system).await.is_err());
+        system.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn execute_schedule_on_sparse_actor_ids_test() {
+        let tasks = diamond();
+        let schedule = HeftScheduler::new(CostModel::new())
+            .schedule(&tasks, 2)
+            .unwrap();
+        // any tensor type does
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(3);
+        let mut system = SystemBuilder::new()
+            .build_with_config::<MockExecutor<CpuTensor>, CpuTensor, MockOpCode>(sys_config)
+            .unwrap();
+        system.issue_order(build_msg!("halt", 0)).await;
+        // orders are processed asynchronously by the event loop
+        for _ in 0..100 {
+            if system.live_ids() == vec![1, 2] {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(system.live_ids(), vec![1, 2]);
+
+        let report = tokio::time::timeout(
+            Duration::from_secs(20),
+            schedule.execute(&tasks, CpuTensor::default(), &mut system),
+        )
+        .await
+        .expect("a task is pinned to a halted actor")
+        .unwrap();
+        assert_eq!(report.predicted_makespan, 371);
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 4);
+    }
+
+    #[tokio::test]
+    async fn execute_schedule_computes_cpu_tensors_test() {
+        // diamond with binary ops: exp -> (sin, add to itself) -> mul
+        let tasks = vec![
+            StaticTask::new(MockOpCode::ExpOp, vec![]),
+            StaticTask::new(MockOpCode::SinOp, vec![0]),
+            StaticTask::new(MockOpCode::AddOp, vec![0, 0]),
+            StaticTask::new(MockOpCode::MulOp, vec![1, 2]),
+        ];
+        let schedule = HeftScheduler::new(CostModel::new())
+            .schedule(&tasks, 2)
+            .unwrap();
+        let input = CpuTensor::from_f64(vec![3], vec![0.0, 0.5, 1.0]).unwrap();
+        let exec = CpuExecutor::new();
+        let exp = exec.unary(MockOpCode::ExpOp, &input).unwrap();
+        let sin = exec.unary(MockOpCode::SinOp, &exp).unwrap();
+        let add = exec.binary(MockOpCode::AddOp, &exp, &exp).unwrap();
+        let mul = exec.binary(MockOpCode::MulOp, &sin, &add).unwrap();
+
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(2);
+        let mut system = SystemBuilder::new()
+            .build_with_config::<CpuExecutor, CpuTensor, MockOpCode>(sys_config)
+            .unwrap();
+        let report = schedule.execute(&tasks, input, &mut system).await.unwrap();
+        assert_eq!(report.outputs, vec![exp, sin, add, mul]);
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 4);
+
+        // an op of three operands is rejected before anything is issued
+        let mut tasks = tasks;
+        tasks[3] = StaticTask::new(MockOpCode::MulOp, vec![0, 1, 2]);
+        let schedule = HeftScheduler::new(CostModel::new())
+            .schedule(&tasks, 1)
+            .unwrap();
+        assert_eq!(
+            schedule
+                .task_graph(&tasks, CpuTensor::default(), &[0])
+                .err(),
+            Some(RaptorsError::scheduling(
+                "task #3 has 3 operands, ops take one or two"
+            ))
+        );
+    }
+
+    #[test]
+    fn schedule_user_opcodes_test() {
+        let model = CostModel::set_model(HashMap::from([(GraphOp::Gemm, 30), (GraphOp::Relu, 3)]));
+        // two gemm layers in parallel, activated together
+        let tasks = vec![
+            StaticTask::new(GraphOp::Gemm, vec![]),
+            StaticTask::new(GraphOp::Gemm, vec![]),
+            StaticTask::new(GraphOp::Relu, vec![0, 1]),
+        ];
+        let schedule = HeftScheduler::new(model).schedule(&tasks, 2).unwrap();
+        assert_eq!(schedule.actor_order(0), vec![0, 2]);
+        assert_eq!(schedule.actor_order(1), vec![1]);
+        assert_eq!(schedule.makespan(), 33);
+        assert_eq!(tasks[2].op(), &GraphOp::Relu);
+    }
+}
@@ -0,0 +1,64 @@
+// LICENSE PLACEHOLDER
+use std::fmt::Debug;
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::info;
+
+use crate::cost_model::OpCodeLike;
+use crate::messages::{ActorCommand, LoadfreeMessage, RaptorMessage};
+use crate::tensor_types::TensorLike;
+
+/// Definition: SupervisorStrategy decides how the actor system reacts when one
+/// of its actors panics.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// let mut sys_config = SystemConfig::new("Raptors", "info");
+/// assert_eq!(sys_config.supervisor_strategy(), SupervisorStrategy::RestartOne);
+/// sys_config.set_supervisor_strategy(SupervisorStrategy::Escalate);
+/// assert_eq!(sys_config.supervisor_strategy(), SupervisorStrategy::Escalate);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SupervisorStrategy {
+    // respawn the failed actor with the same id and executor typeid, the msg it
+    // was processing is requeued
+    #[default]
+    RestartOne,
+    // as RestartOne, besides every other actor is replaced by a fresh one once
+    // it has finished the msgs queued to it
+    RestartAll,
+    // the system gives up, it stops and shutdown reports the failure
+    Escalate,
+    // halt the failed actor, the msg it was processing is dropped
+    Stop,
+}
+
+/// watch the task running an actor and tell the system when it ends, the
+/// returned handle resolves to true only if the actor exits cleanly
+pub(crate) fn watch_actor<T, O>(
+    id: usize,
+    generation: usize,
+    actor_task: JoinHandle<u32>,
+    notify_to: mpsc::Sender<RaptorMessage<T, O>>,
+) -> JoinHandle<bool>
+where
+    T: 'static + TensorLike + Clone + Send + Sync + Debug,
+    O: 'static + OpCodeLike + Send + Debug,
+{
+    tokio::spawn(async move {
+        let (cmd, exit_cleanly) = match actor_task.await {
+            Ok(_) => (ActorCommand::Stopped(id, generation), true),
+            Err(e) if e.is_panic() => (ActorCommand::Panicked(id, generation), false),
+            // the runtime cancelled it
+            Err(_) => (ActorCommand::Stopped(id, generation), false),
+        };
+        info!("::supervisor::actor #{} ends - {:?}", id, cmd);
+        // the system may have finished its event loop already
+        let _ = notify_to
+            .send(RaptorMessage::LoadfreeMSG(LoadfreeMessage::ActorMsg(cmd)))
+            .await;
+        exit_cleanly
+    })
+}
@@ -1,16 +1,38 @@
 // use log::{info};
+use std::any::Any;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
 
-use tokio::sync::mpsc;
-use tracing::{debug, info};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
 
+use crate::actor_ref::{send_order, wait_reply, ActorRef};
+use crate::actor_registry::ActorRegistry;
 use crate::actors::*;
+use crate::blocking_pool::BlockingPool;
 use crate::cost_model::OpCodeLike;
+use crate::dispatcher::{Dispatcher, Route};
+use crate::errors::RaptorsError;
+use crate::executor_registry::ExecutorRegistry;
 use crate::executor_types::ExecutorLike;
+use crate::mailbox::{self, Address, Envelope, MailboxProbe, OverflowPolicy};
 use crate::messages::*;
 use crate::prelude::*;
-use crate::tensor_types::*;
+use crate::scheduling::{Fifo, SchedulingPolicy};
+use crate::supervisor::{watch_actor, SupervisorStrategy};
+use crate::topics::Topics;
+use crate::transport::{self, RemoteSystem};
+use crate::wire::WireTensor;
 
 /// TODO(short-term) make dedicated mod and move it to there, maybe name it system_config.rs
 /// test SystemConfig creation and get
@@ -24,8 +46,24 @@ use crate::tensor_types::*;
 #[derive(Default, Debug)]
 pub struct SystemConfig {
     name: String,
+    #[allow(dead_code)]
     log_level: String,
     ranks: Option<usize>,
+    // WIP executor's typeid of the actors spawned at creation
+    executor_typeid: usize,
+    supervisor_strategy: SupervisorStrategy,
+    // restarts allowed per actor before it is stopped for good
+    max_restarts: Option<usize>,
+    // executor calls of the actors that may run at the same time
+    blocking_threads: Option<usize>,
+    // msgs each lane of an actor mailbox holds
+    mailbox_capacity: Option<usize>,
+    overflow_policy: OverflowPolicy,
+    // costs loaded from a profile, used by the scheduler and the workloads;
+    // a CostModel of the opcode type of the system
+    cost_model: Option<Arc<dyn Any + Send + Sync>>,
+    // learn the costs from the measured executor calls if set
+    cost_learning: Option<Estimator>,
 }
 
 impl SystemConfig {
@@ -35,15 +73,108 @@ impl SystemConfig {
             name: name.to_string(),
             ranks: Default::default(),
             log_level: log_level.to_owned(),
+            executor_typeid: Default::default(),
+            supervisor_strategy: Default::default(),
+            max_restarts: Default::default(),
+            blocking_threads: Default::default(),
+            mailbox_capacity: Default::default(),
+            overflow_policy: Default::default(),
+            cost_model: Default::default(),
+            cost_learning: Default::default(),
         }
     }
 
-    pub fn set_ranks(&mut self, ranks: usize) -> () {
+    pub fn set_ranks(&mut self, ranks: usize) {
         self.ranks = Some(ranks);
     }
 
     pub fn ranks(&self) -> usize {
-        self.ranks.unwrap_or_else(|| 0)
+        self.ranks.unwrap_or(0)
+    }
+
+    pub fn set_executor_typeid(&mut self, typeid: usize) {
+        self.executor_typeid = typeid;
+    }
+
+    pub fn executor_typeid(&self) -> usize {
+        self.executor_typeid
+    }
+
+    pub fn set_supervisor_strategy(&mut self, strategy: SupervisorStrategy) {
+        self.supervisor_strategy = strategy;
+    }
+
+    pub fn supervisor_strategy(&self) -> SupervisorStrategy {
+        self.supervisor_strategy
+    }
+
+    pub fn set_max_restarts(&mut self, max_restarts: usize) {
+        self.max_restarts = Some(max_restarts);
+    }
+
+    pub fn max_restarts(&self) -> usize {
+        self.max_restarts.unwrap_or(3)
+    }
+
+    pub fn set_blocking_threads(&mut self, blocking_threads: usize) {
+        self.blocking_threads = Some(blocking_threads.max(1));
+    }
+
+    /// size of the blocking pool shared by the actors, one per core by default
+    pub fn blocking_threads(&self) -> usize {
+        self.blocking_threads
+            .unwrap_or_else(crate::blocking_pool::default_size)
+    }
+
+    pub fn set_mailbox_capacity(&mut self, capacity: usize) {
+        self.mailbox_capacity = Some(capacity.max(1));
+    }
+
+    pub fn mailbox_capacity(&self) -> usize {
+        self.mailbox_capacity.unwrap_or(16)
+    }
+
+    pub fn set_overflow_policy(&mut self, overflow_policy: OverflowPolicy) {
+        self.overflow_policy = overflow_policy;
+    }
+
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+
+    /// load the cost profile at the path, see `CostModel::from_path`; the
+    /// opcodes are named after O, the opcode type of the system
+    pub fn set_cost_profile<O>(&mut self, path: impl AsRef<Path>) -> Result<(), RaptorsError>
+    where
+        O: 'static + OpCodeLike + Send + Sync,
+    {
+        self.set_cost_model(CostModel::<O>::from_path(path)?);
+        Ok(())
+    }
+
+    pub fn set_cost_model<O>(&mut self, cost_model: CostModel<O>)
+    where
+        O: 'static + OpCodeLike + Send + Sync,
+    {
+        self.cost_model = Some(Arc::new(cost_model));
+    }
+
+    /// the cost model if one is set for the opcode type O
+    pub fn cost_model<O>(&self) -> Option<&CostModel<O>>
+    where
+        O: 'static + OpCodeLike + Send + Sync,
+    {
+        self.cost_model.as_ref()?.downcast_ref()
+    }
+
+    /// actors time their executor calls and the scheduler estimates workloads
+    /// with the costs learned so far, see `CostLearner`
+    pub fn set_cost_learning(&mut self, estimator: Estimator) {
+        self.cost_learning = Some(estimator);
+    }
+
+    pub fn cost_learning(&self) -> Option<Estimator> {
+        self.cost_learning
     }
 
     pub fn name(&self) -> String {
@@ -62,10 +193,9 @@ impl SystemConfig {
 ///
 /// #[tokio::main]
 /// async fn main() {
-///     let system = build_mock_system!("mock system", 2);
+///     let system = build_mock_system!("mock system", 2).unwrap();
 ///     assert_eq!(system.name(), "mock system".to_string());
-///     // TODO-FIX#1, currently not spawn at creation due to async-sync
-///     // assert_eq!(system.ranks(), 2);
+///     assert_eq!(system.ranks(), 2);
 /// }
 /// ```
 ///
@@ -87,20 +217,73 @@ impl SystemBuilder {
     >(
         &mut self,
         config: SystemConfig,
-    ) -> ActorSystemHandle<T, U, O> {
+    ) -> Result<ActorSystemHandle<T, U, O>, RaptorsError> {
         self.cfg = Some(config);
-        let mut system = ActorSystemHandle::<T, U, O>::new(&self.config().name().to_owned());
-        // TODO-FIX#1 make issue_order sync func
-        // let cmd = build_loadfree_msg!("spawn", self.config().ranks());
-        // system.issue_order(cmd).await;
-        system
+        ActorSystemHandle::<T, U, O>::new_with_config(self.config())
+    }
+
+    pub fn build_with_policy<
+        T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync,
+        U: 'static + TensorLike + Clone + Send + Sync + Debug,
+        O: 'static + OpCodeLike + Debug + Send + Sync,
+    >(
+        &mut self,
+        config: SystemConfig,
+        policy: Box<dyn SchedulingPolicy<U, O>>,
+    ) -> Result<ActorSystemHandle<T, U, O>, RaptorsError> {
+        self.cfg = Some(config);
+        ActorSystemHandle::<T, U, O>::new_with_policy(self.config(), policy)
+    }
+
+    pub fn build_with_registry<
+        T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync,
+        U: 'static + TensorLike + Clone + Send + Sync + Debug,
+        O: 'static + OpCodeLike + Debug + Send + Sync,
+    >(
+        &mut self,
+        config: SystemConfig,
+        registry: ExecutorRegistry<T>,
+    ) -> Result<ActorSystemHandle<T, U, O>, RaptorsError> {
+        self.cfg = Some(config);
+        ActorSystemHandle::<T, U, O>::new_with_registry(
+            self.config(),
+            Box::new(Fifo::new()),
+            registry,
+        )
     }
 
     fn config(&self) -> &SystemConfig {
-        &self.cfg.as_ref().unwrap()
+        self.cfg.as_ref().unwrap()
     }
 }
 
+/// Definition: ShutdownSummary is returned once the actor system has been
+/// shut down gracefully, it reports what happened to the actors and workloads.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut system = build_mock_system!("Raptors").unwrap();
+///     let msg = build_loadfree_msg!("spawn", "mock", 2);
+///     system.issue_order(RaptorMessage::LoadfreeMSG(msg)).await;
+///     system.issue_order(build_msg!("add-op")).await;
+///     system.issue_order(build_msg!("exp-op")).await;
+///     let summary = system.shutdown().await.unwrap();
+///     assert_eq!(summary.actors_stopped, 2);
+///     assert_eq!(summary.tasks_completed, 2);
+///     assert_eq!(summary.tasks_dropped, 0);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShutdownSummary {
+    pub actors_stopped: usize,
+    pub actors_restarted: usize,
+    pub tasks_completed: usize,
+    pub tasks_dropped: usize,
+}
+
 #[derive(Debug)]
 pub struct ActorSystemHandle<T, U, O>
 where
@@ -110,26 +293,111 @@ where
 {
     name: String,
     system_cmd_sendbox: mpsc::Sender<RaptorMessage<U, O>>,
-    _markerT: PhantomData<T>,
-    _markerO: PhantomData<O>,
+    system_task: JoinHandle<Result<ShutdownSummary, RaptorsError>>,
+    live_ranks: Arc<AtomicUsize>,
+    mailboxes: MailboxProbes<U, O>,
+    names: ActorNames,
+    next_id: Arc<AtomicUsize>,
+    registry: Arc<ExecutorRegistry<T>>,
+    // how long asks wait for the result, forever if None
+    ask_timeout: Option<Duration>,
+    // accept loops of the sockets the system listens on
+    listeners: Vec<JoinHandle<()>>,
+    cost_learner: Option<CostLearner<O>>,
+    _marker_o: PhantomData<O>,
 }
 
+// ids of the named actors, shared with the handles
+type ActorNames = Arc<RwLock<HashMap<String, usize>>>;
+
+// address and a view on the mailbox of the actors by id, shared with the handles
+type MailboxProbes<U, O> =
+    Arc<RwLock<HashMap<usize, (Address, MailboxProbe<RaptorMessage<U, O>>)>>>;
+
 impl<T, U, O> ActorSystemHandle<T, U, O>
 where
     T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync,
     U: 'static + TensorLike + Clone + Send + Sync + Debug,
     O: 'static + OpCodeLike + Debug + Send + Sync,
 {
-    pub fn new(name: &str) -> Self {
+    pub fn new(name: &str) -> Result<Self, RaptorsError> {
+        Self::new_with_config(&SystemConfig::new(name, "info"))
+    }
+
+    /// create the system described by the config, the actors of the configured
+    /// ranks are spawned and registered as available before the event loop
+    /// starts, no need to await a spawn order; fails if they cannot be spawned
+    pub fn new_with_config(config: &SystemConfig) -> Result<Self, RaptorsError> {
+        Self::new_with_policy(config, Box::new(Fifo::new()))
+    }
+
+    /// as `new_with_config`, workloads are scheduled by the given policy
+    pub fn new_with_policy(
+        config: &SystemConfig,
+        policy: Box<dyn SchedulingPolicy<U, O>>,
+    ) -> Result<Self, RaptorsError> {
+        Self::new_with_registry(config, policy, ExecutorRegistry::new())
+    }
+
+    /// as `new_with_policy`, executors of the actors are built by the registry;
+    /// the cost profile of the config, if any, replaces the cost model of the
+    /// policy, it fails if the profile is not one of the opcode type O
+    pub fn new_with_registry(
+        config: &SystemConfig,
+        mut policy: Box<dyn SchedulingPolicy<U, O>>,
+        registry: ExecutorRegistry<T>,
+    ) -> Result<Self, RaptorsError> {
+        if let Some(cost_model) = config.cost_model::<O>() {
+            policy.set_cost_model(cost_model);
+        } else if config.cost_model.is_some() {
+            return Err(RaptorsError::config(format!(
+                "cost model of the config is not one of {}",
+                std::any::type_name::<O>()
+            )));
+        }
+        let name = config.name();
+        let registry = Arc::new(registry);
         let (sender, receiver) = mpsc::channel(100);
-        let mut system = ActorSystem::<T, U, O>::new(name, receiver, sender.clone());
-        tokio::spawn(async move { system.run().await });
-        Self {
-            name: name.to_string(),
-            system_cmd_sendbox: sender,
-            _markerT: PhantomData,
-            _markerO: PhantomData,
+        let mut system = ActorSystem::<T, U, O>::new(&name, receiver, sender.clone());
+        system.set_supervisor_strategy(config.supervisor_strategy(), config.max_restarts());
+        system.set_scheduling_policy(policy);
+        system.set_executor_registry(registry.clone());
+        system.set_blocking_pool(BlockingPool::new(config.blocking_threads()));
+        system.set_mailbox(config.mailbox_capacity(), config.overflow_policy());
+        let cost_learner = config.cost_learning().map(|estimator| {
+            // the policy merges the learned costs into its own model, the
+            // base only makes the model of the learner, e.g. to save a profile
+            let base = config
+                .cost_model::<O>()
+                .cloned()
+                .unwrap_or_else(|| CostModel::set_model(HashMap::new()));
+            CostLearner::new(estimator, base)
+        });
+        if let Some(learner) = cost_learner.clone() {
+            system.set_cost_learner(learner);
         }
+        if config.ranks() > 0 {
+            system.spawn_actors(config.executor_typeid(), config.ranks())?;
+        }
+        let live_ranks = system.live_ranks();
+        let mailboxes = system.mailboxes.clone();
+        let names = system.names.clone();
+        let next_id = system.next_id.clone();
+        let system_task = tokio::spawn(async move { system.run().await });
+        Ok(Self {
+            name,
+            system_cmd_sendbox: sender,
+            system_task,
+            live_ranks,
+            mailboxes,
+            names,
+            next_id,
+            registry,
+            ask_timeout: None,
+            listeners: vec![],
+            cost_learner,
+            _marker_o: PhantomData,
+        })
     }
 
     // pub fn init() -> () {
@@ -141,15 +409,251 @@ where
         self.name.clone()
     }
 
-    pub async fn issue_order(&mut self, msg: RaptorMessage<U, O>) -> () {
+    /// number of live actors, spawn/halt orders are reflected once the system
+    /// has processed them
+    pub fn ranks(&self) -> usize {
+        self.live_ranks.load(Ordering::SeqCst)
+    }
+
+    /// ids of the live actors in ascending order, with gaps left by halted ones
+    pub fn live_ids(&self) -> Vec<usize> {
+        let mailboxes = self
+            .mailboxes
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut ids: Vec<usize> = mailboxes
+            .iter()
+            .filter(|(_, (_, x))| !x.is_closed())
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// number of msgs waiting in the mailbox of the actor, None if it is halted
+    pub fn mailbox_depth(&self, id: usize) -> Option<usize> {
+        let mailboxes = self
+            .mailboxes
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        mailboxes
+            .get(&id)
+            .filter(|(_, x)| !x.is_closed())
+            .map(|(_, x)| x.depth())
+    }
+
+    /// address of the actor currently running under the id, a restarted actor
+    /// gets a new address
+    pub fn address_of(&self, id: usize) -> Option<Address> {
+        let mailboxes = self
+            .mailboxes
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        mailboxes
+            .get(&id)
+            .filter(|(_, x)| !x.is_closed())
+            .map(|(address, _)| address.clone())
+    }
+
+    /// halt the actor at the address, the other actors keep their ids
+    pub async fn halt(&mut self, address: Address) {
+        let cmd = SystemCommand::Halt(address);
+        self.issue_order(RaptorMessage::LoadfreeMSG(cmd.into()))
+            .await;
+    }
+
+    pub async fn issue_order(&mut self, msg: RaptorMessage<U, O>) {
         debug!("::actor-system-handler::send msg {:?}", msg);
-        self.system_cmd_sendbox.send(msg).await;
+        send_order(&self.system_cmd_sendbox, msg).await;
+    }
+
+    // issue orders from tasks that outlive the borrow of the handle
+    pub(crate) fn sendbox(&self) -> mpsc::Sender<RaptorMessage<U, O>> {
+        self.system_cmd_sendbox.clone()
+    }
+
+    /// spawn `cnt` actors of the executor kind registered under this name
+    pub async fn spawn(&mut self, executor_kind: &str, cnt: usize) -> Result<(), RaptorsError> {
+        let typeid = self.typeid_of(executor_kind)?;
+        let cmd = SystemCommand::Spawn(typeid, cnt);
+        self.issue_order(RaptorMessage::LoadfreeMSG(cmd.into()))
+            .await;
+        Ok(())
+    }
+
+    /// spawn one actor of the executor kind that is looked up by its name, the
+    /// name is free again once the actor is halted
+    pub async fn spawn_named(
+        &mut self,
+        executor_kind: &str,
+        name: &str,
+    ) -> Result<(), RaptorsError> {
+        let typeid = self.typeid_of(executor_kind)?;
+        let id = {
+            let mut names = self.names.write().unwrap_or_else(PoisonError::into_inner);
+            if names.contains_key(name) {
+                return Err(RaptorsError::system(format!(
+                    "actor name {} is taken already",
+                    name
+                )));
+            }
+            // the id is reserved here so that the actor is found right away
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+            names.insert(name.to_string(), id);
+            id
+        };
+        let cmd = SystemCommand::SpawnNamed(typeid, id, name.to_string());
+        self.issue_order(RaptorMessage::LoadfreeMSG(cmd.into()))
+            .await;
+        Ok(())
+    }
+
+    /// reference to the actor spawned under the name
+    pub fn lookup(&self, name: &str) -> Option<ActorRef<U, O>> {
+        let names = self.names.read().unwrap_or_else(PoisonError::into_inner);
+        names
+            .get(name)
+            .map(|id| ActorRef::new(*id, name, self.system_cmd_sendbox.clone(), self.ask_timeout))
+    }
+
+    /// issue a workload to the pool of actors of the executor kind
+    pub async fn issue_to_pool(
+        &mut self,
+        executor_kind: &str,
+        msg: PayloadMessage<U, O>,
+    ) -> Result<(), RaptorsError> {
+        let typeid = self.typeid_of(executor_kind)?;
+        self.issue_order(RaptorMessage::PoolMSG(typeid, msg)).await;
+        Ok(())
+    }
+
+    /// subscribe the actor to the topic, msgs published to the topic reach
+    /// it until it unsubscribes or is halted
+    pub async fn subscribe(&mut self, topic: &str, id: usize) {
+        let cmd = SystemCommand::Subscribe(topic.to_string(), id);
+        self.issue_order(RaptorMessage::LoadfreeMSG(cmd.into()))
+            .await;
+    }
+
+    pub async fn unsubscribe(&mut self, topic: &str, id: usize) {
+        let cmd = SystemCommand::Unsubscribe(topic.to_string(), id);
+        self.issue_order(RaptorMessage::LoadfreeMSG(cmd.into()))
+            .await;
     }
 
-    pub async fn spawn(&mut self, executor_type: &str, cnt: usize) {
-        panic!("deprecated");
-        let cmd: LoadfreeMessage<U> = build_loadfree_msg!("spawn", executor_type, cnt);
-        self.issue_order(RaptorMessage::LoadfreeMSG(cmd)).await
+    /// send the msg to every actor subscribed to the topic
+    pub async fn publish(&mut self, topic: &str, msg: LoadfreeMessage<U>) {
+        self.issue_order(RaptorMessage::PublishMSG(topic.to_string(), msg))
+            .await;
+    }
+
+    /// hand the data to the executor of every actor subscribed to the topic,
+    /// see `ExecutorLike::on_published`
+    pub async fn publish_data(&mut self, topic: &str, data: U) {
+        let msg = LoadfreeMessage::DataMsg(topic.to_string(), data);
+        self.publish(topic, msg).await;
+    }
+
+    fn typeid_of(&self, executor_kind: &str) -> Result<usize, RaptorsError> {
+        self.registry
+            .typeid_of(executor_kind)
+            .ok_or_else(|| RaptorsError::system(format!("unknown executor kind {}", executor_kind)))
+    }
+
+    pub fn set_ask_timeout(&mut self, timeout: Option<Duration>) {
+        self.ask_timeout = timeout;
+    }
+
+    /// the costs learned by the actors, None unless the config enables cost
+    /// learning; take a `snapshot` to export the learned table
+    pub fn cost_learner(&self) -> Option<&CostLearner<O>> {
+        self.cost_learner.as_ref()
+    }
+
+    /// compute the op on the tensor and wait for the result
+    pub async fn ask_unary(&mut self, op: O, inp: U) -> Result<U, RaptorsError> {
+        let (respond_to, reply) = oneshot::channel();
+        let msg = PayloadMessage::UnaryComputeFunctorMsg {
+            op,
+            inp: Arc::new(RwLock::new(inp)),
+            respond_to,
+        };
+        self.ask(msg, reply).await
+    }
+
+    /// compute the op on the two tensors and wait for the result
+    pub async fn ask_binary(&mut self, op: O, lhs: U, rhs: U) -> Result<U, RaptorsError> {
+        let (respond_to, reply) = oneshot::channel();
+        let msg = PayloadMessage::ComputeFunctorMsg {
+            op,
+            lhs: Arc::new(RwLock::new(lhs)),
+            rhs: Arc::new(RwLock::new(rhs)),
+            respond_to,
+        };
+        self.ask(msg, reply).await
+    }
+
+    async fn ask(
+        &mut self,
+        msg: PayloadMessage<U, O>,
+        reply: oneshot::Receiver<Result<U, RaptorsError>>,
+    ) -> Result<U, RaptorsError> {
+        self.issue_order(RaptorMessage::PayloadMSG(msg)).await;
+        wait_reply(reply, self.ask_timeout).await
+    }
+
+    /// shutdown the actor system gracefully: workloads issued before are all
+    /// dispatched and completed, then every actor is stopped and joined.
+    ///
+    /// An actor failure escalated by the supervisor has stopped the system
+    /// already, it is reported here.
+    pub async fn shutdown(mut self) -> Result<ShutdownSummary, RaptorsError> {
+        info!("::actor-system-handler::shutdown");
+        for listener in self.listeners.drain(..) {
+            listener.abort();
+        }
+        let cmd: LoadfreeMessage<U> = build_loadfree_msg!("shutdown");
+        self.issue_order(RaptorMessage::LoadfreeMSG(cmd)).await;
+        self.system_task
+            .await
+            .map_err(|e| RaptorsError::system(format!("fail to shutdown: {}", e)))?
+    }
+}
+
+impl<T, U, O> ActorSystemHandle<T, U, O>
+where
+    T: 'static + ExecutorLike<TensorType = U, OpCodeType = O> + Send + Sync,
+    U: 'static + WireTensor + Clone + Send + Sync + Debug,
+    O: 'static + OpCodeLike + Debug + Send + Sync + Serialize + DeserializeOwned,
+{
+    /// accept peer systems on the socket address until shutdown, returns the
+    /// address bound, e.g. to find the port picked for "127.0.0.1:0"
+    pub async fn listen(&mut self, addr: impl ToSocketAddrs) -> Result<SocketAddr, RaptorsError> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| RaptorsError::transport(format!("fail to listen: {}", e)))?;
+        let local = listener
+            .local_addr()
+            .map_err(|e| RaptorsError::transport(e.to_string()))?;
+        info!("::actor-system-handler::listen on {}", local);
+        let serving = transport::serve(
+            listener,
+            self.system_cmd_sendbox.clone(),
+            self.registry.clone(),
+        );
+        self.listeners.push(tokio::spawn(serving));
+        Ok(local)
+    }
+
+    /// connect to the system listening on the address, asks to it wait as
+    /// long as asks to this system
+    pub async fn connect(
+        &self,
+        addr: impl ToSocketAddrs,
+    ) -> Result<RemoteSystem<U, O>, RaptorsError> {
+        let mut remote = RemoteSystem::connect(addr).await?;
+        remote.set_ask_timeout(self.ask_timeout);
+        Ok(remote)
     }
 }
 
@@ -161,16 +665,41 @@ where
     O: 'static + OpCodeLike + Debug + Send,
 {
     // TODO need a state machine that monitor actors
-    // and allow graceful shutdown
     name: String,
     ranks: usize,
-    pub mails: Vec<mpsc::Sender<RaptorMessage<U, O>>>,
-    pub availables: Vec<usize>,
+    address: Address,
+    actors: ActorRegistry<RaptorMessage<U, O>>,
     system_cmd_recvbox: mpsc::Receiver<RaptorMessage<U, O>>,
     cloned_sendbox: mpsc::Sender<RaptorMessage<U, O>>,
-    delayed_tensor_types: Vec<RaptorMessage<U, O>>,
-    _markerT: PhantomData<T>,
-    _markerO: PhantomData<O>,
+    dispatcher: Dispatcher<U, O>,
+    // watchers of the actor tasks, see supervisor::watch_actor
+    actor_handles: Vec<JoinHandle<bool>>,
+    // number of live actors, shared with the handles for querying
+    live_ranks: Arc<AtomicUsize>,
+    // the next id to give to a spawned actor, named actors reserve theirs
+    // from the handle
+    next_id: Arc<AtomicUsize>,
+    names: ActorNames,
+    topics: Topics,
+    mailboxes: MailboxProbes<U, O>,
+    mailbox_capacity: usize,
+    overflow_policy: OverflowPolicy,
+    // per actor states the supervisor needs to respawn it
+    typeids: HashMap<usize, usize>,
+    // the generation of the actor running under each id, the msgs of an actor
+    // that was replaced since are stale
+    generations: HashMap<usize, usize>,
+    inflights: HashMap<usize, (usize, InflightSlot<U, O>)>,
+    restarts: HashMap<usize, usize>,
+    strategy: SupervisorStrategy,
+    max_restarts: usize,
+    escalated: Option<RaptorsError>,
+    registry: Arc<ExecutorRegistry<T>>,
+    blocking_pool: BlockingPool,
+    cost_learner: Option<CostLearner<O>>,
+    summary: ShutdownSummary,
+    shutting_down: bool,
+    _marker_o: PhantomData<O>,
 }
 
 impl<T, U, O> ActorSystem<T, U, O>
@@ -187,17 +716,35 @@ where
         // refer to stackoverflow.com/questions/48850403/change-timestamp-format-used-by-env-logger
         // set default usage of info log level
 
-        let mut mailboxes: Vec<mpsc::Sender<RaptorMessage<U, O>>> = vec![];
         Self {
             name: String::from(name),
             ranks: 0,
-            mails: mailboxes,
-            availables: vec![],
+            address: Address::new(uuid::Uuid::new_v4()),
+            actors: ActorRegistry::new(),
             system_cmd_recvbox: receiver,
             cloned_sendbox: cloned_sender,
-            delayed_tensor_types: vec![],
-            _markerT: PhantomData,
-            _markerO: PhantomData,
+            dispatcher: Dispatcher::new(),
+            actor_handles: vec![],
+            live_ranks: Arc::new(AtomicUsize::new(0)),
+            next_id: Arc::new(AtomicUsize::new(0)),
+            names: Arc::new(RwLock::new(HashMap::new())),
+            topics: Topics::new(),
+            mailboxes: Arc::new(RwLock::new(HashMap::new())),
+            mailbox_capacity: 16,
+            overflow_policy: OverflowPolicy::default(),
+            typeids: HashMap::new(),
+            generations: HashMap::new(),
+            inflights: HashMap::new(),
+            restarts: HashMap::new(),
+            strategy: SupervisorStrategy::default(),
+            max_restarts: 3,
+            escalated: None,
+            registry: Arc::new(ExecutorRegistry::new()),
+            blocking_pool: BlockingPool::default(),
+            cost_learner: None,
+            summary: ShutdownSummary::default(),
+            shutting_down: false,
+            _marker_o: PhantomData,
         }
     }
 
@@ -209,205 +756,535 @@ where
         self.ranks
     }
 
-    // get the first idle/available actor tid
-    // replace this into mpsc receiver with multiple actor to generate key back
-    pub fn poll_ready_actor(&mut self) -> Option<usize> {
-        if self.availables.is_empty() == true {
-            None
-        } else {
-            Some(self.availables.remove(0))
-        }
+    pub fn live_ranks(&self) -> Arc<AtomicUsize> {
+        self.live_ranks.clone()
+    }
+
+    fn update_live_ranks(&self) {
+        let live = self.actors.live_ids().len();
+        self.live_ranks.store(live, Ordering::SeqCst);
+    }
+
+    pub fn set_supervisor_strategy(&mut self, strategy: SupervisorStrategy, max_restarts: usize) {
+        self.strategy = strategy;
+        self.max_restarts = max_restarts;
+    }
+
+    pub fn set_scheduling_policy(&mut self, policy: Box<dyn SchedulingPolicy<U, O>>) {
+        self.dispatcher.set_policy(policy);
+    }
+
+    pub fn dispatcher(&self) -> &Dispatcher<U, O> {
+        &self.dispatcher
+    }
+
+    pub fn set_executor_registry(&mut self, registry: Arc<ExecutorRegistry<T>>) {
+        self.registry = registry;
+    }
+
+    pub fn set_blocking_pool(&mut self, pool: BlockingPool) {
+        self.blocking_pool = pool;
+    }
+
+    /// actors spawned from now on report their executor calls to the learner
+    pub fn set_cost_learner(&mut self, learner: CostLearner<O>) {
+        self.cost_learner = Some(learner);
+    }
+
+    pub fn set_mailbox(&mut self, capacity: usize, overflow_policy: OverflowPolicy) {
+        self.mailbox_capacity = capacity;
+        self.overflow_policy = overflow_policy;
     }
 
     #[tracing::instrument(name = "actor_system", skip(self, typeid, cnt))]
-    pub fn spawn_actors(&mut self, typeid: usize, cnt: usize) -> Result<(), String> {
-        for id in self.ranks..(self.ranks + cnt) {
-            info!("::actor_system::new actor #{}", id);
-            let (sender, receiver) = mpsc::channel(16);
-            self.mails.push(sender);
-            let mut actor =
-                Actor::<T, U, O>::new(id, receiver, self.cloned_sendbox.clone(), typeid);
-            info!("::actor_system::run-event-loop actor #{}", id);
-            tokio::spawn(async move { actor.run().await });
-
-            self.availables.push(id);
+    pub fn spawn_actors(&mut self, typeid: usize, cnt: usize) -> Result<(), RaptorsError> {
+        if self.registry.name_of(typeid).is_none() {
+            return Err(RaptorsError::system(format!(
+                "no executor kind is registered with typeid {}",
+                typeid
+            )));
+        }
+        for _ in 0..cnt {
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+            self.start_actor(id, typeid);
+            self.enlist_actor(id, typeid);
             info!("::actor-system::enqueue actor-#{} to avlb-queue", id);
         }
         self.ranks += cnt;
+        self.update_live_ranks();
+        Ok(())
+    }
+
+    // the id and name are reserved by the handle already
+    pub fn spawn_named_actor(&mut self, typeid: usize, id: usize) -> Result<(), RaptorsError> {
+        if self.typeids.contains_key(&id) {
+            return Err(RaptorsError::actor(id, "id is taken already"));
+        }
+        self.start_actor(id, typeid);
+        self.enlist_actor(id, typeid);
+        self.ranks += 1;
+        self.update_live_ranks();
+        Ok(())
+    }
+
+    // run a new actor under supervision and register its mailbox by address
+    fn start_actor(&mut self, id: usize, typeid: usize) {
+        let generation = self.generations.get(&id).map_or(0, |x| x + 1);
+        self.generations.insert(id, generation);
+        info!(
+            "::actor_system::new actor #{} of generation {}",
+            id, generation
+        );
+        let (sender, receiver) = mailbox::channel(self.mailbox_capacity, self.overflow_policy);
+        let executor = self.registry.build(typeid);
+        let mut actor = Actor::<T, U, O>::new_with_executor(
+            id,
+            receiver,
+            self.cloned_sendbox.clone(),
+            executor,
+        );
+        actor.set_generation(generation);
+        actor.set_blocking_pool(self.blocking_pool.clone());
+        if let Some(learner) = self.cost_learner.clone() {
+            actor.set_cost_learner(learner);
+        }
+        let address = Address::new(actor.uuid());
+        self.mailboxes
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, (address.clone(), sender.probe()));
+        self.actors.register(id, address, sender);
+        self.typeids.insert(id, typeid);
+        self.inflights.insert(id, (generation, actor.inflight()));
+        info!("::actor_system::run-event-loop actor #{}", id);
+        let actor_task = tokio::spawn(async move { actor.run().await });
+        self.actor_handles.push(watch_actor(
+            id,
+            generation,
+            actor_task,
+            self.cloned_sendbox.clone(),
+        ));
+    }
+
+    // hand the actor to the dispatcher, with the kind its executor is
+    // registered under for the policy to estimate its workloads with
+    fn enlist_actor(&mut self, id: usize, typeid: usize) {
+        match self.registry.name_of(typeid) {
+            Some(kind) => self.dispatcher.add_actor_of_kind(id, typeid, kind),
+            None => self.dispatcher.add_actor(id, typeid),
+        }
+    }
+
+    // replace the actor behind `id` by a fresh one with the same executor typeid,
+    // a replaced actor that is still alive exits once its queued msgs are done
+    fn restart_actor(&mut self, id: usize) -> Result<(), RaptorsError> {
+        let typeid = match self.typeids.get(&id) {
+            Some(typeid) => *typeid,
+            None => return Err(RaptorsError::actor(id, "restart out of actor id range")),
+        };
+        // the acks of a replaced actor that is still alive are stale, nothing
+        // waits for its queued workloads anymore
+        self.dispatcher.retire_actor(id);
+        self.start_actor(id, typeid);
+        self.summary.actors_restarted += 1;
+        self.enlist_actor(id, typeid);
+        info!("::actor-system::restart actor-#{}", id);
         Ok(())
     }
 
     #[tracing::instrument(name = "actor_system", skip(self))]
-    pub fn halt_actor(&mut self, index: usize) -> Result<(), String> {
-        if index >= self.mails.len() {
-            return Err(String::from("halt cmd out of actor id range"));
+    pub async fn on_actor_panicked(
+        &mut self,
+        id: usize,
+        generation: usize,
+    ) -> Result<(), RaptorsError> {
+        if self.is_stale(id, generation) {
+            // an actor replaced under RestartAll, its successor is healthy
+            info!("::actor-system::replaced actor-#{} panicked, ignored", id);
+            return Ok(());
         }
-        self.mails.remove(index);
+        info!("::actor-system::actor-#{} panicked", id);
+        // the workload it was processing is left in its in-flight slot, any
+        // other workload queued to it went down with its mailbox
+        let parked = self
+            .inflights
+            .remove(&id)
+            .filter(|(x, _)| *x == generation)
+            .and_then(|(_, x)| lock_slot(&x).take());
+        let mut lost = self.dispatcher.lose_actor(id);
+
+        let restarts = self.restarts.entry(id).or_insert(0);
+        *restarts += 1;
+        let strategy = match self.strategy {
+            SupervisorStrategy::RestartOne | SupervisorStrategy::RestartAll
+                if *restarts > self.max_restarts =>
+            {
+                info!("::actor-system::actor-#{} restarts too often, stop it", id);
+                SupervisorStrategy::Stop
+            }
+            others => others,
+        };
+
+        let status = match strategy {
+            SupervisorStrategy::RestartOne | SupervisorStrategy::RestartAll => {
+                if let Some(msg) = parked {
+                    info!("::actor-system::requeue workload of actor-#{}", id);
+                    self.dispatcher.requeue(msg);
+                    lost = lost.saturating_sub(1);
+                }
+                self.restart_actor(id)?;
+                if strategy == SupervisorStrategy::RestartAll {
+                    // halted actors stay halted
+                    let others: Vec<usize> = self
+                        .actors
+                        .live_ids()
+                        .into_iter()
+                        .filter(|x| *x != id)
+                        .collect();
+                    for other in others {
+                        self.restart_actor(other)?;
+                    }
+                }
+                self.dispatch_delayed().await;
+                Ok(())
+            }
+            SupervisorStrategy::Stop => {
+                // halting drops the pending workloads stranded by the stop
+                let status = self.halt_actor(id);
+                drop(parked);
+                status
+            }
+            SupervisorStrategy::Escalate => {
+                drop(parked);
+                for msg in self.dispatcher.take_pending() {
+                    msg.reject(RaptorsError::system("the system failed"));
+                    lost += 1;
+                }
+                self.escalated = Some(RaptorsError::actor(
+                    id,
+                    "panicked, the failure is escalated by the supervisor",
+                ));
+                Ok(())
+            }
+        };
+        self.summary.tasks_dropped += lost;
+        status
+    }
+
+    // msgs of an actor that was restarted since it sent them
+    fn is_stale(&self, id: usize, generation: usize) -> bool {
+        self.generations.get(&id) != Some(&generation)
+    }
+
+    // route the workload through the dispatcher and deliver it if an actor is
+    // found, otherwise it stays pending or is dropped
+    async fn dispatch(&mut self, msg: RaptorMessage<U, O>) {
+        match self.dispatcher.route(msg) {
+            Route::Deliver(idx, msg) => {
+                info!("::actor-system::dispatch workload to actor #{:?}", idx);
+                self.deliver_to(msg, idx).await;
+            }
+            Route::Delayed => info!("::actor-system::not-find avlb-actor, delay the workload"),
+            Route::Dropped(msg) => self.drop_workload(msg, "no actor can take the workload"),
+        }
+    }
+
+    // the workload is never computed, its submitter is told why
+    fn drop_workload(&mut self, msg: RaptorMessage<U, O>, reason: &str) {
+        info!("::actor-system::drop workload - {}", reason);
+        msg.reject(RaptorsError::system(reason));
+        self.summary.tasks_dropped += 1;
+    }
+
+    // pending workloads wait for an actor to be spawned, except on shutdown:
+    // once no actor is left they are dropped, otherwise the system waits on
+    // them forever
+    fn drop_stranded(&mut self) {
+        if !self.shutting_down || !self.actors.live_ids().is_empty() {
+            return;
+        }
+        for msg in self.dispatcher.take_pending() {
+            self.drop_workload(msg, "shutting down without actors");
+        }
+    }
+
+    // hand pending workloads over to available actors, as many as possible
+    async fn dispatch_delayed(&mut self) {
+        for (idx, msg) in self.dispatcher.drain() {
+            info!(
+                "::actor-system::dispatch delayed payload-msg to actor-#{}",
+                idx
+            );
+            self.deliver_to(msg, idx).await;
+        }
+    }
+
+    #[tracing::instrument(name = "actor_system", skip(self))]
+    pub fn halt_actor(&mut self, index: usize) -> Result<(), RaptorsError> {
+        // the actor exits once it has handled the msgs queued to it already
+        if self.actors.deregister(index).is_none() {
+            return Err(RaptorsError::system(format!(
+                "halt cmd out of actor id range, got #{}",
+                index
+            )));
+        }
+        for msg in self.dispatcher.remove_actor(index) {
+            self.drop_workload(msg, "no actor is left in the pool");
+        }
+        self.names
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|_, id| *id != index);
+        self.topics.remove_actor(index);
+        self.update_live_ranks();
+        self.drop_stranded();
         Ok(())
     }
 
     #[tracing::instrument(name = "actor_system", skip(self))]
-    pub fn halt_all(&mut self) -> Result<(), String> {
-        self.mails.clear();
+    pub fn halt_address(&mut self, address: &Address) -> Result<(), RaptorsError> {
+        match self.actors.resolve(address) {
+            Some(index) => self.halt_actor(index),
+            None => Err(RaptorsError::system(format!(
+                "halt cmd on unknown address {}",
+                address.into_aid()
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "actor_system", skip(self))]
+    pub fn halt_all(&mut self) -> Result<(), RaptorsError> {
+        self.actors.clear();
+        self.names
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        self.topics.clear();
+        for msg in self.dispatcher.remove_all() {
+            self.drop_workload(msg, "no actor is left in the pool");
+        }
+        self.update_live_ranks();
+        self.drop_stranded();
         Ok(())
     }
 
+    // send a workload routed by the dispatcher to the actor
     #[tracing::instrument(name = "actor_system", skip(self, msg, to))]
-    pub async fn deliver_to(&self, msg: RaptorMessage<U, O>, to: usize) {
-        self.mails[to].send(msg).await;
+    pub async fn deliver_to(&mut self, msg: RaptorMessage<U, O>, to: usize) {
+        // the target of pinned and pool workloads is resolved here
+        let msg = match msg {
+            RaptorMessage::PinnedMSG(_, payload) | RaptorMessage::PoolMSG(_, payload) => {
+                RaptorMessage::PayloadMSG(payload)
+            }
+            msg => msg,
+        };
+        let sent = match self.actors.address_of(to) {
+            Some(receiver) => {
+                let envelope = Envelope::new(self.address.clone(), receiver.clone(), msg);
+                self.actors.deliver(envelope).await
+            }
+            None => Err((RaptorsError::actor(to, "no such actor"), msg)),
+        };
+        match sent {
+            Ok(None) => (),
+            // the mailbox made room by dropping its oldest workload
+            Ok(Some(displaced)) => {
+                info!("::actor_system::mailbox of actor #{:?} overflows", to);
+                if displaced.is_workload() {
+                    self.dispatcher.on_undelivered(to, &displaced);
+                    self.drop_workload(displaced, "mailbox overflows");
+                }
+            }
+            Err((e, msg)) => {
+                info!("::actor_system::msg to actor #{:?} dropped - {}", to, e);
+                self.dispatcher.on_undelivered(to, &msg);
+                self.drop_workload(msg, "the actor refused the workload");
+            }
+        }
         debug!("::actor_system::send msg to actor #{:?}", to);
     }
 
     #[tracing::instrument(name = "actor_system", skip(self, msg))]
     pub async fn broadcast(&self, msg: LoadfreeMessage<U>) {
-        for mail in &self.mails {
-            mail.send(RaptorMessage::LoadfreeMSG(msg.clone())).await;
+        for mail in self.actors.mails() {
+            // halted actors are skipped silently
+            let _ = mail.send(RaptorMessage::LoadfreeMSG(msg.clone())).await;
         }
         debug!("::actor_system::send msg to all actors");
     }
 
+    pub fn topics(&self) -> &Topics {
+        &self.topics
+    }
+
+    #[tracing::instrument(name = "actor_system", skip(self))]
+    pub fn subscribe(&mut self, topic: &str, id: usize) -> Result<(), RaptorsError> {
+        if !self.actors.contains(id) {
+            return Err(RaptorsError::system(format!(
+                "subscribe cmd on unknown actor #{}",
+                id
+            )));
+        }
+        if !self.topics.subscribe(topic, id) {
+            debug!(
+                "::actor_system::actor #{} subscribed to {} already",
+                id, topic
+            );
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "actor_system", skip(self))]
+    pub fn unsubscribe(&mut self, topic: &str, id: usize) -> Result<(), RaptorsError> {
+        self.topics.unsubscribe(topic, id)
+    }
+
+    // unlike workloads, published msgs are not acknowledged by the actors
+    #[tracing::instrument(name = "actor_system", skip(self, msg))]
+    pub async fn publish(
+        &mut self,
+        topic: &str,
+        msg: LoadfreeMessage<U>,
+    ) -> Result<(), RaptorsError> {
+        let msg = match msg {
+            // mock tensors are computed and acknowledged like any workload
+            LoadfreeMessage::MockTensorMsg(_) => {
+                return Err(RaptorsError::system(format!(
+                    "workloads are dispatched, not published to topic {}",
+                    topic
+                )))
+            }
+            // data is delivered on the topic it is published to
+            LoadfreeMessage::DataMsg(_, data) => LoadfreeMessage::DataMsg(topic.to_string(), data),
+            msg => msg,
+        };
+        for id in self.topics.subscribers(topic) {
+            let receiver = match self.actors.address_of(id) {
+                Some(receiver) => receiver.clone(),
+                None => continue,
+            };
+            let envelope = Envelope::new(
+                self.address.clone(),
+                receiver,
+                RaptorMessage::LoadfreeMSG(msg.clone()),
+            );
+            if let Err(e) = self.actors.send(envelope).await {
+                info!(
+                    "::actor_system::msg on {} to actor #{:?} dropped - {}",
+                    topic, id, e
+                );
+            }
+        }
+        debug!("::actor_system::publish msg to subscribers of {}", topic);
+        Ok(())
+    }
+
+    // shutdown starts by refusing new workloads, the delayed ones are still
+    // dispatched; if no actor is alive to take them they are dropped instead,
+    // now or once the last actor is halted or stopped
+    #[tracing::instrument(name = "actor_system", skip(self))]
+    pub fn begin_shutdown(&mut self) -> Result<(), RaptorsError> {
+        info!("::actor-system::begin-shutdown");
+        self.shutting_down = true;
+        self.drop_stranded();
+        Ok(())
+    }
+
+    // close every mailbox so that actors exit their event loop, then join them
+    async fn finish_shutdown(&mut self) -> Result<ShutdownSummary, RaptorsError> {
+        let _ = self.halt_all();
+        // nobody listens to the actors anymore, their last words are dropped
+        self.system_cmd_recvbox.close();
+        for handle in self.actor_handles.drain(..) {
+            if let Ok(true) = handle.await {
+                self.summary.actors_stopped += 1;
+            }
+        }
+        info!("::actor-system::shutdown {:?}", self.summary);
+        match self.escalated.take() {
+            Some(e) => Err(e),
+            None => Ok(self.summary),
+        }
+    }
+
     #[tracing::instrument(name = "system::run", skip(self))]
-    pub async fn run(&mut self) -> () {
+    pub async fn run(&mut self) -> Result<ShutdownSummary, RaptorsError> {
         info!("::actor-system::start-event-loop");
         loop {
-            match self.system_cmd_recvbox.recv().await {
-                Some(gmsg) => {
-                    match gmsg {
-                        RaptorMessage::PayloadMSG(ref msg) => {
-                            match msg {
-                                PayloadMessage::NonRetBinaryComputeFunctorMsg { .. } => {
-                                    debug!("::actor-system::recv payload-msg {:?}", msg);
-                                    let idle_actor = self.poll_ready_actor();
-                                    match idle_actor {
-                                        None => {
-                                            info!("::actor-system::not-find avlb-actor");
-                                            info!("::actor-system::delay this unary-compute-task");
-                                            self.delayed_tensor_types.push(gmsg);
-                                            Ok(())
-                                        }
-                                        Some(idx) => {
-                                            info!("::actor-system::find avlb-actor-#{:?}", idx);
-                                            info!("::actor-system::poll actor-#{} out from avlb-queue", idx);
-                                            info!("::actor-system::dispatch payload-msg to actor #{:?}", idx);
-                                            self.deliver_to(gmsg, idx).await;
-                                            Ok(())
-                                        }
-                                    }
-                                }
-                                PayloadMessage::NonRetUnaryComputeFunctorMsg { .. } => {
-                                    debug!("::actor-system::recv payload-msg {:?}", msg);
-                                    let idle_actor = self.poll_ready_actor();
-                                    match idle_actor {
-                                        None => {
-                                            info!("::actor-system::not-find avlb-actor");
-                                            info!("::actor-system::delay this unary-compute-task");
-                                            self.delayed_tensor_types.push(gmsg);
-                                            Ok(())
-                                        }
-                                        Some(idx) => {
-                                            info!("::actor-system::find avlb-actor-#{:?}", idx);
-                                            info!("::actor-system::poll actor-#{} out from avlb-queue", idx);
-                                            info!("::actor-system::dispatch payload-msg to actor #{:?}", idx);
-                                            self.deliver_to(gmsg, idx).await;
-                                            Ok(())
-                                        }
-                                    }
-                                }
-                                PayloadMessage::UnaryComputeFunctorMsg { .. } => {
-                                    debug!("::actor-system::recv payload-msg {:?}", msg);
-                                    let idle_actor = self.poll_ready_actor();
-                                    match idle_actor {
-                                        None => {
-                                            info!("::actor-system::not-find avlb-actor");
-                                            info!("::actor-system::delay this unary-compute-task");
-                                            self.delayed_tensor_types.push(gmsg);
-                                            Ok(())
-                                        }
-                                        Some(idx) => {
-                                            info!("::actor-system::find avlb-actor-#{:?}", idx);
-                                            info!("::actor-system::poll actor-#{} out from avlb-queue", idx);
-                                            info!("::actor-system::dispatch payload-msg to actor #{:?}", idx);
-                                            self.deliver_to(gmsg, idx).await;
-                                            Ok(())
-                                        }
-                                    }
-                                }
-                                PayloadMessage::ComputeFunctorMsg { .. } => {
-                                    debug!("::actor-system::recv payload-msg {:?}", msg);
-                                    let idle_actor = self.poll_ready_actor();
-                                    match idle_actor {
-                                        None => {
-                                            info!("::actor-system::not-find avlb-actor");
-                                            info!("::actor-system::delay this binary-compute-task");
-                                            self.delayed_tensor_types.push(gmsg);
-                                            Ok(())
-                                        }
-                                        Some(idx) => {
-                                            info!("::actor-system::find avlb-actor-#{:?}", idx);
-                                            info!("::actor-system::poll actor-#{} out from avlb-queue", idx);
-                                            info!("::actor-system::dispatch payload-msg to actor #{:?}", idx);
-                                            self.deliver_to(gmsg, idx).await;
-                                            Ok(())
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        RaptorMessage::LoadfreeMSG(ref msg) => {
-                            match msg {
-                                LoadfreeMessage::SystemMsg(cmd) => match cmd {
-                                    SystemCommand::Spawn(typeid, cnt) => {
-                                        info!("::actor-system::recv loadfree-msg-spawn {:?}", cnt);
-                                        self.spawn_actors(*typeid, *cnt)
-                                    }
-                                    SystemCommand::HaltOn(idx) => self.halt_actor(*idx),
-                                    SystemCommand::HaltAll => self.halt_all(),
-                                    _ => Err("not implemented".to_string()),
-                                },
-                                LoadfreeMessage::MockTensorMsg(_) => {
-                                    let idle_actor = self.poll_ready_actor();
-                                    match idle_actor {
-                                        None => {
-                                            self.delayed_tensor_types.push(gmsg);
-                                            Ok(())
-                                        }
-                                        Some(idx) => {
-                                            info!("::actor-system::dispatch payload-msg to actor #{:?}", idx);
-                                            self.deliver_to(gmsg, idx).await;
-                                            Ok(())
-                                        }
-                                    }
-                                }
-                                LoadfreeMessage::ActorMsg(_amsg) => match _amsg {
-                                    ActorCommand::Available(idx) => {
-                                        info!(
-                                            "::actor-system::enqueue actor-#{} to avlb-queue",
-                                            idx
-                                        );
-                                        self.availables.push(*idx);
-                                        if self.delayed_tensor_types.is_empty() == false {
-                                            let idle_actor = self.poll_ready_actor().unwrap();
-                                            let _delayed_wkl = self.delayed_tensor_types.remove(0);
-                                            info!(
-                                                "::actor-system::dispatch delayed payload-msg to actor-#{}",
-                                                idle_actor
-                                            );
-                                            info!("::actor-system::poll actor-#{} out from avlb-queue", idx);
-                                            self.deliver_to(_delayed_wkl, idle_actor).await;
-                                        }
-                                        Ok(())
-                                    }
-                                    _ => panic!("not implemented"),
-                                },
+            if self.escalated.is_some() || (self.shutting_down && self.dispatcher.is_drained()) {
+                break;
+            }
+            let status = match self.system_cmd_recvbox.recv().await {
+                Some(gmsg) if self.shutting_down && gmsg.is_workload() => {
+                    self.drop_workload(gmsg, "the system is shutting down");
+                    Ok(())
+                }
+                Some(gmsg) if gmsg.is_workload() => {
+                    debug!("::actor-system::recv workload {:?}", gmsg);
+                    self.dispatch(gmsg).await;
+                    Ok(())
+                }
+                Some(RaptorMessage::LoadfreeMSG(LoadfreeMessage::SystemMsg(cmd))) => match cmd {
+                    SystemCommand::Spawn(typeid, cnt) => {
+                        info!("::actor-system::recv loadfree-msg-spawn {:?}", cnt);
+                        self.spawn_actors(typeid, cnt)
+                    }
+                    SystemCommand::SpawnNamed(typeid, id, name) => {
+                        info!("::actor-system::recv loadfree-msg-spawn {}", name);
+                        self.spawn_named_actor(typeid, id)
+                    }
+                    SystemCommand::Subscribe(topic, id) => self.subscribe(&topic, id),
+                    SystemCommand::Unsubscribe(topic, id) => self.unsubscribe(&topic, id),
+                    SystemCommand::HaltOn(idx) => self.halt_actor(idx),
+                    SystemCommand::Halt(address) => self.halt_address(&address),
+                    SystemCommand::HaltAll => self.halt_all(),
+                    SystemCommand::Shutdown => self.begin_shutdown(),
+                },
+                Some(RaptorMessage::PublishMSG(topic, msg)) => self.publish(&topic, msg).await,
+                Some(RaptorMessage::LoadfreeMSG(LoadfreeMessage::ActorMsg(_amsg))) => match _amsg {
+                    // a replaced actor finished a workload of its backlog, its
+                    // successor is enlisted on its own
+                    ActorCommand::Available(idx, generation) if self.is_stale(idx, generation) => {
+                        self.summary.tasks_completed += 1;
+                        Ok(())
+                    }
+                    ActorCommand::Available(idx, _) => {
+                        self.summary.tasks_completed += 1;
+                        info!("::actor-system::enqueue actor-#{} to avlb-queue", idx);
+                        self.dispatcher.on_available(idx);
+                        if let Some(learner) = self.cost_learner.as_ref() {
+                            for (op, estimate) in learner.take_changed() {
+                                self.dispatcher.learn_cost(&op, estimate);
                             }
                         }
-                        // not handle msg other than system cmd
-                        _ => Ok(()),
+                        // several actors may be free by now, drain as many as possible
+                        self.dispatch_delayed().await;
+                        Ok(())
                     }
-                }
-                _ => Ok(()),
+                    ActorCommand::Panicked(idx, generation) => {
+                        self.on_actor_panicked(idx, generation).await
+                    }
+                    ActorCommand::Stopped(idx, _) => {
+                        info!("::actor-system::actor-#{} stopped", idx);
+                        Ok(())
+                    }
+                    _ => Err(RaptorsError::system(format!(
+                        "actor msg {:?} is not handled by the system",
+                        _amsg
+                    ))),
+                },
+                Some(gmsg) => Err(RaptorsError::system(format!(
+                    "msg {:?} is not handled by the system",
+                    gmsg
+                ))),
+                // every sender is gone, nobody is able to talk to the system anymore
+                None => break,
             };
+            if let Err(e) = status {
+                info!("::actor-system::fail to handle msg - {}", e);
+            }
         }
+        self.finish_shutdown().await
     }
 }
 
@@ -415,25 +1292,1097 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cost_model::GraphOp;
+
+    #[tokio::test]
+    async fn build_fails_on_unregistered_executor_test() {
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(2);
+        sys_config.set_executor_typeid(7);
+        let system = SystemBuilder::new()
+            .build_with_config::<MockExecutor, MockTensor, MockOpCode>(sys_config);
+        assert_eq!(
+            system.err(),
+            Some(RaptorsError::system(
+                "no executor kind is registered with typeid 7"
+            ))
+        );
+    }
 
     #[tokio::test]
     async fn create_system_with_new_test_1() {
         let system =
-            ActorSystemHandle::<MockExecutor, MockTensor, MockOpCode>::new("raptor system");
+            ActorSystemHandle::<MockExecutor, MockTensor, MockOpCode>::new("raptor system")
+                .unwrap();
         assert_eq!(system.name(), "raptor system");
     }
 
     #[tokio::test]
     async fn create_system_with_macro_test_1() {
-        let mut system = build_mock_system!("Raptors");
+        let system = build_mock_system!("Raptors").unwrap();
         assert_eq!(system.name(), "Raptors");
     }
 
     #[tokio::test]
     async fn create_system_with_macro_test_2() {
-        let mut system = build_mock_system!("Raptors", 2);
+        let system = build_mock_system!("Raptors", 2).unwrap();
         assert_eq!(system.name(), "Raptors");
-        // TODO-FIX#1, currently not spawn at creation due to async-sync
-        // assert_eq!(system.ranks(), 2);
+        assert_eq!(system.ranks(), 2);
+    }
+
+    #[tokio::test]
+    async fn create_system_with_config_test() {
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(3);
+        sys_config.set_executor_typeid(2);
+        assert_eq!(sys_config.executor_typeid(), 2);
+        let mut system = SystemBuilder::new()
+            .build_with_config::<MockExecutor, MockTensor, MockOpCode>(sys_config)
+            .unwrap();
+        assert_eq!(system.ranks(), 3);
+
+        // actors spawned at creation take workloads without any spawn order
+        for _ in 0..4 {
+            system.issue_order(build_msg!("add-op")).await;
+        }
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.actors_stopped, 3);
+        assert_eq!(summary.tasks_completed, 4);
+    }
+
+    #[tokio::test]
+    async fn ranks_follow_spawn_and_halt_test() {
+        let mut system = build_mock_system!("Raptors", 1).unwrap();
+        assert_eq!(system.ranks(), 1);
+        system.spawn("mock", 2).await.unwrap();
+        system.issue_order(build_msg!("halt", 0)).await;
+        // orders are processed asynchronously by the event loop
+        for _ in 0..100 {
+            if system.ranks() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(system.ranks(), 2);
+        assert_eq!(system.live_ids(), vec![1, 2]);
+        system.issue_order(build_msg!("halt-all")).await;
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.actors_stopped, 3);
+    }
+
+    #[tokio::test]
+    async fn event_loop_runs_while_actors_compute_test() {
+        use std::sync::RwLock;
+        use std::time::{Duration, Instant};
+        use tokio::sync::oneshot;
+
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(1);
+        sys_config.set_blocking_threads(1);
+        assert_eq!(sys_config.blocking_threads(), 1);
+        let mut system = SystemBuilder::new()
+            .build_with_config::<MockExecutor, MockTensor, MockOpCode>(sys_config)
+            .unwrap();
+        // the mock executor sleeps a second on it
+        let (tx, rx) = oneshot::channel();
+        let msg = PayloadMessage::UnaryComputeFunctorMsg {
+            op: MockOpCode::ExpOp,
+            inp: Arc::new(RwLock::new(MockTensor::new(MockOpCode::ExpOp))),
+            respond_to: tx,
+        };
+        system.issue_order(RaptorMessage::PayloadMSG(msg)).await;
+
+        let now = Instant::now();
+        system.spawn("mock", 1).await.unwrap();
+        while system.ranks() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(now.elapsed() < Duration::from_millis(500));
+        assert!(rx.await.unwrap().is_ok());
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 1);
+    }
+
+    #[tokio::test]
+    async fn mailbox_overflow_rejects_workloads_test() {
+        use std::sync::RwLock;
+        use std::time::Duration;
+        use tokio::sync::oneshot;
+
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(1);
+        sys_config.set_mailbox_capacity(1);
+        sys_config.set_overflow_policy(OverflowPolicy::Reject);
+        let mut system = SystemBuilder::new()
+            .build_with_policy::<MockExecutor, MockTensor, MockOpCode>(
+                sys_config,
+                Box::new(LeastLoaded::new(CostModel::new())),
+            )
+            .unwrap();
+        let mut replies = vec![];
+        for _ in 0..3 {
+            let (tx, rx) = oneshot::channel();
+            let msg = PayloadMessage::UnaryComputeFunctorMsg {
+                op: MockOpCode::ExpOp,
+                inp: Arc::new(RwLock::new(MockTensor::new(MockOpCode::ExpOp))),
+                respond_to: tx,
+            };
+            system.issue_order(RaptorMessage::PayloadMSG(msg)).await;
+            replies.push(rx);
+            // the actor takes the first one out of its mailbox meanwhile
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(system.mailbox_depth(0), Some(1));
+        assert_eq!(system.mailbox_depth(1), None);
+        let mut outcomes = vec![];
+        for rx in replies {
+            // the rejected workload is answered with the error
+            outcomes.push(rx.await.unwrap().is_ok());
+        }
+        assert_eq!(outcomes, vec![true, true, false]);
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 2);
+        assert_eq!(summary.tasks_dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn halt_by_address_keeps_other_ids_test() {
+        let mut system = build_mock_system!("Raptors", 3).unwrap();
+        let address = system.address_of(1).unwrap();
+        assert_ne!(system.address_of(2), Some(address.clone()));
+        system.halt(address.clone()).await;
+        for _ in 0..100 {
+            if system.ranks() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(system.address_of(1), None);
+        // a second halt on the address is refused, nothing else changes
+        system.halt(address).await;
+
+        // actor #2 is still reached as #2
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let msg = PayloadMessage::UnaryComputeFunctorMsg {
+            op: MockOpCode::ExpOp,
+            inp: Arc::new(std::sync::RwLock::new(MockTensor::new(MockOpCode::ExpOp))),
+            respond_to: tx,
+        };
+        system.issue_order(RaptorMessage::PinnedMSG(2, msg)).await;
+        assert!(rx.await.unwrap().is_ok());
+        assert_eq!(system.ranks(), 2);
+        // the halted actor #1 is not reached by pinned workloads either
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let msg = PayloadMessage::UnaryComputeFunctorMsg {
+            op: MockOpCode::ExpOp,
+            inp: Arc::new(std::sync::RwLock::new(MockTensor::new(MockOpCode::ExpOp))),
+            respond_to: tx,
+        };
+        system.issue_order(RaptorMessage::PinnedMSG(1, msg)).await;
+        assert!(rx.await.unwrap().is_err());
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 1);
+        assert_eq!(summary.tasks_dropped, 1);
+        assert_eq!(summary.actors_stopped, 3);
+    }
+
+    #[tokio::test]
+    async fn ask_returns_the_result_test() {
+        let mut registry = ExecutorRegistry::<CpuExecutor>::new();
+        registry.register(3, "cpu", CpuExecutor::new).unwrap();
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(1);
+        sys_config.set_executor_typeid(3);
+        let mut system = SystemBuilder::new()
+            .build_with_registry(sys_config, registry)
+            .unwrap();
+        let lhs = CpuTensor::from_f32(vec![2], vec![1.0, 2.0]).unwrap();
+        let rhs = CpuTensor::from_f32(vec![2], vec![3.0, 4.0]).unwrap();
+        let out = system
+            .ask_binary(MockOpCode::AddOp, lhs, rhs)
+            .await
+            .unwrap();
+        assert_eq!(out.as_f32().unwrap(), &[4.0, 6.0]);
+        let out = system
+            .ask_unary(MockOpCode::ExpOp, CpuTensor::zeros(vec![1], DType::F64))
+            .await
+            .unwrap();
+        assert_eq!(out.as_f64().unwrap(), &[1.0]);
+
+        // a failed kernel is reported as the result
+        let lhs = CpuTensor::from_f32(vec![2], vec![1.0, 2.0]).unwrap();
+        let rhs = CpuTensor::from_f64(vec![2], vec![3.0, 4.0]).unwrap();
+        let err = system.ask_binary(MockOpCode::AddOp, lhs, rhs).await;
+        assert!(matches!(
+            err,
+            Err(RaptorsError::Executor { actor_id: 0, .. })
+        ));
+        system.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn ask_times_out_or_reports_dropped_workloads_test() {
+        let mut system = build_mock_system!("Raptors", 1).unwrap();
+        // the mock executor takes a second
+        system.set_ask_timeout(Some(std::time::Duration::from_millis(100)));
+        let err = system
+            .ask_unary(MockOpCode::ExpOp, MockTensor::new(MockOpCode::ExpOp))
+            .await
+            .unwrap_err();
+        assert_eq!(err, RaptorsError::system("no response within 100ms"));
+
+        system.set_ask_timeout(None);
+        system.issue_order(build_msg!("shutdown")).await;
+        let err = system
+            .ask_unary(MockOpCode::ExpOp, MockTensor::new(MockOpCode::ExpOp))
+            .await
+            .unwrap_err();
+        // the submitter is told why
+        assert_eq!(err, RaptorsError::system("the system is shutting down"));
+    }
+
+    #[tokio::test]
+    async fn publish_reaches_subscribers_only_test() {
+        let (sender, receiver) = mpsc::channel(8);
+        let mut system =
+            ActorSystem::<MockExecutor, MockTensor, MockOpCode>::new("Raptors", receiver, sender);
+        let mut receivers = vec![];
+        for id in 0..3 {
+            let (mail, receiver) = mailbox::channel(4, OverflowPolicy::Block);
+            system
+                .actors
+                .register(id, Address::new(uuid::Uuid::new_v4()), mail);
+            receivers.push(receiver);
+        }
+        system.subscribe("params", 0).unwrap();
+        system.subscribe("params", 2).unwrap();
+        assert!(system.subscribe("params", 5).is_err());
+
+        let update: LoadfreeMessage<MockTensor> = build_loadfree_msg!("available", 7, 0);
+        system.publish("params", update.clone()).await.unwrap();
+        assert_eq!(receivers[1].depth(), 0);
+        for id in [0, 2] {
+            let received = receivers[id].recv().await;
+            assert!(matches!(received, Some(RaptorMessage::LoadfreeMSG(msg)) if msg == update));
+        }
+        assert!(system
+            .publish("params", build_loadfree_msg!("add-op"))
+            .await
+            .is_err());
+
+        // halted actors are unsubscribed
+        system.halt_actor(2).unwrap();
+        assert_eq!(system.topics().subscribers("params"), vec![0]);
+        system.unsubscribe("params", 0).unwrap();
+        system.publish("params", update).await.unwrap();
+        assert_eq!(receivers[0].depth(), 0);
+    }
+
+    // executor that keeps the data published to its actor
+    struct ParamExecutor {
+        inner: MockExecutor,
+        received: Arc<std::sync::Mutex<Vec<(String, MockTensor)>>>,
+    }
+
+    impl ExecutorLike for ParamExecutor {
+        type OpCodeType = MockOpCode;
+        type TensorType = MockTensor;
+        fn new_with_typeid(typeid: usize) -> Self {
+            Self {
+                inner: MockExecutor::new_with_typeid(typeid),
+                received: Default::default(),
+            }
+        }
+
+        fn init(&mut self) {}
+
+        fn mock_compute(&mut self, arg: MockTensor) -> Result<MockTensor, RaptorsError> {
+            self.inner.mock_compute(arg)
+        }
+
+        fn unary_compute(
+            &mut self,
+            op: MockOpCode,
+            arg: Arc<std::sync::RwLock<MockTensor>>,
+        ) -> Result<MockTensor, RaptorsError> {
+            self.inner.unary_compute(op, arg)
+        }
+
+        fn unary_compute_v2(
+            &mut self,
+            op: MockOpCode,
+            arg: Arc<std::sync::RwLock<MockTensor>>,
+            out: Arc<std::sync::RwLock<MockTensor>>,
+        ) -> Result<(), RaptorsError> {
+            self.inner.unary_compute_v2(op, arg, out)
+        }
+
+        fn binary_compute(
+            &mut self,
+            op: MockOpCode,
+            lhs: Arc<std::sync::RwLock<MockTensor>>,
+            rhs: Arc<std::sync::RwLock<MockTensor>>,
+        ) -> Result<MockTensor, RaptorsError> {
+            self.inner.binary_compute(op, lhs, rhs)
+        }
+
+        fn binary_compute_v2(
+            &mut self,
+            op: MockOpCode,
+            lhs: Arc<std::sync::RwLock<MockTensor>>,
+            rhs: Arc<std::sync::RwLock<MockTensor>>,
+            out: Arc<std::sync::RwLock<MockTensor>>,
+        ) -> Result<(), RaptorsError> {
+            self.inner.binary_compute_v2(op, lhs, rhs, out)
+        }
+
+        fn on_published(&mut self, topic: &str, data: MockTensor) -> Result<(), RaptorsError> {
+            self.received
+                .lock()
+                .unwrap()
+                .push((topic.to_string(), data));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn published_data_reaches_subscribed_executors_test() {
+        let received = Arc::new(std::sync::Mutex::new(vec![]));
+        let shared = received.clone();
+        let mut registry = ExecutorRegistry::new();
+        registry
+            .register(4, "param", move || ParamExecutor {
+                inner: MockExecutor::new(),
+                received: shared.clone(),
+            })
+            .unwrap();
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(3);
+        sys_config.set_executor_typeid(4);
+        let mut system: ActorSystemHandle<ParamExecutor, MockTensor, MockOpCode> =
+            SystemBuilder::new()
+                .build_with_registry(sys_config, registry)
+                .unwrap();
+        system.subscribe("params", 0).await;
+        system.subscribe("params", 2).await;
+        let update = MockTensor::new(MockOpCode::MatmulOp);
+        system.publish_data("params", update.clone()).await;
+        // the data goes out on the topic it is published to
+        let msg = LoadfreeMessage::DataMsg("others".to_string(), update.clone());
+        system.publish("params", msg).await;
+        system.publish_data("others", MockTensor::default()).await;
+        system.shutdown().await.unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(*received, vec![("params".to_string(), update); 4]);
+    }
+
+    #[tokio::test]
+    async fn named_actors_take_told_workloads_test() {
+        let mut registry = ExecutorRegistry::<CpuExecutor>::new();
+        registry.register(3, "cpu", CpuExecutor::new).unwrap();
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(2);
+        sys_config.set_executor_typeid(3);
+        let mut system = SystemBuilder::new()
+            .build_with_registry(sys_config, registry)
+            .unwrap();
+        system.spawn_named("cpu", "resident").await.unwrap();
+        assert!(system.spawn_named("cpu", "resident").await.is_err());
+        assert!(system.lookup("absent").is_none());
+        let resident = system.lookup("resident").unwrap();
+        assert_eq!(resident.id(), 2);
+
+        // the failure tells which actor computed it
+        for _ in 0..3 {
+            let lhs = CpuTensor::from_f32(vec![1], vec![1.0]).unwrap();
+            let rhs = CpuTensor::from_f64(vec![1], vec![1.0]).unwrap();
+            let err = resident.ask_binary(MockOpCode::AddOp, lhs, rhs).await;
+            assert!(matches!(
+                err,
+                Err(RaptorsError::Executor { actor_id: 2, .. })
+            ));
+        }
+        // told workloads are acknowledged, the actor is available for others
+        for _ in 0..6 {
+            let inp = CpuTensor::zeros(vec![1], DType::F32);
+            assert!(system.ask_unary(MockOpCode::ExpOp, inp).await.is_ok());
+        }
+
+        system.issue_order(build_msg!("halt", 2)).await;
+        for _ in 0..100 {
+            if system.lookup("resident").is_none() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(system.lookup("resident").is_none());
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 9);
+    }
+
+    #[tokio::test]
+    async fn shutdown_without_actors_test() {
+        let system = build_mock_system!("Raptors").unwrap();
+        assert_eq!(system.ranks(), 0);
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary, ShutdownSummary::default());
+    }
+
+    #[tokio::test]
+    async fn shutdown_drops_delayed_workloads_without_actors_test() {
+        let mut system = build_mock_system!("Raptors").unwrap();
+        system.issue_order(build_msg!("add-op")).await;
+        system.issue_order(build_msg!("exp-op")).await;
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.actors_stopped, 0);
+        assert_eq!(summary.tasks_completed, 0);
+        assert_eq!(summary.tasks_dropped, 2);
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_delayed_workloads_test() {
+        let mut system = build_mock_system!("Raptors").unwrap();
+        system.spawn("mock", 2).await.unwrap();
+        for _ in 0..7 {
+            system.issue_order(build_msg!("sub-op")).await;
+        }
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.actors_stopped, 2);
+        assert_eq!(summary.tasks_completed, 7);
+        assert_eq!(summary.tasks_dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn least_loaded_queues_on_busy_actors_test() {
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(2);
+        let mut system = SystemBuilder::new()
+            .build_with_policy::<MockExecutor, MockTensor, MockOpCode>(
+                sys_config,
+                Box::new(LeastLoaded::new(CostModel::new())),
+            )
+            .unwrap();
+        for _ in 0..3 {
+            system.issue_order(build_msg!("exp-op")).await;
+            system.issue_order(build_msg!("add-op")).await;
+        }
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 6);
+        assert_eq!(summary.tasks_dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn pool_workloads_go_to_their_executor_kind_test() {
+        use std::sync::RwLock;
+        use tokio::sync::oneshot;
+
+        let mut registry = ExecutorRegistry::<DynExecutor<CpuTensor, MockOpCode>>::new();
+        registry
+            .register_dyn(0, "mock", MockExecutor::<CpuTensor>::new)
+            .unwrap();
+        registry.register_dyn(3, "cpu", CpuExecutor::new).unwrap();
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(1);
+        let mut system = SystemBuilder::new()
+            .build_with_registry(sys_config, registry)
+            .unwrap();
+        system.spawn("cpu", 1).await.unwrap();
+
+        let inp = Arc::new(RwLock::new(
+            CpuTensor::from_f32(vec![2], vec![0.0, 1.0]).unwrap(),
+        ));
+        let (tx, rx) = oneshot::channel();
+        let msg = PayloadMessage::UnaryComputeFunctorMsg {
+            op: MockOpCode::ExpOp,
+            inp,
+            respond_to: tx,
+        };
+        system.issue_to_pool("cpu", msg).await.unwrap();
+        let out = rx.await.unwrap().unwrap();
+        assert_eq!(out.as_f32().unwrap(), &[1.0, 1.0_f32.exp()]);
+
+        // no actor hosts a blas executor, the workload is dropped
+        for (kind, known) in [("blas", true), ("tpu", false)] {
+            let (tx, _rx) = oneshot::channel();
+            let msg = PayloadMessage::UnaryComputeFunctorMsg {
+                op: MockOpCode::ExpOp,
+                inp: Arc::new(RwLock::new(CpuTensor::default())),
+                respond_to: tx,
+            };
+            assert_eq!(system.issue_to_pool(kind, msg).await.is_ok(), known);
+        }
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 1);
+        assert_eq!(summary.tasks_dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn halting_the_last_actor_of_a_pool_drops_its_workloads_test() {
+        use std::sync::RwLock;
+        use tokio::sync::oneshot;
+
+        let mut registry = ExecutorRegistry::<DynExecutor<CpuTensor, MockOpCode>>::new();
+        registry
+            .register_dyn(0, "mock", MockExecutor::<CpuTensor>::new)
+            .unwrap();
+        registry
+            .register_dyn(5, "slow", MockExecutor::<CpuTensor>::new)
+            .unwrap();
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(1);
+        let mut system = SystemBuilder::new()
+            .build_with_registry(sys_config, registry)
+            .unwrap();
+        system.spawn("slow", 1).await.unwrap();
+
+        // the slow actor computes the first workload for a second, the
+        // second one waits pending when the actor is halted
+        let mut replies = vec![];
+        for _ in 0..2 {
+            let (tx, rx) = oneshot::channel();
+            let msg = PayloadMessage::UnaryComputeFunctorMsg {
+                op: MockOpCode::ExpOp,
+                inp: Arc::new(RwLock::new(CpuTensor::default())),
+                respond_to: tx,
+            };
+            system.issue_to_pool("slow", msg).await.unwrap();
+            replies.push(rx);
+        }
+        system.issue_order(build_msg!("halt", 1)).await;
+        let summary = tokio::time::timeout(Duration::from_secs(10), system.shutdown())
+            .await
+            .expect("shutdown hangs")
+            .unwrap();
+        assert_eq!(summary.tasks_completed, 1);
+        assert_eq!(summary.tasks_dropped, 1);
+        assert_eq!(
+            replies.pop().unwrap().await.unwrap().unwrap_err(),
+            RaptorsError::system("no actor is left in the pool")
+        );
+        assert!(replies.pop().unwrap().await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn compute_failure_goes_back_to_submitter_test() {
+        use std::sync::RwLock;
+        use tokio::sync::oneshot;
+
+        let mut system = build_mock_system!("Raptors", 1).unwrap();
+        let poisoned = Arc::new(RwLock::new(MockTensor::new(MockOpCode::ExpOp)));
+        let writer = poisoned.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = writer.write().unwrap();
+            panic!("poison the operand");
+        })
+        .join();
+
+        let (tx, rx) = oneshot::channel();
+        let msg = PayloadMessage::UnaryComputeFunctorMsg {
+            op: MockOpCode::ExpOp,
+            inp: poisoned,
+            respond_to: tx,
+        };
+        system.issue_order(RaptorMessage::PayloadMSG(msg)).await;
+        let err = rx.await.unwrap().unwrap_err();
+        assert_eq!(
+            err,
+            RaptorsError::executor(0, &MockOpCode::ExpOp, "operand lock poisoned")
+        );
+
+        // the actor survives and keeps serving workloads
+        system.issue_order(build_msg!("add-op")).await;
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.actors_stopped, 1);
+        assert_eq!(summary.tasks_completed, 2);
+    }
+
+    #[tokio::test]
+    async fn compute_failure_poisons_dependents_test() {
+        use std::sync::RwLock;
+        use tokio::sync::oneshot;
+
+        // diamond deps: var1 -> (var2, var3) -> var4, computing var1 fails
+        let mut system = build_mock_system!("Raptors", 2).unwrap();
+        let new_var = || Arc::new(RwLock::new(MockTensor::new(MockOpCode::AddOp)));
+        let (inp, var1, var2, var3, var4) = (new_var(), new_var(), new_var(), new_var(), new_var());
+        let writer = inp.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = writer.write().unwrap();
+            panic!("poison the operand");
+        })
+        .join();
+
+        let (inp_tx, inp_rx) = oneshot::channel();
+        let (tx_12, rx_12) = oneshot::channel();
+        let (tx_13, rx_13) = oneshot::channel();
+        let (tx_24, rx_24) = oneshot::channel();
+        let (tx_34, rx_34) = oneshot::channel();
+        let (sink_tx, sink_rx) = oneshot::channel();
+        inp_tx.send(Ok(())).unwrap();
+
+        let msgs = vec![
+            PayloadMessage::NonRetUnaryComputeFunctorMsg {
+                op: MockOpCode::ExpOp,
+                inp,
+                out: var1.clone(),
+                inp_ready_checker: inp_rx,
+                respond_to: vec![tx_12, tx_13],
+                respond_id: 1,
+            },
+            PayloadMessage::NonRetUnaryComputeFunctorMsg {
+                op: MockOpCode::SinOp,
+                inp: var1.clone(),
+                out: var2.clone(),
+                inp_ready_checker: rx_12,
+                respond_to: vec![tx_24],
+                respond_id: 2,
+            },
+            PayloadMessage::NonRetUnaryComputeFunctorMsg {
+                op: MockOpCode::SinOp,
+                inp: var1,
+                out: var3.clone(),
+                inp_ready_checker: rx_13,
+                respond_to: vec![tx_34],
+                respond_id: 3,
+            },
+            PayloadMessage::NonRetBinaryComputeFunctorMsg {
+                op: MockOpCode::AddOp,
+                lhs: var2,
+                rhs: var3,
+                out: var4,
+                lhs_ready_checker: rx_24,
+                rhs_ready_checker: rx_34,
+                respond_to: vec![sink_tx],
+                respond_id: 4,
+            },
+        ];
+        for msg in msgs {
+            system.issue_order(RaptorMessage::PayloadMSG(msg)).await;
+        }
+
+        // the submitter only sees the root failure, not one error per var
+        match sink_rx.await.unwrap() {
+            Err(RaptorsError::Executor { opcode, msg, .. }) => {
+                assert_eq!(opcode, "ExpOp");
+                assert_eq!(msg, "operand lock poisoned");
+            }
+            others => panic!("expect the root failure, got {:?}", others),
+        }
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 4);
+    }
+
+    // executor that panics computing ExpOp, other unary ops panic only the first
+    // time they are seen so that each test owns its op
+    struct PanickyExecutor {
+        inner: MockExecutor,
+    }
+
+    static PANICKED_OPS: std::sync::Mutex<Vec<MockOpCode>> = std::sync::Mutex::new(Vec::new());
+
+    impl ExecutorLike for PanickyExecutor {
+        type OpCodeType = MockOpCode;
+        type TensorType = MockTensor;
+        fn new_with_typeid(typeid: usize) -> Self {
+            Self {
+                inner: MockExecutor::new_with_typeid(typeid),
+            }
+        }
+
+        fn init(&mut self) {}
+
+        fn mock_compute(&mut self, arg: MockTensor) -> Result<MockTensor, RaptorsError> {
+            self.inner.mock_compute(arg)
+        }
+
+        fn unary_compute(
+            &mut self,
+            op: MockOpCode,
+            arg: Arc<std::sync::RwLock<MockTensor>>,
+        ) -> Result<MockTensor, RaptorsError> {
+            let first_time = {
+                let mut panicked = PANICKED_OPS.lock().unwrap();
+                let first_time = !panicked.contains(&op);
+                panicked.push(op);
+                first_time
+            };
+            if op == MockOpCode::ExpOp || first_time {
+                panic!("executor crashes computing {:?}", op);
+            }
+            self.inner.unary_compute(op, arg)
+        }
+
+        fn unary_compute_v2(
+            &mut self,
+            op: MockOpCode,
+            arg: Arc<std::sync::RwLock<MockTensor>>,
+            out: Arc<std::sync::RwLock<MockTensor>>,
+        ) -> Result<(), RaptorsError> {
+            self.inner.unary_compute_v2(op, arg, out)
+        }
+
+        fn binary_compute(
+            &mut self,
+            op: MockOpCode,
+            lhs: Arc<std::sync::RwLock<MockTensor>>,
+            rhs: Arc<std::sync::RwLock<MockTensor>>,
+        ) -> Result<MockTensor, RaptorsError> {
+            self.inner.binary_compute(op, lhs, rhs)
+        }
+
+        fn binary_compute_v2(
+            &mut self,
+            op: MockOpCode,
+            lhs: Arc<std::sync::RwLock<MockTensor>>,
+            rhs: Arc<std::sync::RwLock<MockTensor>>,
+            out: Arc<std::sync::RwLock<MockTensor>>,
+        ) -> Result<(), RaptorsError> {
+            self.inner.binary_compute_v2(op, lhs, rhs, out)
+        }
+    }
+
+    fn panicky_system(
+        strategy: SupervisorStrategy,
+        ranks: usize,
+    ) -> ActorSystemHandle<PanickyExecutor, MockTensor, MockOpCode> {
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(ranks);
+        sys_config.set_supervisor_strategy(strategy);
+        ActorSystemHandle::new_with_config(&sys_config).unwrap()
+    }
+
+    fn unary_payload(
+        op: MockOpCode,
+    ) -> (
+        PayloadMessage<MockTensor, MockOpCode>,
+        tokio::sync::oneshot::Receiver<Result<MockTensor, RaptorsError>>,
+    ) {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let msg = PayloadMessage::UnaryComputeFunctorMsg {
+            op,
+            inp: Arc::new(std::sync::RwLock::new(MockTensor::new(op))),
+            respond_to: tx,
+        };
+        (msg, rx)
+    }
+
+    fn unary_msg(
+        op: MockOpCode,
+    ) -> (
+        RaptorMessage<MockTensor, MockOpCode>,
+        tokio::sync::oneshot::Receiver<Result<MockTensor, RaptorsError>>,
+    ) {
+        let (msg, rx) = unary_payload(op);
+        (RaptorMessage::PayloadMSG(msg), rx)
+    }
+
+    #[tokio::test]
+    async fn restart_one_requeues_the_crashed_workload_test() {
+        let mut system = panicky_system(SupervisorStrategy::RestartOne, 1);
+        let (msg, rx) = unary_msg(MockOpCode::SinOp);
+        system.issue_order(msg).await;
+        // the respawned actor computes the workload its predecessor crashed on
+        assert!(rx.await.unwrap().is_ok());
+        assert_eq!(system.ranks(), 1);
+
+        system.issue_order(build_msg!("add-op")).await;
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.actors_restarted, 1);
+        assert_eq!(summary.tasks_completed, 2);
+        assert_eq!(summary.tasks_dropped, 0);
+        assert_eq!(summary.actors_stopped, 1);
+    }
+
+    #[tokio::test]
+    async fn restart_all_replaces_every_actor_test() {
+        let mut system = panicky_system(SupervisorStrategy::RestartAll, 2);
+        let (msg, rx) = unary_msg(MockOpCode::ConvOp);
+        system.issue_order(msg).await;
+        assert!(rx.await.unwrap().is_ok());
+        assert_eq!(system.ranks(), 2);
+
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.actors_restarted, 2);
+        assert_eq!(summary.tasks_completed, 1);
+        // the replaced healthy actor exits cleanly as well
+        assert_eq!(summary.actors_stopped, 3);
+    }
+
+    #[tokio::test]
+    async fn restart_all_ignores_panics_of_replaced_actors_test() {
+        use tokio::sync::oneshot;
+
+        let mut system = panicky_system(SupervisorStrategy::RestartAll, 2);
+        let new_var = || Arc::new(std::sync::RwLock::new(MockTensor::new(MockOpCode::AddOp)));
+        let (gate_tx, gate_rx) = oneshot::channel();
+        let (done_tx, done_rx) = oneshot::channel();
+        // actor #1 computes until the gate opens, a crash is queued behind
+        let gated = PayloadMessage::NonRetUnaryComputeFunctorMsg {
+            op: MockOpCode::IdentityOp,
+            inp: new_var(),
+            out: new_var(),
+            inp_ready_checker: gate_rx,
+            respond_to: vec![done_tx],
+            respond_id: 1,
+        };
+        system.issue_order(RaptorMessage::PinnedMSG(1, gated)).await;
+        let (crash, crash_rx) = unary_payload(MockOpCode::ExpOp);
+        system.issue_order(RaptorMessage::PinnedMSG(1, crash)).await;
+        // actor #0 crashes once, both actors are replaced
+        let (msg, rx) = unary_payload(MockOpCode::MatmulOp);
+        system.issue_order(RaptorMessage::PinnedMSG(0, msg)).await;
+        assert!(rx.await.unwrap().is_ok());
+
+        // the replaced actor #1 finishes its backlog and crashes on it
+        gate_tx.send(Ok(())).unwrap();
+        assert!(done_rx.await.unwrap().is_ok());
+        assert!(crash_rx.await.is_err());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // its successor is left running and keeps serving workloads
+        let (msg, rx) = unary_payload(MockOpCode::MatmulOp);
+        system.issue_order(RaptorMessage::PinnedMSG(1, msg)).await;
+        assert!(rx.await.unwrap().is_ok());
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.actors_restarted, 2);
+        assert_eq!(summary.tasks_completed, 3);
+        assert_eq!(summary.actors_stopped, 2);
+    }
+
+    #[tokio::test]
+    async fn restart_gives_up_after_max_restarts_test() {
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(1);
+        sys_config.set_max_restarts(2);
+        let mut system =
+            ActorSystemHandle::<PanickyExecutor, MockTensor, MockOpCode>::new_with_config(
+                &sys_config,
+            )
+            .unwrap();
+        let (msg, rx) = unary_msg(MockOpCode::ExpOp);
+        system.issue_order(msg).await;
+        // the workload crashes every actor, it is dropped once the actor is stopped
+        assert!(rx.await.is_err());
+
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.actors_restarted, 2);
+        assert_eq!(summary.tasks_dropped, 1);
+        assert_eq!(summary.actors_stopped, 0);
+    }
+
+    #[tokio::test]
+    async fn stop_halts_the_crashed_actor_test() {
+        let mut system = panicky_system(SupervisorStrategy::Stop, 2);
+        let (msg, rx) = unary_msg(MockOpCode::ExpOp);
+        system.issue_order(msg).await;
+        assert!(rx.await.is_err());
+        assert_eq!(system.ranks(), 1);
+
+        // the surviving actor keeps serving workloads
+        system.issue_order(build_msg!("add-op")).await;
+        let summary = system.shutdown().await.unwrap();
+        assert_eq!(summary.actors_restarted, 0);
+        assert_eq!(summary.tasks_completed, 1);
+        assert_eq!(summary.tasks_dropped, 1);
+        assert_eq!(summary.actors_stopped, 1);
+    }
+
+    #[tokio::test]
+    async fn stop_of_the_last_actor_while_shutting_down_test() {
+        use tokio::sync::oneshot;
+
+        let mut system = panicky_system(SupervisorStrategy::Stop, 1);
+        let new_var = || Arc::new(std::sync::RwLock::new(MockTensor::new(MockOpCode::AddOp)));
+        let (gate_tx, gate_rx) = oneshot::channel();
+        let (done_tx, done_rx) = oneshot::channel();
+        // the only actor computes for a second, the others wait pending
+        let gated = PayloadMessage::NonRetUnaryComputeFunctorMsg {
+            op: MockOpCode::IdentityOp,
+            inp: new_var(),
+            out: new_var(),
+            inp_ready_checker: gate_rx,
+            respond_to: vec![done_tx],
+            respond_id: 1,
+        };
+        system.issue_order(RaptorMessage::PayloadMSG(gated)).await;
+        let (crash, crash_rx) = unary_msg(MockOpCode::ExpOp);
+        system.issue_order(crash).await;
+        let (pending, pending_rx) = unary_msg(MockOpCode::ExpOp);
+        system.issue_order(pending).await;
+        system.issue_order(build_msg!("add-op")).await;
+        gate_tx.send(Ok(())).unwrap();
+
+        // shutdown is received before the actor crashes and is stopped
+        let summary = tokio::time::timeout(Duration::from_secs(10), system.shutdown())
+            .await
+            .expect("shutdown hangs")
+            .unwrap();
+        assert!(done_rx.await.unwrap().is_ok());
+        assert!(crash_rx.await.is_err());
+        assert!(matches!(
+            pending_rx.await,
+            Ok(Err(RaptorsError::System { .. }))
+        ));
+        assert_eq!(summary.tasks_completed, 1);
+        assert_eq!(summary.tasks_dropped, 3);
+        assert_eq!(summary.actors_stopped, 0);
+    }
+
+    #[tokio::test]
+    async fn escalate_reports_the_failure_on_shutdown_test() {
+        let mut system = panicky_system(SupervisorStrategy::Escalate, 1);
+        let (msg, rx) = unary_msg(MockOpCode::ExpOp);
+        system.issue_order(msg).await;
+        assert!(rx.await.is_err());
+
+        let err = system.shutdown().await.unwrap_err();
+        assert_eq!(err.actor_id(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn system_uses_configured_cost_profile_test() {
+        use tokio::sync::oneshot;
+
+        // SinOp costs less than ExpOp by default, more by the profile
+        let mut model = CostModel::new();
+        model.update_model(MockOpCode::SinOp, 500);
+        // unique to this run, suites running at once write profiles of their own
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "raptors-{}-{}-system-profile.toml",
+            std::process::id(),
+            RUNS.fetch_add(1, Ordering::SeqCst)
+        ));
+        model.save(&path).unwrap();
+
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        assert!(sys_config.cost_model::<MockOpCode>().is_none());
+        sys_config.set_cost_profile::<MockOpCode>(&path).unwrap();
+        assert_eq!(sys_config.cost_model(), Some(&model));
+
+        sys_config.set_ranks(1);
+        let mut sys_builder = SystemBuilder::new();
+        let mut system: ActorSystemHandle<MockExecutor, MockTensor, MockOpCode> = sys_builder
+            .build_with_policy(
+                sys_config,
+                Box::new(ShortestJobFirst::new(CostModel::new())),
+            )
+            .unwrap();
+        // each non-returning workload computes for a second, the first one
+        // keeps the only actor busy until both others are pending
+        let nonret_msg = |op, checker| {
+            let (done_tx, done_rx) = oneshot::channel();
+            let msg = PayloadMessage::NonRetUnaryComputeFunctorMsg {
+                op,
+                inp: Arc::new(std::sync::RwLock::new(MockTensor::new(op))),
+                out: Arc::new(std::sync::RwLock::new(MockTensor::new(op))),
+                inp_ready_checker: checker,
+                respond_to: vec![done_tx],
+                respond_id: 1,
+            };
+            (RaptorMessage::PayloadMSG(msg), done_rx)
+        };
+        let ready = || {
+            let (tx, rx) = oneshot::channel();
+            tx.send(Ok(())).unwrap();
+            rx
+        };
+        let (gate_tx, gate_rx) = oneshot::channel();
+        let (gated, _) = nonret_msg(MockOpCode::IdentityOp, gate_rx);
+        system.issue_order(gated).await;
+        let (sin, mut sin_rx) = nonret_msg(MockOpCode::SinOp, ready());
+        system.issue_order(sin).await;
+        let (exp, exp_rx) = nonret_msg(MockOpCode::ExpOp, ready());
+        system.issue_order(exp).await;
+        gate_tx.send(Ok(())).unwrap();
+
+        // the policy picks the workload that is cheaper by the profile
+        assert!(exp_rx.await.unwrap().is_ok());
+        assert!(sin_rx.try_recv().is_err());
+        assert!(sin_rx.await.unwrap().is_ok());
+        system.shutdown().await.unwrap();
+
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        std::fs::write(&path, "SinOp = 5\nCosOp = 3\n").unwrap();
+        assert_eq!(
+            sys_config.set_cost_profile::<MockOpCode>(&path),
+            Err(RaptorsError::config("unknown opcode CosOp"))
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn build_fails_on_cost_model_of_other_opcodes_test() {
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_cost_model(CostModel::set_model(HashMap::from([(GraphOp::Gemm, 1)])));
+        let system = SystemBuilder::new()
+            .build_with_config::<MockExecutor, MockTensor, MockOpCode>(sys_config);
+        assert!(matches!(system, Err(RaptorsError::Config { .. })));
+    }
+
+    #[tokio::test]
+    async fn actors_feed_cost_learner_test() {
+        let mut sys_config = SystemConfig::new("Raptors", "info");
+        sys_config.set_ranks(2);
+        sys_config.set_cost_learning(Estimator::WindowedMedian { window: 8 });
+        let mut sys_builder = SystemBuilder::new();
+        let mut system: ActorSystemHandle<MockExecutor, MockTensor, MockOpCode> = sys_builder
+            .build_with_policy(sys_config, Box::new(LeastLoaded::new(CostModel::new())))
+            .unwrap();
+        for _ in 0..3 {
+            let out = system
+                .ask_unary(MockOpCode::ExpOp, MockTensor::new(MockOpCode::ExpOp))
+                .await;
+            assert!(out.is_ok());
+        }
+        let out = system
+            .ask_binary(
+                MockOpCode::AddOp,
+                MockTensor::new(MockOpCode::AddOp),
+                MockTensor::new(MockOpCode::AddOp),
+            )
+            .await;
+        assert!(out.is_ok());
+
+        let learner = system.cost_learner().unwrap().clone();
+        let snapshot = learner.snapshot();
+        assert_eq!(snapshot.keys().collect::<Vec<_>>(), vec!["AddOp", "ExpOp"]);
+        assert_eq!(snapshot["ExpOp"].samples, 3);
+        assert_eq!(snapshot["AddOp"].samples, 1);
+        assert!(snapshot["ExpOp"].min <= snapshot["ExpOp"].estimate);
+        system.shutdown().await.unwrap();
+        // every estimate has been merged into the policy by then
+        assert!(learner.take_changed().is_empty());
+    }
+
+    #[tokio::test]
+    async fn system_survives_partial_cost_table_test() {
+        let mut costs = HashMap::new();
+        costs.insert(MockOpCode::AddOp, 11);
+        let cost_model = CostModel::set_model(costs);
+        let policies: Vec<Box<dyn SchedulingPolicy<MockTensor, MockOpCode>>> = vec![
+            Box::new(ShortestJobFirst::new(cost_model.clone())),
+            Box::new(LeastLoaded::new(cost_model.clone())),
+        ];
+        for policy in policies {
+            let mut sys_config = SystemConfig::new("Raptors", "info");
+            sys_config.set_ranks(1);
+            let mut sys_builder = SystemBuilder::new();
+            let mut system: ActorSystemHandle<MockExecutor, MockTensor, MockOpCode> =
+                sys_builder.build_with_policy(sys_config, policy).unwrap();
+            system.issue_order(build_msg!("identity-op")).await;
+            system.issue_order(build_msg!("add-op")).await;
+            system.issue_order(build_msg!("exp-op")).await;
+            system.issue_order(build_msg!("sin-op")).await;
+            let out = system
+                .ask_unary(MockOpCode::ExpOp, MockTensor::new(MockOpCode::ExpOp))
+                .await;
+            assert!(out.is_ok());
+            let summary = system.shutdown().await.unwrap();
+            assert_eq!(summary.tasks_completed, 5);
+        }
+        assert!(cost_model.missing().contains_key(&MockOpCode::ExpOp));
+        assert!(cost_model.missing().contains_key(&MockOpCode::IdentityOp));
     }
 }
@@ -1,5 +1,9 @@
 // LICENSE PLACEHOLDER
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Div, Mul, Sub};
 use std::{thread, time};
+
+use serde::{Deserialize, Serialize};
 use tracing::info;
 
 use crate::cost_model::CostModel;
@@ -11,7 +15,72 @@ use crate::prelude::*;
 // tensortype -> payload
 // tensorlike -> computable
 // compute(a: Computable) -> ??
-pub trait TensorLike {}
+pub trait TensorLike {
+    /// shape and dtype for the cost estimation, None for tensors holding no
+    /// data, e.g. MockTensor
+    fn meta(&self) -> Option<TensorMeta> {
+        None
+    }
+
+    /// name of the opcode the tensor stands for when it is sent as a simulated
+    /// workload, None for tensors that are data only
+    fn op_name(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Definition: Chunked tensors are split into chunks along their row-major
+/// data and joined back, collectives move and reduce them chunk by chunk.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// let mut tensor = CpuTensor::from_f32(vec![2, 2], vec![1., 2., 3., 4.]).unwrap();
+/// let chunks = tensor.split(3);
+/// assert_eq!(chunks[0].as_f32(), Some(&[1., 2.][..]));
+/// assert_eq!(chunks[2].as_f32(), Some(&[4.][..]));
+/// tensor.join(&chunks).unwrap();
+/// assert_eq!(tensor.shape(), &[2, 2]);
+/// ```
+pub trait Chunked: Sized {
+    /// `parts` 1-d chunks in order, the first `numel % parts` of them hold one
+    /// element more than the others
+    fn split(&self, parts: usize) -> Vec<Self>;
+
+    /// overwrite the data by the chunks in order, the shape is kept
+    fn join(&mut self, chunks: &[Self]) -> Result<(), RaptorsError>;
+}
+
+/// Definition: TensorMeta is what cost functions see of an operand.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// let tensor = CpuTensor::zeros(vec![4, 8], DType::F64);
+/// let meta = tensor.meta().unwrap();
+/// assert_eq!(meta.numel(), 32);
+/// assert_eq!(meta.bytes(), 256);
+/// assert!(MockTensor::default().meta().is_none());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TensorMeta {
+    pub shape: Vec<usize>,
+    pub dtype: DType,
+}
+
+impl TensorMeta {
+    pub fn new(shape: Vec<usize>, dtype: DType) -> Self {
+        Self { shape, dtype }
+    }
+
+    pub fn numel(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.numel() * self.dtype.size_of()
+    }
+}
 
 // dummy workload as dummy message but has a timeout for
 // emulating the execution
@@ -27,40 +96,48 @@ pub trait TensorLike {}
 // each actor
 //
 //
-#[derive(Clone, Debug, PartialEq, Default, Eq)]
+#[derive(Clone, Debug, PartialEq, Default, Eq, Serialize, Deserialize)]
 pub struct MockTensor {
     op: MockOpCode,
 }
 
-impl TensorLike for MockTensor {}
+impl TensorLike for MockTensor {
+    fn op_name(&self) -> Option<String> {
+        Some(self.op.name())
+    }
+}
 
 impl MockTensor {
     pub fn new(op: MockOpCode) -> MockTensor {
-        return Self { op: op };
+        Self { op }
     }
 
-    pub fn payload(&self) -> usize {
-        CostModel::new().estimate(self)
+    /// estimated cost of the workload by the cost model, pass the one of the
+    /// system config to get the estimate the scheduler works with
+    pub fn payload(&self, cost_model: &CostModel) -> usize {
+        cost_model.estimate(self)
     }
 
     pub fn op(&self) -> MockOpCode {
-        self.op.clone()
+        self.op
     }
 
     // mock function that will fakely run for that period long
     // TODO:
     // 1. change signiture to return values
     // 2. values may use a value type that defined include possible results
-    #[tracing::instrument(name = "workload::mock_run", skip(self))]
-    pub fn mock_run(&self) -> () {
+    #[tracing::instrument(name = "workload::mock_run", skip(self, cost_model))]
+    pub fn mock_run(&self, cost_model: &CostModel) {
         info!("WKL - compute");
-        thread::sleep(time::Duration::from_millis((self.payload() * 10) as u64));
+        thread::sleep(time::Duration::from_millis(
+            (self.payload(cost_model) * 10) as u64,
+        ));
     }
 }
 
-impl Into<LoadfreeMessage<MockTensor>> for MockTensor {
-    fn into(self) -> LoadfreeMessage<MockTensor> {
-        LoadfreeMessage::<MockTensor>::MockTensorMsg(self)
+impl From<MockTensor> for LoadfreeMessage<MockTensor> {
+    fn from(tensor: MockTensor) -> LoadfreeMessage<MockTensor> {
+        LoadfreeMessage::<MockTensor>::MockTensorMsg(tensor)
     }
 }
 
@@ -72,21 +149,311 @@ pub struct MockTensorMsg {
 
 impl MockTensorMsg {
     pub fn new(workload: MockTensor) -> Self {
-        return Self { workload: workload };
+        Self { workload }
     }
 }
 
 // TODO: More Ops to add; Other way to implement Opcode
 
+/// element types the cpu backend computes on
+pub trait CpuScalar:
+    Copy
+    + Debug
+    + Default
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + AddAssign
+{
+    fn exp(self) -> Self;
+    fn sin(self) -> Self;
+    // dims of a shape tensor, only non-negative integral values are valid
+    fn to_dim(self) -> Option<usize>;
+}
+
+macro_rules! impl_cpu_scalar {
+    ($t:ty) => {
+        impl CpuScalar for $t {
+            fn exp(self) -> Self {
+                <$t>::exp(self)
+            }
+
+            fn sin(self) -> Self {
+                <$t>::sin(self)
+            }
+
+            fn to_dim(self) -> Option<usize> {
+                if self >= 0.0 && self.fract() == 0.0 {
+                    Some(self as usize)
+                } else {
+                    None
+                }
+            }
+        }
+    };
+}
+
+impl_cpu_scalar!(f32);
+impl_cpu_scalar!(f64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DType {
+    F32,
+    F64,
+}
+
+impl DType {
+    /// bytes of an element
+    pub fn size_of(&self) -> usize {
+        match self {
+            DType::F32 => 4,
+            DType::F64 => 8,
+        }
+    }
+}
+
+/// dense row-major storage of a CpuTensor
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CpuData {
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+impl CpuData {
+    pub fn len(&self) -> usize {
+        match self {
+            CpuData::F32(x) => x.len(),
+            CpuData::F64(x) => x.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn dtype(&self) -> DType {
+        match self {
+            CpuData::F32(_) => DType::F32,
+            CpuData::F64(_) => DType::F64,
+        }
+    }
+}
+
+/// Definition: CpuTensor is a dense n-dimensional f32/f64 tensor computed by
+/// the CpuExecutor.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// let tensor = CpuTensor::from_f32(vec![2, 3], vec![1., 2., 3., 4., 5., 6.]).unwrap();
+/// assert_eq!(tensor.shape(), &[2, 3]);
+/// assert_eq!(tensor.dtype(), DType::F32);
+/// let flat = tensor.reshape(vec![6]).unwrap();
+/// assert_eq!(flat.as_f32(), Some(&[1., 2., 3., 4., 5., 6.][..]));
+/// assert!(CpuTensor::from_f64(vec![4], vec![1.]).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "CpuTensorParts")]
+pub struct CpuTensor {
+    shape: Vec<usize>,
+    data: CpuData,
+}
+
+// a deserialized tensor is checked like a new one
+#[derive(Deserialize)]
+struct CpuTensorParts {
+    shape: Vec<usize>,
+    data: CpuData,
+}
+
+impl TryFrom<CpuTensorParts> for CpuTensor {
+    type Error = RaptorsError;
+
+    fn try_from(parts: CpuTensorParts) -> Result<Self, Self::Error> {
+        CpuTensor::new(parts.shape, parts.data)
+    }
+}
+
+impl TensorLike for CpuTensor {
+    fn meta(&self) -> Option<TensorMeta> {
+        Some(TensorMeta::new(self.shape.clone(), self.dtype()))
+    }
+}
+
+impl Default for CpuTensor {
+    fn default() -> Self {
+        Self {
+            shape: vec![0],
+            data: CpuData::F32(vec![]),
+        }
+    }
+}
+
+impl CpuTensor {
+    pub fn new(shape: Vec<usize>, data: CpuData) -> Result<Self, RaptorsError> {
+        let numel: usize = shape.iter().product();
+        if numel != data.len() {
+            return Err(RaptorsError::tensor(format!(
+                "shape {:?} holds {} elements, got {}",
+                shape,
+                numel,
+                data.len()
+            )));
+        }
+        Ok(Self { shape, data })
+    }
+
+    pub fn from_f32(shape: Vec<usize>, data: Vec<f32>) -> Result<Self, RaptorsError> {
+        Self::new(shape, CpuData::F32(data))
+    }
+
+    pub fn from_f64(shape: Vec<usize>, data: Vec<f64>) -> Result<Self, RaptorsError> {
+        Self::new(shape, CpuData::F64(data))
+    }
+
+    pub fn zeros(shape: Vec<usize>, dtype: DType) -> Self {
+        let numel = shape.iter().product();
+        let data = match dtype {
+            DType::F32 => CpuData::F32(vec![0.; numel]),
+            DType::F64 => CpuData::F64(vec![0.; numel]),
+        };
+        Self { shape, data }
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn numel(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn dtype(&self) -> DType {
+        self.data.dtype()
+    }
+
+    pub fn data(&self) -> &CpuData {
+        &self.data
+    }
+
+    pub fn as_f32(&self) -> Option<&[f32]> {
+        match &self.data {
+            CpuData::F32(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<&[f64]> {
+        match &self.data {
+            CpuData::F64(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// same elements viewed with another shape
+    pub fn reshape(&self, shape: Vec<usize>) -> Result<Self, RaptorsError> {
+        Self::new(shape, self.data.clone())
+    }
+}
+
+// bounds of the chunks of `numel` elements split into `parts`
+fn chunk_bounds(numel: usize, parts: usize) -> Vec<(usize, usize)> {
+    let parts = parts.max(1);
+    let (base, extra) = (numel / parts, numel % parts);
+    let mut start = 0;
+    (0..parts)
+        .map(|part| {
+            let len = base + usize::from(part < extra);
+            start += len;
+            (start - len, start)
+        })
+        .collect()
+}
+
+impl Chunked for CpuTensor {
+    fn split(&self, parts: usize) -> Vec<Self> {
+        chunk_bounds(self.numel(), parts)
+            .into_iter()
+            .map(|(start, end)| {
+                let data = match &self.data {
+                    CpuData::F32(x) => CpuData::F32(x[start..end].to_vec()),
+                    CpuData::F64(x) => CpuData::F64(x[start..end].to_vec()),
+                };
+                Self {
+                    shape: vec![end - start],
+                    data,
+                }
+            })
+            .collect()
+    }
+
+    fn join(&mut self, chunks: &[Self]) -> Result<(), RaptorsError> {
+        let numel: usize = chunks.iter().map(|x| x.numel()).sum();
+        if numel != self.numel() {
+            return Err(RaptorsError::tensor(format!(
+                "chunks hold {} elements, the tensor {}",
+                numel,
+                self.numel()
+            )));
+        }
+        let data = match self.dtype() {
+            DType::F32 => CpuData::F32(
+                chunks
+                    .iter()
+                    .map(|x| x.as_f32().ok_or_else(|| dtype_mismatch(x)))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .concat(),
+            ),
+            DType::F64 => CpuData::F64(
+                chunks
+                    .iter()
+                    .map(|x| x.as_f64().ok_or_else(|| dtype_mismatch(x)))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .concat(),
+            ),
+        };
+        self.data = data;
+        Ok(())
+    }
+}
+
+fn dtype_mismatch(chunk: &CpuTensor) -> RaptorsError {
+    RaptorsError::tensor(format!("chunk of {:?} joined", chunk.dtype()))
+}
+
 // unit tests
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn split_and_join_cpu_tensor_test() {
+        let data: Vec<f64> = (0..7).map(f64::from).collect();
+        let tensor = CpuTensor::from_f64(vec![7], data.clone()).unwrap();
+        let chunks = tensor.split(3);
+        let lens: Vec<usize> = chunks.iter().map(|x| x.numel()).collect();
+        assert_eq!(lens, vec![3, 2, 2]);
+        // more parts than elements leave the last chunks empty
+        let lens: Vec<usize> = tensor.split(9).iter().map(|x| x.numel()).collect();
+        assert_eq!(lens, vec![1, 1, 1, 1, 1, 1, 1, 0, 0]);
+
+        let mut joined = CpuTensor::zeros(vec![7], DType::F64);
+        joined.join(&chunks).unwrap();
+        assert_eq!(joined, tensor);
+        assert!(joined.join(&chunks[1..]).is_err());
+        let f32_chunks = CpuTensor::zeros(vec![7], DType::F32).split(2);
+        assert!(joined.join(&f32_chunks).is_err());
+    }
+
     #[test]
     fn create_dummy_workload_test() {
         let load = MockTensor::new(MockOpCode::AddOp);
-        assert_eq!(load.payload(), 11 as usize);
+        assert_eq!(load.payload(&CostModel::new()), 11_usize);
+        let mut cost_model = CostModel::new();
+        cost_model.update_model(MockOpCode::AddOp, 3);
+        assert_eq!(load.payload(&cost_model), 3_usize);
         assert_eq!(load.op(), MockOpCode::AddOp);
     }
 
@@ -94,7 +461,10 @@ mod tests {
     fn worklaod_mock_run_test() {
         let load = MockTensor::new(MockOpCode::ConvOp);
         let now = time::Instant::now();
-        load.mock_run();
+        let cost_model = CostModel::new();
+        load.mock_run(&cost_model);
+        let payload = load.payload(&cost_model);
+        assert!(now.elapsed() >= time::Duration::from_millis((payload * 10) as u64));
         assert_eq!(load.op(), MockOpCode::ConvOp);
     }
 
@@ -116,6 +486,26 @@ mod tests {
         assert_eq!(load.op(), MockOpCode::ExpOp);
     }
 
+    #[test]
+    fn cpu_tensor_shape_test() {
+        let tensor = CpuTensor::zeros(vec![2, 2], DType::F64);
+        assert_eq!(tensor.numel(), 4);
+        assert_eq!(tensor.as_f64(), Some(&[0.; 4][..]));
+        assert_eq!(tensor.as_f32(), None);
+        assert_eq!(
+            tensor.reshape(vec![3]),
+            Err(RaptorsError::tensor("shape [3] holds 3 elements, got 4"))
+        );
+        assert_eq!(CpuTensor::default().numel(), 0);
+    }
+
+    #[test]
+    fn cpu_scalar_to_dim_test() {
+        assert_eq!(3.0_f32.to_dim(), Some(3));
+        assert_eq!(2.5_f64.to_dim(), None);
+        assert_eq!((-1.0_f64).to_dim(), None);
+    }
+
     #[test]
     fn workload_message_test() {
         let load = MockTensor::new(MockOpCode::ExpOp);
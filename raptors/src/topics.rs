@@ -0,0 +1,137 @@
+// LICENSE PLACEHOLDER
+use std::collections::{BTreeSet, HashMap};
+
+use crate::errors::RaptorsError;
+
+/// Definition: Topics keeps the ids of the actors subscribed to each named
+/// topic, the system fans a published msg out to these actors only.
+///
+/// A topic exists as long as it has subscribers, a halted actor is removed
+/// from all of its topics.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// let mut topics = Topics::new();
+/// topics.subscribe("params", 0);
+/// topics.subscribe("params", 2);
+/// topics.subscribe("grads", 2);
+/// assert_eq!(topics.subscribers("params"), vec![0, 2]);
+/// topics.remove_actor(2);
+/// assert_eq!(topics.subscribers("params"), vec![0]);
+/// assert!(!topics.contains("grads"));
+/// ```
+#[derive(Debug, Default)]
+pub struct Topics {
+    subscribers: HashMap<String, BTreeSet<usize>>,
+}
+
+impl Topics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// returns false if the actor is subscribed to the topic already
+    pub fn subscribe(&mut self, topic: &str, id: usize) -> bool {
+        self.subscribers
+            .entry(topic.to_string())
+            .or_default()
+            .insert(id)
+    }
+
+    pub fn unsubscribe(&mut self, topic: &str, id: usize) -> Result<(), RaptorsError> {
+        let removed = match self.subscribers.get_mut(topic) {
+            Some(ids) => ids.remove(&id),
+            None => false,
+        };
+        if !removed {
+            return Err(RaptorsError::system(format!(
+                "actor #{} is not subscribed to topic {}",
+                id, topic
+            )));
+        }
+        self.subscribers.retain(|_, ids| !ids.is_empty());
+        Ok(())
+    }
+
+    /// unsubscribe the actor from every topic, returns how many it was on
+    pub fn remove_actor(&mut self, id: usize) -> usize {
+        let mut cnt = 0;
+        for ids in self.subscribers.values_mut() {
+            if ids.remove(&id) {
+                cnt += 1;
+            }
+        }
+        self.subscribers.retain(|_, ids| !ids.is_empty());
+        cnt
+    }
+
+    pub fn clear(&mut self) {
+        self.subscribers.clear();
+    }
+
+    pub fn contains(&self, topic: &str) -> bool {
+        self.subscribers.contains_key(topic)
+    }
+
+    /// ids of the subscribers of the topic in ascending order
+    pub fn subscribers(&self, topic: &str) -> Vec<usize> {
+        self.subscribers
+            .get(topic)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// names of the topics the actor is subscribed to
+    pub fn topics_of(&self, id: usize) -> Vec<String> {
+        let mut topics: Vec<String> = self
+            .subscribers
+            .iter()
+            .filter(|(_, ids)| ids.contains(&id))
+            .map(|(topic, _)| topic.clone())
+            .collect();
+        topics.sort();
+        topics
+    }
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topics_subscribe_and_unsubscribe_test() {
+        let mut topics = Topics::new();
+        assert!(topics.subscribe("params", 1));
+        assert!(!topics.subscribe("params", 1));
+        assert!(topics.subscribe("params", 0));
+        assert!(topics.subscribe("grads", 1));
+        assert_eq!(topics.subscribers("params"), vec![0, 1]);
+        assert_eq!(topics.topics_of(1), vec!["grads", "params"]);
+
+        assert!(topics.unsubscribe("params", 1).is_ok());
+        assert!(topics.unsubscribe("params", 1).is_err());
+        assert!(topics.unsubscribe("absent", 0).is_err());
+        assert_eq!(topics.subscribers("params"), vec![0]);
+
+        // emptied topics are dropped
+        assert!(topics.unsubscribe("grads", 1).is_ok());
+        assert!(!topics.contains("grads"));
+        assert!(topics.subscribers("grads").is_empty());
+    }
+
+    #[test]
+    fn topics_forget_removed_actors_test() {
+        let mut topics = Topics::new();
+        topics.subscribe("params", 0);
+        topics.subscribe("params", 1);
+        topics.subscribe("grads", 1);
+        assert_eq!(topics.remove_actor(1), 2);
+        assert_eq!(topics.remove_actor(1), 0);
+        assert_eq!(topics.subscribers("params"), vec![0]);
+        assert!(topics.topics_of(1).is_empty());
+        topics.clear();
+        assert!(!topics.contains("params"));
+    }
+}
@@ -0,0 +1,564 @@
+// LICENSE PLACEHOLDER
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info};
+
+use crate::actor_ref::{send_order, wait_reply};
+use crate::cost_model::OpCodeLike;
+use crate::errors::RaptorsError;
+use crate::executor_registry::ExecutorRegistry;
+use crate::executor_types::ExecutorLike;
+use crate::messages::{LoadfreeMessage, PayloadMessage, RaptorMessage, SystemCommand};
+use crate::tensor_types::TensorLike;
+use crate::wire::{self, WireKind, WireTensor};
+
+// frames larger than this are refused, a corrupted length must not make the
+// reader allocate without bound
+const MAX_FRAME_LEN: usize = 64 << 20;
+
+/// Definition: Frame is what a peer system and the system it is connected to
+/// exchange over a socket, each frame is sent as a big-endian u32 length
+/// followed by the frame in the wire format; peers of another wire version
+/// are dropped on their first frame.
+///
+/// Payload messages travel in their serializable form: the tensors
+/// themselves instead of shared references, and a request id instead of the
+/// reply channel.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Frame<U, O>
+where
+    U: TensorLike + Clone,
+{
+    // orders and workloads without payload, as issued to a local system
+    Loadfree(LoadfreeMessage<U>),
+    // spawn actors of the executor kind registered under the name
+    Spawn {
+        request: u64,
+        kind: String,
+        cnt: usize,
+    },
+    // compute the op on one operand for unary ops or two for binary ones, on
+    // the actor of the id if given
+    Compute {
+        request: u64,
+        actor: Option<usize>,
+        op: O,
+        operands: Vec<U>,
+    },
+    // result of a request, spawns reply without a tensor
+    Reply {
+        request: u64,
+        result: Result<Option<U>, RaptorsError>,
+    },
+}
+
+pub(crate) async fn write_frame<W, F>(writer: &mut W, frame: &F) -> Result<(), RaptorsError>
+where
+    W: AsyncWrite + Unpin,
+    F: Serialize,
+{
+    let bytes = wire::encode(WireKind::Frame, frame)?;
+    if bytes.len() > MAX_FRAME_LEN {
+        return Err(RaptorsError::transport(format!(
+            "frame of {} bytes is too large",
+            bytes.len()
+        )));
+    }
+    let io_error = |e: std::io::Error| RaptorsError::transport(e.to_string());
+    writer
+        .write_u32(bytes.len() as u32)
+        .await
+        .map_err(io_error)?;
+    writer.write_all(&bytes).await.map_err(io_error)?;
+    writer.flush().await.map_err(io_error)
+}
+
+/// next frame of the stream, None once the peer closed it between frames
+pub(crate) async fn read_frame<R, F>(reader: &mut R) -> Result<Option<F>, RaptorsError>
+where
+    R: AsyncRead + Unpin,
+    F: DeserializeOwned,
+{
+    let len = match reader.read_u32().await {
+        Ok(len) => len as usize,
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(RaptorsError::transport(e.to_string())),
+    };
+    if len > MAX_FRAME_LEN {
+        return Err(RaptorsError::transport(format!(
+            "frame of {} bytes is too large",
+            len
+        )));
+    }
+    let mut bytes = vec![0; len];
+    reader
+        .read_exact(&mut bytes)
+        .await
+        .map_err(|e| RaptorsError::transport(format!("truncated frame: {}", e)))?;
+    wire::decode(WireKind::Frame, &bytes).map(Some)
+}
+
+/// accept peers on the listener and hand their frames over to the system
+/// behind the sendbox, until the task is aborted
+pub(crate) async fn serve<T, U, O>(
+    listener: TcpListener,
+    sendbox: mpsc::Sender<RaptorMessage<U, O>>,
+    registry: Arc<ExecutorRegistry<T>>,
+) where
+    T: 'static + ExecutorLike + Send + Sync,
+    U: 'static + WireTensor + Clone + Send + Sync + Debug,
+    O: 'static + OpCodeLike + Debug + Send + Sync + Serialize + DeserializeOwned,
+{
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                info!("::transport::accept peer {}", peer);
+                tokio::spawn(serve_peer(stream, sendbox.clone(), registry.clone()));
+            }
+            Err(e) => info!("::transport::fail to accept peer - {}", e),
+        }
+    }
+}
+
+async fn serve_peer<T, U, O>(
+    stream: TcpStream,
+    sendbox: mpsc::Sender<RaptorMessage<U, O>>,
+    registry: Arc<ExecutorRegistry<T>>,
+) where
+    T: 'static + ExecutorLike + Send + Sync,
+    U: 'static + WireTensor + Clone + Send + Sync + Debug,
+    O: 'static + OpCodeLike + Debug + Send + Sync + Serialize + DeserializeOwned,
+{
+    let (mut reader, mut writer) = stream.into_split();
+    // replies of concurrent workloads go out through a single writer
+    let (replies, mut outbox) = mpsc::channel::<Frame<U, O>>(64);
+    let writing = tokio::spawn(async move {
+        while let Some(frame) = outbox.recv().await {
+            if let Err(e) = write_frame(&mut writer, &frame).await {
+                info!("::transport::fail to reply - {}", e);
+                break;
+            }
+        }
+    });
+    loop {
+        let frame = match read_frame(&mut reader).await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break,
+            Err(e) => {
+                info!("::transport::drop peer - {}", e);
+                break;
+            }
+        };
+        match frame {
+            Frame::Loadfree(msg) => send_order(&sendbox, RaptorMessage::LoadfreeMSG(msg)).await,
+            Frame::Spawn { request, kind, cnt } => {
+                let result = match registry.typeid_of(&kind) {
+                    Some(typeid) => {
+                        let cmd = SystemCommand::Spawn(typeid, cnt);
+                        send_order(&sendbox, RaptorMessage::LoadfreeMSG(cmd.into())).await;
+                        Ok(None)
+                    }
+                    None => Err(RaptorsError::system(format!(
+                        "unknown executor kind {}",
+                        kind
+                    ))),
+                };
+                let _ = replies.send(Frame::Reply { request, result }).await;
+            }
+            Frame::Compute {
+                request,
+                actor,
+                op,
+                operands,
+            } => {
+                let (respond_to, reply) = oneshot::channel();
+                let msg = match into_payload(op, operands, respond_to) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        let result = Err(e);
+                        let _ = replies.send(Frame::Reply { request, result }).await;
+                        continue;
+                    }
+                };
+                let msg = match actor {
+                    Some(id) => RaptorMessage::PinnedMSG(id, msg),
+                    None => RaptorMessage::PayloadMSG(msg),
+                };
+                send_order(&sendbox, msg).await;
+                let replies = replies.clone();
+                tokio::spawn(async move {
+                    let result = wait_reply(reply, None).await.map(Some);
+                    let _ = replies.send(Frame::Reply { request, result }).await;
+                });
+            }
+            Frame::Reply { request, .. } => {
+                info!("::transport::unexpected reply to request #{}", request)
+            }
+        }
+    }
+    // the writer exits once the replies still pending are sent
+    drop(replies);
+    let _ = writing.await;
+}
+
+fn into_payload<U, O>(
+    op: O,
+    mut operands: Vec<U>,
+    respond_to: oneshot::Sender<Result<U, RaptorsError>>,
+) -> Result<PayloadMessage<U, O>, RaptorsError>
+where
+    U: TensorLike + Clone,
+    O: OpCodeLike,
+{
+    match operands.len() {
+        1 => Ok(PayloadMessage::UnaryComputeFunctorMsg {
+            op,
+            inp: Arc::new(RwLock::new(operands.remove(0))),
+            respond_to,
+        }),
+        2 => {
+            let rhs = operands.remove(1);
+            Ok(PayloadMessage::ComputeFunctorMsg {
+                op,
+                lhs: Arc::new(RwLock::new(operands.remove(0))),
+                rhs: Arc::new(RwLock::new(rhs)),
+                respond_to,
+            })
+        }
+        cnt => Err(RaptorsError::transport(format!(
+            "compute takes 1 or 2 operands, got {}",
+            cnt
+        ))),
+    }
+}
+
+type PendingReplies<U> = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Option<U>, RaptorsError>>>>>;
+
+/// Definition: RemoteSystem is the connection of a peer to an actor system
+/// listening on a socket, actors are spawned and workloads are computed on
+/// the remote system as they are on a local handle.
+///
+/// ```
+/// use raptors::prelude::*;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut system = build_mock_system!("Raptors", 1).unwrap();
+///     let addr = system.listen("127.0.0.1:0").await.unwrap();
+///
+///     let mut remote = RemoteSystem::<MockTensor, MockOpCode>::connect(addr)
+///         .await
+///         .unwrap();
+///     remote.spawn("mock", 1).await.unwrap();
+///     let out = remote
+///         .ask_unary(MockOpCode::ExpOp, MockTensor::new(MockOpCode::ExpOp))
+///         .await;
+///     assert!(out.is_ok());
+///     system.shutdown().await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RemoteSystem<U, O>
+where
+    U: TensorLike + Clone,
+{
+    peer: SocketAddr,
+    outbox: mpsc::Sender<Frame<U, O>>,
+    pending: PendingReplies<U>,
+    next_request: AtomicU64,
+    // how long asks wait for the result, forever if None
+    ask_timeout: Option<Duration>,
+}
+
+impl<U, O> RemoteSystem<U, O>
+where
+    U: 'static + WireTensor + Clone + Send + Sync + Debug,
+    O: 'static + OpCodeLike + Debug + Send + Sync + Serialize + DeserializeOwned,
+{
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self, RaptorsError> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| RaptorsError::transport(format!("fail to connect: {}", e)))?;
+        let peer = stream
+            .peer_addr()
+            .map_err(|e| RaptorsError::transport(e.to_string()))?;
+        let (mut reader, mut writer) = stream.into_split();
+
+        let (outbox, mut frames) = mpsc::channel::<Frame<U, O>>(64);
+        tokio::spawn(async move {
+            while let Some(frame) = frames.recv().await {
+                if let Err(e) = write_frame(&mut writer, &frame).await {
+                    info!("::remote-system::fail to send frame - {}", e);
+                    break;
+                }
+            }
+        });
+        let pending: PendingReplies<U> = Arc::new(Mutex::new(HashMap::new()));
+        let replies = pending.clone();
+        tokio::spawn(async move {
+            loop {
+                match read_frame::<_, Frame<U, O>>(&mut reader).await {
+                    Ok(Some(Frame::Reply { request, result })) => {
+                        let waiter = replies
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .remove(&request);
+                        if let Some(waiter) = waiter {
+                            let _ = waiter.send(result);
+                        }
+                    }
+                    Ok(Some(frame)) => info!("::remote-system::unexpected frame {:?}", frame),
+                    Ok(None) => break,
+                    Err(e) => {
+                        info!("::remote-system::connection lost - {}", e);
+                        break;
+                    }
+                }
+            }
+            // the requests still waiting are never answered
+            replies
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clear();
+        });
+        Ok(Self {
+            peer,
+            outbox,
+            pending,
+            next_request: AtomicU64::new(0),
+            ask_timeout: None,
+        })
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    pub fn set_ask_timeout(&mut self, timeout: Option<Duration>) {
+        self.ask_timeout = timeout;
+    }
+
+    /// issue the msg to the remote system as if issued by its handle
+    pub async fn issue_order(&mut self, msg: LoadfreeMessage<U>) -> Result<(), RaptorsError> {
+        self.send(Frame::Loadfree(msg)).await
+    }
+
+    /// spawn `cnt` actors of the executor kind registered on the remote system
+    pub async fn spawn(&mut self, executor_kind: &str, cnt: usize) -> Result<(), RaptorsError> {
+        let (request, reply) = self.new_request();
+        let frame = Frame::Spawn {
+            request,
+            kind: executor_kind.to_string(),
+            cnt,
+        };
+        self.send(frame).await?;
+        wait_reply(reply, self.ask_timeout).await.map(|_| ())
+    }
+
+    /// compute the op on the tensor with any remote actor and wait for the result
+    pub async fn ask_unary(&mut self, op: O, inp: U) -> Result<U, RaptorsError> {
+        self.ask(None, op, vec![inp]).await
+    }
+
+    /// compute the op on the two tensors with any remote actor and wait for the result
+    pub async fn ask_binary(&mut self, op: O, lhs: U, rhs: U) -> Result<U, RaptorsError> {
+        self.ask(None, op, vec![lhs, rhs]).await
+    }
+
+    /// as `ask_unary`, computed by the remote actor of the id
+    pub async fn ask_unary_on(&mut self, actor: usize, op: O, inp: U) -> Result<U, RaptorsError> {
+        self.ask(Some(actor), op, vec![inp]).await
+    }
+
+    /// as `ask_binary`, computed by the remote actor of the id
+    pub async fn ask_binary_on(
+        &mut self,
+        actor: usize,
+        op: O,
+        lhs: U,
+        rhs: U,
+    ) -> Result<U, RaptorsError> {
+        self.ask(Some(actor), op, vec![lhs, rhs]).await
+    }
+
+    async fn ask(
+        &mut self,
+        actor: Option<usize>,
+        op: O,
+        operands: Vec<U>,
+    ) -> Result<U, RaptorsError> {
+        let (request, reply) = self.new_request();
+        let frame = Frame::Compute {
+            request,
+            actor,
+            op,
+            operands,
+        };
+        self.send(frame).await?;
+        wait_reply(reply, self.ask_timeout)
+            .await?
+            .ok_or_else(|| RaptorsError::transport("compute replied without a result"))
+    }
+
+    fn new_request(&self) -> (u64, oneshot::Receiver<Result<Option<U>, RaptorsError>>) {
+        let request = self.next_request.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(request, tx);
+        (request, rx)
+    }
+
+    async fn send(&self, frame: Frame<U, O>) -> Result<(), RaptorsError> {
+        debug!("::remote-system::send frame to {}", self.peer);
+        self.outbox
+            .send(frame)
+            .await
+            .map_err(|_| RaptorsError::transport(format!("connection to {} is closed", self.peer)))
+    }
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn frame_round_trip_test() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let frame: Frame<CpuTensor, MockOpCode> = Frame::Compute {
+            request: 7,
+            actor: Some(1),
+            op: MockOpCode::MatmulOp,
+            operands: vec![CpuTensor::from_f64(vec![1, 2], vec![0.5, -1.5]).unwrap()],
+        };
+        write_frame(&mut client, &frame).await.unwrap();
+        write_frame(
+            &mut client,
+            &Frame::<CpuTensor, MockOpCode>::Loadfree(build_loadfree_msg!("halt", 3)),
+        )
+        .await
+        .unwrap();
+        drop(client);
+
+        match read_frame::<_, Frame<CpuTensor, MockOpCode>>(&mut server).await {
+            Ok(Some(Frame::Compute {
+                request: 7,
+                actor: Some(1),
+                op: MockOpCode::MatmulOp,
+                operands,
+            })) => assert_eq!(operands[0].as_f64(), Some(&[0.5, -1.5][..])),
+            others => panic!("expect the compute frame, got {:?}", others),
+        }
+        assert!(matches!(
+            read_frame::<_, Frame<CpuTensor, MockOpCode>>(&mut server).await,
+            Ok(Some(Frame::Loadfree(msg))) if msg == build_loadfree_msg!("halt", 3)
+        ));
+        assert!(matches!(
+            read_frame::<_, Frame<CpuTensor, MockOpCode>>(&mut server).await,
+            Ok(None)
+        ));
+    }
+
+    #[tokio::test]
+    async fn frame_rejects_malformed_input_test() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client.write_u32(u32::MAX).await.unwrap();
+        let frame = read_frame::<_, Frame<MockTensor, MockOpCode>>(&mut server).await;
+        assert!(matches!(frame, Err(RaptorsError::Transport { .. })));
+
+        // so is a frame of a message in place of a frame
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let msg: LoadfreeMessage<MockTensor> = build_loadfree_msg!("halt-all");
+        let bytes = msg.to_wire().unwrap();
+        client.write_u32(bytes.len() as u32).await.unwrap();
+        client.write_all(&bytes).await.unwrap();
+        let frame = read_frame::<_, Frame<MockTensor, MockOpCode>>(&mut server).await;
+        assert!(matches!(frame, Err(RaptorsError::Transport { .. })));
+
+        // a tensor whose data does not match its shape is refused
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let bytes =
+            wire::encode(WireKind::Frame, &(vec![3_usize], CpuData::F32(vec![1.0]))).unwrap();
+        client.write_u32(bytes.len() as u32).await.unwrap();
+        client.write_all(&bytes).await.unwrap();
+        let tensor = read_frame::<_, CpuTensor>(&mut server).await;
+        assert!(matches!(tensor, Err(RaptorsError::Transport { .. })));
+    }
+
+    #[tokio::test]
+    async fn remote_system_on_localhost_test() {
+        let mut registry = ExecutorRegistry::<CpuExecutor>::new();
+        registry.register(3, "cpu", CpuExecutor::new).unwrap();
+        let mut sys_config = SystemConfig::new("server", "info");
+        sys_config.set_executor_typeid(3);
+        let mut server = SystemBuilder::new()
+            .build_with_registry(sys_config, registry)
+            .unwrap();
+        let addr = server.listen("127.0.0.1:0").await.unwrap();
+
+        let mut sys_config = SystemConfig::new("peer", "info");
+        sys_config.set_ranks(1);
+        let peer = SystemBuilder::new()
+            .build_with_config::<CpuExecutor, CpuTensor, MockOpCode>(sys_config)
+            .unwrap();
+        let mut remote = peer.connect(addr).await.unwrap();
+        assert_eq!(remote.peer_addr(), addr);
+
+        assert!(remote.spawn("gpu", 1).await.is_err());
+        remote.spawn("cpu", 2).await.unwrap();
+        let lhs = CpuTensor::from_f64(vec![2], vec![1.0, 2.0]).unwrap();
+        let rhs = CpuTensor::from_f64(vec![2], vec![0.5, 0.5]).unwrap();
+        let out = remote
+            .ask_binary(MockOpCode::AddOp, lhs.clone(), rhs)
+            .await
+            .unwrap();
+        assert_eq!(out.as_f64(), Some(&[1.5, 2.5][..]));
+        let out = remote
+            .ask_unary_on(1, MockOpCode::ExpOp, lhs.clone())
+            .await
+            .unwrap();
+        assert_eq!(out.as_f64(), Some(&[1.0_f64.exp(), 2.0_f64.exp()][..]));
+
+        // failures come back with the remote actor that computed them
+        let rhs = CpuTensor::from_f32(vec![2], vec![0.5, 0.5]).unwrap();
+        let err = remote.ask_binary_on(1, MockOpCode::AddOp, lhs, rhs).await;
+        assert!(matches!(
+            err,
+            Err(RaptorsError::Executor { actor_id: 1, .. })
+        ));
+
+        remote
+            .issue_order(build_loadfree_msg!("halt", 0))
+            .await
+            .unwrap();
+        for _ in 0..100 {
+            if server.ranks() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(server.ranks(), 1);
+
+        let summary = server.shutdown().await.unwrap();
+        assert_eq!(summary.tasks_completed, 3);
+        assert!(remote
+            .ask_unary(MockOpCode::ExpOp, CpuTensor::default())
+            .await
+            .is_err());
+        peer.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+// LICENSE PLACEHOLDER
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::errors::RaptorsError;
+use crate::messages::LoadfreeMessage;
+use crate::tensor_types::{CpuTensor, MockTensor, TensorLike};
+
+/// first bytes of every encoded value
+pub const WIRE_MAGIC: [u8; 4] = *b"RPTR";
+
+/// version of the wire format, bumped on any change of the encoding of an
+/// existing message; new enum variants are appended so that older payloads
+/// keep decoding the same
+pub const WIRE_VERSION: u16 = 1;
+
+// magic, version and kind
+const HEADER_LEN: usize = 7;
+
+/// what an encoded value holds, decoding checks it against the expected kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WireKind {
+    Message,
+    Tensor,
+    Frame,
+}
+
+impl WireKind {
+    fn tag(self) -> u8 {
+        match self {
+            WireKind::Message => 0,
+            WireKind::Tensor => 1,
+            WireKind::Frame => 2,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(WireKind::Message),
+            1 => Some(WireKind::Tensor),
+            2 => Some(WireKind::Frame),
+            _ => None,
+        }
+    }
+}
+
+/// Definition: encode the value in the wire format of raptors, a header of the
+/// magic bytes, the little-endian u16 version and the kind, then the bincode
+/// encoding of the value.
+///
+/// ```
+/// use raptors::prelude::*;
+/// use raptors::wire::{self, WireKind};
+///
+/// let msg: LoadfreeMessage<MockTensor> = build_loadfree_msg!("halt", 3);
+/// let bytes = wire::encode(WireKind::Message, &msg).unwrap();
+/// assert_eq!(&bytes[..4], b"RPTR");
+/// let back: LoadfreeMessage<MockTensor> = wire::decode(WireKind::Message, &bytes).unwrap();
+/// assert_eq!(back, msg);
+/// ```
+pub fn encode<V: Serialize>(kind: WireKind, value: &V) -> Result<Vec<u8>, RaptorsError> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.extend_from_slice(&WIRE_MAGIC);
+    bytes.extend_from_slice(&WIRE_VERSION.to_le_bytes());
+    bytes.push(kind.tag());
+    bincode::serialize_into(&mut bytes, value)
+        .map_err(|e| RaptorsError::transport(format!("fail to encode {:?}: {}", kind, e)))?;
+    Ok(bytes)
+}
+
+/// decode a value encoded by `encode`, payloads of another version or kind
+/// are refused
+pub fn decode<V: DeserializeOwned>(kind: WireKind, bytes: &[u8]) -> Result<V, RaptorsError> {
+    if bytes.len() < HEADER_LEN || bytes[..4] != WIRE_MAGIC {
+        return Err(RaptorsError::transport("not a raptors wire payload"));
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != WIRE_VERSION {
+        return Err(RaptorsError::transport(format!(
+            "unsupported wire version {}, expect {}",
+            version, WIRE_VERSION
+        )));
+    }
+    match WireKind::from_tag(bytes[6]) {
+        Some(found) if found == kind => (),
+        found => {
+            return Err(RaptorsError::transport(format!(
+                "expect a {:?} payload, got {:?}",
+                kind, found
+            )))
+        }
+    }
+    bincode::deserialize(&bytes[HEADER_LEN..])
+        .map_err(|e| RaptorsError::transport(format!("fail to decode {:?}: {}", kind, e)))
+}
+
+/// serialization extension of TensorLike, tensors implementing it travel as
+/// payloads of remote workloads and can be written to logs and checkpoints
+pub trait WireTensor: TensorLike + Serialize + DeserializeOwned {
+    fn to_wire(&self) -> Result<Vec<u8>, RaptorsError> {
+        encode(WireKind::Tensor, self)
+    }
+
+    fn from_wire(bytes: &[u8]) -> Result<Self, RaptorsError> {
+        decode(WireKind::Tensor, bytes)
+    }
+}
+
+impl WireTensor for MockTensor {}
+
+// decoding checks the data against the shape
+impl WireTensor for CpuTensor {}
+
+impl<T> LoadfreeMessage<T>
+where
+    T: WireTensor + Clone,
+{
+    pub fn to_wire(&self) -> Result<Vec<u8>, RaptorsError> {
+        encode(WireKind::Message, self)
+    }
+
+    pub fn from_wire(bytes: &[u8]) -> Result<Self, RaptorsError> {
+        decode(WireKind::Message, bytes)
+    }
+}
+
+// unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use uuid::Uuid;
+
+    fn round_trip<T: WireTensor + Clone + PartialEq + std::fmt::Debug>(msg: LoadfreeMessage<T>) {
+        let bytes = msg.to_wire().unwrap();
+        assert_eq!(LoadfreeMessage::<T>::from_wire(&bytes), Ok(msg));
+    }
+
+    #[test]
+    fn loadfree_messages_round_trip_test() {
+        let cmds = vec![
+            SystemCommand::HaltAll,
+            SystemCommand::HaltOn(3),
+            SystemCommand::Halt(Address::new(Uuid::new_v4())),
+            SystemCommand::Spawn(1, 4),
+            SystemCommand::SpawnNamed(0, 2, "resident".to_string()),
+            SystemCommand::Subscribe("params".to_string(), 1),
+            SystemCommand::Unsubscribe("params".to_string(), 1),
+            SystemCommand::Shutdown,
+        ];
+        for cmd in cmds {
+            round_trip::<MockTensor>(cmd.into());
+        }
+        for cmd in [
+            ActorCommand::Available(1, 0),
+            ActorCommand::Stopped(2, 1),
+            ActorCommand::Panicked(3, 2),
+            ActorCommand::PLACEHOLDER,
+        ] {
+            round_trip::<MockTensor>(cmd.into());
+        }
+        round_trip(build_loadfree_msg!("sin-op"));
+        round_trip(LoadfreeMessage::MockTensorMsg(
+            CpuTensor::from_f64(vec![2, 1], vec![0.25, -4.0]).unwrap(),
+        ));
+    }
+
+    #[test]
+    fn tensors_round_trip_test() {
+        let tensor = MockTensor::new(MockOpCode::ConvOp);
+        assert_eq!(
+            MockTensor::from_wire(&tensor.to_wire().unwrap()),
+            Ok(tensor)
+        );
+        for tensor in [
+            CpuTensor::from_f32(vec![2, 2], vec![1.0, f32::MIN, f32::MAX, -0.0]).unwrap(),
+            CpuTensor::zeros(vec![3, 0], DType::F64),
+            CpuTensor::default(),
+        ] {
+            assert_eq!(CpuTensor::from_wire(&tensor.to_wire().unwrap()), Ok(tensor));
+        }
+    }
+
+    #[test]
+    fn wire_format_is_stable_test() {
+        let msg: LoadfreeMessage<MockTensor> = build_loadfree_msg!("halt", 3);
+        let mut expected = b"RPTR".to_vec();
+        // version 1, kind message
+        expected.extend_from_slice(&[1, 0, 0]);
+        // variant SystemMsg, variant HaltOn, id as u64
+        expected.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0]);
+        expected.extend_from_slice(&3_u64.to_le_bytes());
+        assert_eq!(msg.to_wire().unwrap(), expected);
+    }
+
+    #[test]
+    fn wire_refuses_foreign_payloads_test() {
+        let tensor = CpuTensor::from_f32(vec![2], vec![1.0, 2.0]).unwrap();
+        let bytes = tensor.to_wire().unwrap();
+        assert!(LoadfreeMessage::<CpuTensor>::from_wire(&bytes).is_err());
+        assert!(CpuTensor::from_wire(&bytes[..HEADER_LEN + 3]).is_err());
+        assert!(CpuTensor::from_wire(b"RPT").is_err());
+
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        assert_eq!(
+            CpuTensor::from_wire(&newer),
+            Err(RaptorsError::transport(
+                "unsupported wire version 2, expect 1"
+            ))
+        );
+        let mut unknown = bytes;
+        unknown[6] = 9;
+        assert!(CpuTensor::from_wire(&unknown).is_err());
+    }
+}